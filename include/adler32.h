@@ -0,0 +1,56 @@
+/*
+ * C interface to the adler32 golden model, built as a cdylib with
+ * `cargo build --release` (target/release/libadler32.so).
+ *
+ * From SystemVerilog:
+ *
+ *   import "DPI-C" function chandle adler32_init();
+ *   import "DPI-C" function void adler32_update_byte(chandle state, byte unsigned b);
+ *   import "DPI-C" function int unsigned adler32_final(chandle state);
+ *   import "DPI-C" function void adler32_reset(chandle state);
+ *   import "DPI-C" function void adler32_free(chandle state);
+ *   import "DPI-C" function chandle adler32_format_new(string spec);
+ *   import "DPI-C" function void adler32_format_free(chandle format);
+ *   import "DPI-C" function int adler32_parse_line(chandle format, string line,
+ *       output bit length_valid, output longint unsigned length,
+ *       output bit data_valid, output longint unsigned data);
+ */
+
+#ifndef ADLER32_H
+#define ADLER32_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Adler32 Adler32;
+typedef struct LineFormat LineFormat;
+
+/* Running checksum */
+Adler32 *adler32_init(void);
+void adler32_update(Adler32 *state, const uint8_t *data, size_t len);
+void adler32_update_byte(Adler32 *state, uint8_t byte);
+uint32_t adler32_final(const Adler32 *state);
+void adler32_reset(Adler32 *state);
+void adler32_free(Adler32 *state);
+
+/* One shot checksum */
+uint32_t adler32_checksum(const uint8_t *data, size_t len);
+
+/* Stimulus parsing. A null spec or format means the default
+ * {lv}_{len:b32}_{dv}_{data:b8}. adler32_format_new returns null for an
+ * invalid spec, adler32_parse_line returns -1 for a line that doesn't parse. */
+LineFormat *adler32_format_new(const char *spec);
+void adler32_format_free(LineFormat *format);
+int adler32_parse_line(const LineFormat *format, const char *line,
+                       uint8_t *length_valid, uint64_t *length,
+                       uint8_t *data_valid, uint64_t *data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
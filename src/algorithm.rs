@@ -0,0 +1,195 @@
+//! The checksums the golden model can compute, so one stimulus format serves
+//! DUTs computing different ones.
+
+use core::fmt::{self, Display};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{Adler32, Crc32, Fletcher16, Fletcher32, Fletcher64};
+
+/// A checksum a DUT computes over each packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    #[default]
+    Adler32,
+    Crc32,
+    Fletcher16,
+    Fletcher32,
+    Fletcher64,
+}
+
+impl Algorithm {
+    pub const ALL: [Self; 5] = [
+        Self::Adler32,
+        Self::Crc32,
+        Self::Fletcher16,
+        Self::Fletcher32,
+        Self::Fletcher64,
+    ];
+
+    /// Name as written in headers, e.g. `crc32`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Adler32 => "adler32",
+            Self::Crc32 => "crc32",
+            Self::Fletcher16 => "fletcher16",
+            Self::Fletcher32 => "fletcher32",
+            Self::Fletcher64 => "fletcher64",
+        }
+    }
+
+    /// Name as written in prose, e.g. `CRC-32`
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Adler32 => "Adler-32",
+            Self::Crc32 => "CRC-32",
+            Self::Fletcher16 => "Fletcher-16",
+            Self::Fletcher32 => "Fletcher-32",
+            Self::Fletcher64 => "Fletcher-64",
+        }
+    }
+
+    /// Width of its checksums as written. Fletcher-16's are written as 32
+    /// bits like the others, so only Fletcher-64's are wider.
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::Fletcher64 => 64,
+            _ => 32,
+        }
+    }
+
+    /// `checksum` as a verilog literal as wide as [`bits`](Self::bits).
+    ///
+    /// ```
+    /// use adler32::Algorithm;
+    ///
+    /// assert_eq!(Algorithm::Adler32.literal(0x11e6_0398).to_string(), "32'h11e60398");
+    /// assert_eq!(Algorithm::Fletcher64.literal(1).to_string(), "64'h0000000000000001");
+    /// ```
+    pub fn literal(self, checksum: u64) -> impl Display {
+        Literal {
+            bits: self.bits(),
+            checksum,
+        }
+    }
+
+    /// The algorithm [`name`](Self::name) gives `name` to.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+
+    /// Checksum of a complete buffer.
+    ///
+    /// ```
+    /// use adler32::Algorithm;
+    ///
+    /// assert_eq!(Algorithm::Adler32.checksum(b"Wikipedia"), 0x11e6_0398);
+    /// assert_eq!(Algorithm::Crc32.checksum(b"123456789"), 0xcbf4_3926);
+    /// assert_eq!(Algorithm::Fletcher16.checksum(b"abcde"), 0xc8f0);
+    /// assert_eq!(Algorithm::Fletcher64.checksum(b"abcde"), 0xc8c6_c527_6463_62c6);
+    /// ```
+    pub fn checksum(self, data: &[u8]) -> u64 {
+        let mut checksum = Checksum::new(self);
+        checksum.update(data);
+        checksum.finalize()
+    }
+}
+
+/// `bits'h` and the checksum in as many hex digits as it takes
+struct Literal {
+    bits: u32,
+    checksum: u64,
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.bits as usize / 4;
+        write!(f, "{}'h{:0>digits$x}", self.bits, self.checksum)
+    }
+}
+
+/// Incremental hasher for any [`Algorithm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    Adler32(Adler32),
+    Crc32(Crc32),
+    Fletcher16(Fletcher16),
+    Fletcher32(Fletcher32),
+    Fletcher64(Fletcher64),
+}
+
+impl Checksum {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Adler32 => Self::Adler32(Adler32::new()),
+            Algorithm::Crc32 => Self::Crc32(Crc32::new()),
+            Algorithm::Fletcher16 => Self::Fletcher16(Fletcher16::new()),
+            Algorithm::Fletcher32 => Self::Fletcher32(Fletcher32::new()),
+            Algorithm::Fletcher64 => Self::Fletcher64(Fletcher64::new()),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::Adler32(_) => Algorithm::Adler32,
+            Self::Crc32(_) => Algorithm::Crc32,
+            Self::Fletcher16(_) => Algorithm::Fletcher16,
+            Self::Fletcher32(_) => Algorithm::Fletcher32,
+            Self::Fletcher64(_) => Algorithm::Fletcher64,
+        }
+    }
+
+    /// Add `data` to the running checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Adler32(hasher) => hasher.update(data),
+            Self::Crc32(hasher) => hasher.update(data),
+            Self::Fletcher16(hasher) => hasher.update(data),
+            Self::Fletcher32(hasher) => hasher.update(data),
+            Self::Fletcher64(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Checksum of everything added since the last reset.
+    pub fn finalize(&self) -> u64 {
+        match self {
+            Self::Adler32(hasher) => hasher.finalize().into(),
+            Self::Crc32(hasher) => hasher.finalize().into(),
+            Self::Fletcher16(hasher) => hasher.finalize().into(),
+            Self::Fletcher32(hasher) => hasher.finalize().into(),
+            Self::Fletcher64(hasher) => hasher.finalize(),
+        }
+    }
+
+    /// Start over from where the hasher started, e.g. a resumed
+    /// [`Adler32`]'s checksum.
+    pub fn reset(&mut self) {
+        match self {
+            Self::Adler32(hasher) => hasher.reset(),
+            Self::Crc32(hasher) => hasher.reset(),
+            Self::Fletcher16(hasher) => hasher.reset(),
+            Self::Fletcher32(hasher) => hasher.reset(),
+            Self::Fletcher64(hasher) => hasher.reset(),
+        }
+    }
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Self::new(Algorithm::default())
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Checksum {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+//! Avalon-ST flavoured stimulus: packets are framed by startofpacket and
+//! endofpacket, and `empty` counts the unused symbols of the last beat.
+
+use std::{fmt::Display, io::BufRead};
+
+use crate::{Algorithm, Checksum, Error, ParseLineError, Result};
+
+/// One Avalon-ST beat, written as
+/// `<valid> <data:hex> <startofpacket> <endofpacket> <empty:dec>`.
+///
+/// Symbols are one byte, first symbol in the most significant bits of
+/// `data` (Avalon's default `firstSymbolInHighOrderBits`), so the empty
+/// symbols of a final beat are the least significant ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvalonBeat {
+    pub valid: bool,
+    pub data: u64,
+    pub start: bool,
+    pub end: bool,
+    pub empty: u32,
+}
+
+impl AvalonBeat {
+    /// Column headings, as written at the top of an encoded file
+    pub const HEADER: &'static str = "# valid data startofpacket endofpacket empty";
+
+    /// Writes the beat for a `data_width` bit bus.
+    pub fn display(&self, data_width: u32) -> impl Display + '_ {
+        Formatted {
+            beat: self,
+            data_width,
+        }
+    }
+
+    /// Parse a beat of a `data_width` bit bus.
+    pub fn parse(value: &str, data_width: u32) -> std::result::Result<Self, ParseLineError> {
+        let mut columns = value.split_whitespace();
+        let mut column = |name| columns.next().ok_or(ParseLineError::MissingColumn(name));
+        let flag = |name, text: &str| match text {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(ParseLineError::InvalidColumn {
+                column: name,
+                value: text.to_owned(),
+            }),
+        };
+        let invalid = |name, text: &str| ParseLineError::InvalidColumn {
+            column: name,
+            value: text.to_owned(),
+        };
+
+        let valid = flag("valid", column("valid")?)?;
+        let text = column("data")?;
+        let data = u64::from_str_radix(text, 16)
+            .ok()
+            .filter(|value| value.checked_shr(data_width).unwrap_or(0) == 0)
+            .ok_or_else(|| invalid("data", text))?;
+        let start = flag("startofpacket", column("startofpacket")?)?;
+        let end = flag("endofpacket", column("endofpacket")?)?;
+        let text = column("empty")?;
+        let empty = text
+            .parse()
+            .ok()
+            .filter(|&empty| empty <= data_width / 8)
+            .ok_or_else(|| invalid("empty", text))?;
+        if let Some(rest) = columns.next() {
+            return Err(ParseLineError::Trailing(rest.to_owned()));
+        }
+        Ok(Self {
+            valid,
+            data,
+            start,
+            end,
+            empty,
+        })
+    }
+}
+
+struct Formatted<'a> {
+    beat: &'a AvalonBeat,
+    data_width: u32,
+}
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let beat = self.beat;
+        write!(
+            f,
+            "{} {:0>data$x} {} {} {}",
+            beat.valid as u8,
+            beat.data,
+            beat.start as u8,
+            beat.end as u8,
+            beat.empty,
+            data = self.data_width.div_ceil(4) as usize,
+        )
+    }
+}
+
+/// Beats for one packet on a `data_width` bit bus. An empty packet is a
+/// single beat with startofpacket, endofpacket and every symbol empty.
+///
+/// ```
+/// use adler32::{encode_avalon_packet, AvalonStream};
+///
+/// let beats: Vec<_> = encode_avalon_packet(b"Wikipedia", 32).collect();
+/// assert_eq!(beats.len(), 3);
+/// assert!(beats[0].start && beats[2].end);
+/// assert_eq!(beats[2].empty, 3);
+///
+/// let (checksum, _) = AvalonStream::new(beats.into_iter(), 32).next().unwrap();
+/// assert_eq!(checksum, 0x11e6_0398);
+/// ```
+pub fn encode_avalon_packet(data: &[u8], data_width: u32) -> impl Iterator<Item = AvalonBeat> + '_ {
+    let bytes_per_beat = (data_width / 8) as usize;
+    let beats = data.len().div_ceil(bytes_per_beat).max(1);
+    (0..beats).map(move |index| {
+        let chunk = data.get(index * bytes_per_beat..).unwrap_or_default();
+        let chunk = &chunk[..chunk.len().min(bytes_per_beat)];
+        let empty = bytes_per_beat - chunk.len();
+        AvalonBeat {
+            valid: true,
+            data: chunk
+                .iter()
+                .fold(0u64, |word, &byte| (word << 8) | byte as u64)
+                .checked_shl(8 * empty as u32)
+                .unwrap_or(0),
+            start: index == 0,
+            end: index == beats - 1,
+            empty: empty as u32,
+        }
+    })
+}
+
+/// Parse Avalon-ST stimulus, skipping `#` comment lines. Parse errors carry
+/// the (1-based) line number they occurred on.
+pub fn read_avalon<R: BufRead>(
+    reader: R,
+    data_width: u32,
+) -> impl Iterator<Item = Result<AvalonBeat>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.starts_with('#')))
+        .map(move |(number, line)| {
+            let text = line.map_err(Error::Read)?;
+            AvalonBeat::parse(&text, data_width).map_err(|source| Error::Parse {
+                line: number + 1,
+                text,
+                source,
+            })
+        })
+}
+
+/// Replays Avalon-ST beats, yielding `(checksum, content)` for every packet
+/// endofpacket closes. Beats outside a packet are ignored, and a second
+/// startofpacket restarts the packet.
+pub struct AvalonStream<I>
+where
+    I: Iterator<Item = AvalonBeat>,
+{
+    beats: I,
+    bytes_per_beat: usize,
+    in_packet: bool,
+    content: Vec<u8>,
+    hasher: Checksum,
+}
+
+impl<I> AvalonStream<I>
+where
+    I: Iterator<Item = AvalonBeat>,
+{
+    pub fn new(beats: I, data_width: u32) -> Self {
+        Self {
+            beats,
+            bytes_per_beat: (data_width / 8) as usize,
+            in_packet: false,
+            content: Vec::new(),
+            hasher: Checksum::default(),
+        }
+    }
+
+    /// Checksum packets with `algorithm` instead of Adler-32.
+    pub fn with_algorithm(self, algorithm: Algorithm) -> Self {
+        self.with_hasher(Checksum::new(algorithm))
+    }
+
+    /// Checksum packets with `hasher`, starting each from where it starts,
+    /// e.g. a resumed [`Adler32`](crate::Adler32)'s checksum.
+    pub fn with_hasher(mut self, hasher: Checksum) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    /// Checksum and content of the packet the input ended before
+    /// endofpacket of, once the stream has run out.
+    pub fn truncated(&self) -> Option<(u64, &[u8])> {
+        self.in_packet
+            .then(|| (self.hasher.finalize(), self.content.as_slice()))
+    }
+}
+
+impl<I> Iterator for AvalonStream<I>
+where
+    I: Iterator<Item = AvalonBeat>,
+{
+    type Item = (u64, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for beat in self.beats.by_ref() {
+            if !beat.valid {
+                continue;
+            }
+            if beat.start {
+                self.in_packet = true;
+                self.hasher.reset();
+                self.content.clear();
+            }
+            if !self.in_packet {
+                continue;
+            }
+            let used = if beat.end {
+                self.bytes_per_beat.saturating_sub(beat.empty as usize)
+            } else {
+                self.bytes_per_beat
+            };
+            let bytes = beat.data.to_be_bytes();
+            let symbols = &bytes[8 - self.bytes_per_beat..][..used];
+            self.content.extend_from_slice(symbols);
+            self.hasher.update(symbols);
+            if beat.end {
+                self.in_packet = false;
+                let retval = (self.hasher.finalize(), std::mem::take(&mut self.content));
+                self.hasher.reset();
+                return Some(retval);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,289 @@
+use core::{
+    hash::Hasher,
+    sync::atomic::{AtomicU8, Ordering},
+};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::simd;
+
+/// Largest prime below 2^16, the modulus for both Adler-32 sums.
+pub const MOD_ADLER: u32 = 65521;
+
+/// Most bytes that can be added to sums below [`MOD_ADLER`] before they're
+/// reduced, without `b` overflowing 32 bits even if every byte is `0xff`.
+/// Still small enough for sums below any modulus up to 2^16.
+pub(crate) const NMAX: usize = 5552;
+
+/// The ways this crate can add data to the sums, which all give the same
+/// checksum.
+///
+/// [`Reference`](Self::Reference) is the golden model: it does what the
+/// hardware does for each byte of a beat, and is the one the others are
+/// tested against. The others are for hashing bulk data quickly, and are
+/// only built with the `fast` and `simd` cargo features, both on by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Implementation {
+    /// A byte at a time, reducing both sums after every byte
+    Reference,
+    /// A byte at a time, reducing the sums once every few thousand bytes
+    Fast,
+    /// Vectors of bytes at a time, reducing the same way as [`Fast`](Self::Fast)
+    Simd,
+}
+
+/// [`Implementation`] picked with [`Implementation::choose`], as its
+/// discriminant, or `u8::MAX` to pick by the length of the data
+static CHOSEN: AtomicU8 = AtomicU8::new(u8::MAX);
+
+impl Implementation {
+    pub const ALL: [Self; 3] = [Self::Reference, Self::Fast, Self::Simd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Reference => "reference",
+            Self::Fast => "fast",
+            Self::Simd => "simd",
+        }
+    }
+
+    /// Whether this build has it and the CPU running it can use it.
+    pub fn available(self) -> bool {
+        match self {
+            Self::Reference => true,
+            Self::Fast => cfg!(feature = "fast"),
+            Self::Simd => vector_unit().is_some(),
+        }
+    }
+
+    /// Have every [`Adler32::update`] from now on use this, instead of
+    /// picking the quickest for the length of the data.
+    pub fn choose(self) {
+        CHOSEN.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// The one picked with [`choose`](Self::choose), if any
+    pub fn chosen() -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|&implementation| implementation as u8 == CHOSEN.load(Ordering::Relaxed))
+    }
+}
+
+/// The vector instructions [`Implementation::Simd`] uses on the CPU running
+/// this, e.g. `"avx2"`, or `None` if there are none it knows or this build
+/// doesn't have the `simd` feature.
+pub fn vector_unit() -> Option<&'static str> {
+    simd::unit()
+}
+
+/// Incremental Adler-32 hasher.
+///
+/// Feed data in as many chunks as convenient; the result only depends on the
+/// concatenated bytes. Both sums are reduced by the end of every
+/// [`update`](Self::update), so feeding a beat at a time gives the checksum
+/// the hardware holds after each beat.
+///
+/// ```
+/// use adler32::Adler32;
+///
+/// let mut hasher = Adler32::new();
+/// hasher.update(b"Wiki");
+/// hasher.update(b"pedia");
+/// assert_eq!(hasher.finalize(), 0x11e6_0398);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+    /// Checksum to start from, and to go back to on reset
+    start: u32,
+    /// What both sums are reduced modulo, [`MOD_ADLER`] but for experiments
+    modulus: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Self {
+        Self::resume(1)
+    }
+
+    /// Hasher starting from `checksum` instead of 1, as if whatever had
+    /// that checksum had already been added. Each half is reduced modulo
+    /// the modulus.
+    ///
+    /// ```
+    /// use adler32::{adler32, Adler32};
+    ///
+    /// let mut hasher = Adler32::resume(adler32(b"Wiki"));
+    /// hasher.update(b"pedia");
+    /// assert_eq!(hasher.finalize(), adler32(b"Wikipedia"));
+    /// hasher.reset();
+    /// assert_eq!(hasher.finalize(), adler32(b"Wiki"));
+    /// ```
+    pub fn resume(checksum: u32) -> Self {
+        let mut hasher = Self {
+            a: 0,
+            b: 0,
+            start: checksum,
+            modulus: MOD_ADLER,
+        };
+        hasher.reset();
+        hasher
+    }
+
+    /// The same hasher, started over, reducing the sums modulo `modulus`
+    /// instead of [`MOD_ADLER`], for trying out variants of the hardware.
+    /// Only the standard modulus uses vector instructions.
+    ///
+    /// ```
+    /// use adler32::Adler32;
+    ///
+    /// let mut hasher = Adler32::new().with_modulus(1 << 16);
+    /// hasher.update(&[0xff; 300]);
+    /// assert_eq!(hasher.finalize() & 0xffff, (1 + 300 * 0xff) % (1 << 16));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `modulus` is below 2 or above 2^16, where checksums wouldn't fit
+    /// in 32 bits.
+    pub fn with_modulus(mut self, modulus: u32) -> Self {
+        assert!(
+            (2..=1 << 16).contains(&modulus),
+            "modulus {modulus} isn't between 2 and 65536"
+        );
+        self.modulus = modulus;
+        self.reset();
+        self
+    }
+
+    /// Add `data` to the running checksum, with the implementation picked
+    /// with [`Implementation::choose`], or else with vector instructions if
+    /// there's enough of it and the CPU has them.
+    pub fn update(&mut self, data: &[u8]) {
+        let implementation =
+            Implementation::chosen().unwrap_or(match data.len() >= simd::MIN_LEN {
+                true => Implementation::Simd,
+                false => Implementation::Fast,
+            });
+        self.update_with(implementation, data);
+    }
+
+    /// Add `data` to the running checksum with `implementation`, or with the
+    /// next simplest one if it's not [`available`](Implementation::available).
+    ///
+    /// ```
+    /// use adler32::{Adler32, Implementation};
+    ///
+    /// for implementation in Implementation::ALL {
+    ///     let mut hasher = Adler32::new();
+    ///     hasher.update_with(implementation, b"Wikipedia");
+    ///     assert_eq!(hasher.finalize(), 0x11e6_0398);
+    /// }
+    /// ```
+    pub fn update_with(&mut self, implementation: Implementation, data: &[u8]) {
+        match implementation {
+            Implementation::Simd if self.modulus != MOD_ADLER => {
+                self.update_with(Implementation::Fast, data)
+            }
+            Implementation::Simd => match simd::update(self.a, self.b, data) {
+                Some((a, b)) => (self.a, self.b) = (a, b),
+                None => self.update_with(Implementation::Fast, data),
+            },
+            Implementation::Fast if cfg!(feature = "fast") => {
+                for block in data.chunks(NMAX) {
+                    for &byte in block {
+                        self.a += byte as u32;
+                        self.b += self.a;
+                    }
+                    self.a %= self.modulus;
+                    self.b %= self.modulus;
+                }
+            }
+            Implementation::Reference | Implementation::Fast => {
+                for &byte in data {
+                    self.a = (self.a + byte as u32) % self.modulus;
+                    self.b = (self.b + self.a) % self.modulus;
+                }
+            }
+        }
+    }
+
+    /// Checksum of everything passed to [`update`](Self::update) since the
+    /// last reset. Does not consume the hasher, so more data can follow.
+    pub fn finalize(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+
+    /// Start over from the checksum it was created with.
+    pub fn reset(&mut self) {
+        self.a = (self.start & 0xffff) % self.modulus;
+        self.b = (self.start >> 16) % self.modulus;
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lets a reader be hashed with [`io::copy`].
+///
+/// ```
+/// use adler32::Adler32;
+///
+/// let mut hasher = Adler32::new();
+/// std::io::copy(&mut &b"Wikipedia"[..], &mut hasher).unwrap();
+/// assert_eq!(hasher.finalize(), 0x11e6_0398);
+/// ```
+#[cfg(feature = "std")]
+impl io::Write for Adler32 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `finish` returns the 32 bit checksum zero extended.
+impl Hasher for Adler32 {
+    fn finish(&self) -> u64 {
+        self.finalize() as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+}
+
+/// Adler-32 of a complete buffer.
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut hasher = Adler32::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Adler-32 of two buffers one after the other, from the checksum of each
+/// and the length of the second, without going over the data again.
+///
+/// ```
+/// use adler32::{adler32, combine};
+///
+/// let (wiki, pedia) = (adler32(b"Wiki"), adler32(b"pedia"));
+/// assert_eq!(combine(wiki, pedia, 5), adler32(b"Wikipedia"));
+/// ```
+pub fn combine(first: u32, second: u32, second_len: u64) -> u32 {
+    let modulus = MOD_ADLER as u64;
+    let len = second_len % modulus;
+    let (a1, b1) = ((first & 0xffff) as u64, (first >> 16) as u64);
+    let (a2, b2) = ((second & 0xffff) as u64, (second >> 16) as u64);
+    // The second checksum started from a = 1 rather than where the first
+    // left off, which its every byte added to b
+    let a = (a1 + a2 + modulus - 1) % modulus;
+    let b = (b1 + b2 + len * (a1 + modulus - 1)) % modulus;
+    ((b << 16) | a) as u32
+}
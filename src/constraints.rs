@@ -0,0 +1,136 @@
+//! Constraint files steering `generate`, written as JSON:
+//!
+//! ```json
+//! {
+//!     "packets": 100,
+//!     "lengths": [{ "min": 1, "max": 16, "weight": 3 }, { "min": 1000, "max": 1024 }],
+//!     "bytes": [[48, 57], [255, 255]],
+//!     "bubbles": 0.1,
+//!     "ready_duty": 0.8
+//! }
+//! ```
+//!
+//! Every key is optional.
+
+use adler32::{Error, Result, Rng};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Constraints {
+    /// Number of packets
+    #[serde(default = "one")]
+    pub packets: usize,
+    /// Ranges of packet lengths, picked between by weight
+    #[serde(default = "Constraints::default_lengths")]
+    pub lengths: Vec<Lengths>,
+    /// Inclusive `[low, high]` ranges of byte values packets may contain,
+    /// any byte if empty
+    #[serde(default)]
+    pub bytes: Vec<[u8; 2]>,
+    /// Chance of an idle line before each data beat, as `--bubbles`
+    pub bubbles: Option<f64>,
+    /// Fraction of cycles the DUT is ready, as `--ready-duty`
+    pub ready_duty: Option<f64>,
+}
+
+/// Packet lengths from `min` to `max` bytes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lengths {
+    pub min: usize,
+    pub max: usize,
+    /// Relative chance of this range being picked
+    #[serde(default = "one")]
+    pub weight: u64,
+}
+
+fn one<T: From<u8>>() -> T {
+    T::from(1)
+}
+
+impl Constraints {
+    /// `packets` packets of `min` to `max` bytes, with any byte values
+    pub fn new(packets: usize, min: usize, max: usize) -> Self {
+        Self {
+            packets,
+            lengths: vec![Lengths {
+                min,
+                max,
+                weight: 1,
+            }],
+            bytes: Vec::new(),
+            bubbles: None,
+            ready_duty: None,
+        }
+    }
+
+    fn default_lengths() -> Vec<Lengths> {
+        Self::new(1, 0, 256).lengths
+    }
+
+    /// Read and check the constraint file at `path`.
+    pub fn load(path: &str, source: impl std::io::Read) -> Result<Self> {
+        let invalid = |message: String| Error::Usage(format!("{path}: {message}"));
+        let constraints: Self =
+            serde_json::from_reader(source).map_err(|e| invalid(e.to_string()))?;
+
+        if constraints.total_weight() == 0 {
+            return Err(invalid("no length range has any weight".to_owned()));
+        }
+        if let Some(lengths) = constraints.lengths.iter().find(|l| l.min > l.max) {
+            return Err(invalid(format!(
+                "length range {}..{} is backwards",
+                lengths.min, lengths.max
+            )));
+        }
+        if let Some([low, high]) = constraints.bytes.iter().find(|[low, high]| low > high) {
+            return Err(invalid(format!("byte range [{low}, {high}] is backwards")));
+        }
+        if !constraints.bubbles.is_none_or(|p| (0.0..1.0).contains(&p)) {
+            return Err(invalid("bubbles must be from 0 up to 1".to_owned()));
+        }
+        if !constraints.ready_duty.is_none_or(|d| d > 0.0 && d <= 1.0) {
+            return Err(invalid("ready_duty must be above 0 and up to 1".to_owned()));
+        }
+        Ok(constraints)
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.lengths.iter().map(|lengths| lengths.weight).sum()
+    }
+
+    /// Draw every packet from `rng`.
+    pub fn generate(&self, rng: &mut Rng) -> Vec<Vec<u8>> {
+        (0..self.packets).map(|_| self.packet(rng)).collect()
+    }
+
+    fn packet(&self, rng: &mut Rng) -> Vec<u8> {
+        let mut pick = rng.range(0..=self.total_weight() - 1);
+        let lengths = self
+            .lengths
+            .iter()
+            .find(|lengths| {
+                let found = pick < lengths.weight;
+                pick = pick.saturating_sub(lengths.weight);
+                found
+            })
+            .expect("pick is below the total weight");
+        let len = rng.range(lengths.min as u64..=lengths.max as u64) as usize;
+        if self.bytes.is_empty() {
+            return rng.bytes(len);
+        }
+
+        // Uniform over every allowed value, even where ranges overlap
+        let mut allowed = self
+            .bytes
+            .iter()
+            .flat_map(|&[low, high]| low..=high)
+            .collect::<Vec<_>>();
+        allowed.sort_unstable();
+        allowed.dedup();
+        (0..len)
+            .map(|_| allowed[rng.range(0..=allowed.len() as u64 - 1) as usize])
+            .collect()
+    }
+}
@@ -0,0 +1,34 @@
+//! Packets that exercise the edge cases of an Adler-32 implementation.
+
+use crate::{checksum::NMAX, MOD_ADLER};
+
+/// Known-tricky packets no longer than `max_length`: empty, single bytes,
+/// lengths either side of the modulus, all-0xff runs that wrap the sums
+/// many times, and an all-0xff packet of `max_length` bytes.
+///
+/// ```
+/// use adler32::corner_packets;
+///
+/// let packets = corner_packets(1);
+/// assert_eq!(packets, [vec![], vec![0x00], vec![0xff]]);
+/// ```
+pub fn corner_packets(max_length: usize) -> Vec<Vec<u8>> {
+    let modulus = MOD_ADLER as usize;
+    let counting = |len: usize| (0..len).map(|i| i as u8).collect::<Vec<_>>();
+    let mut packets = vec![
+        vec![],
+        vec![0x00],
+        vec![0xff],
+        counting(modulus - 1),
+        counting(modulus),
+        counting(modulus + 1),
+        vec![0xff; NMAX],
+        vec![0xff; NMAX + 1],
+        vec![0xff; 2 * modulus + 1],
+    ];
+    packets.retain(|packet| packet.len() <= max_length);
+    if packets.iter().all(|packet| packet.len() != max_length) {
+        packets.push(vec![0xff; max_length]);
+    }
+    packets
+}
@@ -0,0 +1,80 @@
+//! CRC-32 as zlib, Ethernet and PNG compute it: the reflected polynomial
+//! `0xedb88320`, starting from all ones and inverted at the end.
+
+/// Reversed CRC-32 polynomial
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+/// CRC of each byte value, for adding a byte at a time
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => crc >> 1 ^ POLYNOMIAL,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+}
+
+/// Incremental CRC-32 hasher.
+///
+/// ```
+/// use adler32::Crc32;
+///
+/// let mut hasher = Crc32::new();
+/// hasher.update(b"1234");
+/// hasher.update(b"56789");
+/// assert_eq!(hasher.finalize(), 0xcbf4_3926);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+    /// The CRC so far, not yet inverted
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { crc: !0 }
+    }
+
+    /// Add `data` to the running checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc = self.crc >> 8 ^ TABLE[(self.crc as u8 ^ byte) as usize];
+        }
+    }
+
+    /// Checksum of everything passed to [`update`](Self::update) since the
+    /// last reset. More data can follow.
+    pub fn finalize(&self) -> u32 {
+        !self.crc
+    }
+
+    /// Start over as if freshly created.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CRC-32 of a complete buffer.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = Crc32::new();
+    hasher.update(data);
+    hasher.finalize()
+}
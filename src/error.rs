@@ -0,0 +1,152 @@
+use std::{fmt::Display, io};
+
+use crate::{line::ParseLineError, Algorithm};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Couldn't open `path`
+    Open { path: String, source: io::Error },
+    /// Failed reading input after it was opened
+    Read(io::Error),
+    /// Line number `line` of encoded stimulus is malformed
+    Parse {
+        line: usize,
+        text: String,
+        source: ParseLineError,
+    },
+    /// A packet of `length` bytes doesn't fit in a `width` bit length field
+    PacketTooLong { length: u64, width: u32 },
+    /// Line number `line` of a checksum list isn't a 32 or 64 bit hex value
+    InvalidChecksum { line: usize, text: String },
+    /// Failed writing output
+    Write(io::Error),
+    /// A computed `algorithm` checksum differs from the one it was checked
+    /// against
+    ChecksumMismatch {
+        algorithm: Algorithm,
+        expected: u64,
+        actual: u64,
+    },
+    /// Options that can't be used together
+    Usage(String),
+    /// `failed` out of `total` packets or files didn't match their expected
+    /// checksum
+    Verify { failed: usize, total: usize },
+    /// Packet number `packet` of encoded stimulus has no bytes
+    ZeroLength { packet: usize },
+    /// The input ended in the middle of packet number `packet`
+    Truncated { packet: usize },
+    /// Lint found `problems` problems in encoded stimulus
+    Lint { problems: usize },
+    /// The lines above line number `line`, a trailer, add up to `actual`
+    /// rather than the `expected` checksum it records
+    Trailer {
+        line: usize,
+        expected: u32,
+        actual: u32,
+    },
+    /// `differing` out of `total` packets differ between two files
+    Differ { differing: usize, total: usize },
+    /// An external command, e.g. the simulator, exited unsuccessfully
+    Command { command: String, status: String },
+}
+
+impl Error {
+    /// Process exit code for this class of error, so scripts can tell a
+    /// failed check apart from a broken invocation. Usage errors share 2
+    /// with the ones clap reports.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::ChecksumMismatch { .. }
+            | Error::Verify { .. }
+            | Error::Lint { .. }
+            | Error::Differ { .. } => 1,
+            Error::Usage(_) => 2,
+            Error::Read(_) => 3,
+            Error::Parse { .. }
+            | Error::InvalidChecksum { .. }
+            | Error::PacketTooLong { .. }
+            | Error::ZeroLength { .. }
+            | Error::Truncated { .. }
+            | Error::Trailer { .. } => 4,
+            Error::Write(_) => 5,
+            Error::Open { .. } => 6,
+            Error::Command { .. } => 7,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Open { path, source } => write!(f, "failed to open {path}: {source}"),
+            Error::Read(e) => write!(f, "failed to read input: {e}"),
+            Error::Parse { line, text, source } => write!(f, "line {line}: {source}\n    {text}"),
+            Error::PacketTooLong { length, width } => write!(
+                f,
+                "packet of {length} bytes doesn't fit in a {width} bit length field"
+            ),
+            Error::InvalidChecksum { line, text } => {
+                write!(f, "line {line}: '{text}' is not a hex checksum")
+            }
+            Error::Write(e) => write!(f, "failed to write output: {e}"),
+            Error::ChecksumMismatch {
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch: expected {}, got {}",
+                algorithm.literal(*expected),
+                algorithm.literal(*actual)
+            ),
+            Error::Usage(message) => f.write_str(message),
+            Error::Verify { failed, total } => {
+                write!(f, "{failed} of {total} checksums did not match")
+            }
+            Error::ZeroLength { packet } => write!(f, "packet {packet} is zero-length"),
+            Error::Truncated { packet } => {
+                write!(f, "input ended in the middle of packet {packet}")
+            }
+            Error::Lint { problems: 1 } => f.write_str("found 1 problem"),
+            Error::Lint { problems } => write!(f, "found {problems} problems"),
+            Error::Trailer {
+                line,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "line {line}: the lines above have checksum 32'h{actual:0>8x}, not \
+                 32'h{expected:0>8x} as the trailer says, so the file has changed \
+                 since it was written"
+            ),
+            Error::Differ { differing, total } => {
+                write!(f, "{differing} of {total} packets differ")
+            }
+            Error::Command { command, status } => write!(f, "{command} exited with {status}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Open { source, .. } => Some(source),
+            Error::Read(e) | Error::Write(e) => Some(e),
+            Error::Parse { source, .. } => Some(source),
+            Error::InvalidChecksum { .. }
+            | Error::PacketTooLong { .. }
+            | Error::ChecksumMismatch { .. }
+            | Error::Usage(_)
+            | Error::Verify { .. }
+            | Error::ZeroLength { .. }
+            | Error::Truncated { .. }
+            | Error::Lint { .. }
+            | Error::Trailer { .. }
+            | Error::Differ { .. }
+            | Error::Command { .. } => None,
+        }
+    }
+}
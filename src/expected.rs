@@ -0,0 +1,138 @@
+use std::io::BufRead;
+
+use regex::Regex;
+
+use crate::{Error, Result};
+
+/// Parse a 32 or 64 bit checksum written as a verilog literal
+/// (`32'h091e01de`), with a `0x` prefix or as bare hex. Underscores are
+/// ignored.
+///
+/// ```
+/// use adler32::parse_checksum;
+///
+/// assert_eq!(parse_checksum("32'h091e_01de"), Some(0x091e01de));
+/// assert_eq!(parse_checksum("0x091E01DE"), Some(0x091e01de));
+/// assert_eq!(parse_checksum("91e01de"), Some(0x091e01de));
+/// assert_eq!(parse_checksum("64'hc8c6c527_646362c6"), Some(0xc8c6c527_646362c6));
+/// assert_eq!(parse_checksum("32'hc8c6c527_646362c6"), None);
+/// assert_eq!(parse_checksum("32'd1"), None);
+/// ```
+pub fn parse_checksum(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (digits, max) = match text.split_at_checked(4) {
+        Some(("32'h" | "32'H", digits)) => (digits, 8),
+        Some(("64'h" | "64'H", digits)) => (digits, 16),
+        _ => (
+            text.strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))
+                .unwrap_or(text),
+            16,
+        ),
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || digits.len() > max {
+        return None;
+    }
+    u64::from_str_radix(&digits, 16).ok()
+}
+
+/// The checksum an `expect 32'h091e01de` comment embedded in stimulus
+/// records, with the comment prefix already removed. `None` if the comment
+/// isn't one.
+///
+/// ```
+/// use adler32::parse_expect;
+///
+/// assert_eq!(parse_expect(" expect 32'h091e01de"), Some(0x091e01de));
+/// assert_eq!(parse_expect(" expected to fail"), None);
+/// ```
+pub fn parse_expect(comment: &str) -> Option<u64> {
+    let (keyword, checksum) = comment.trim().split_once(char::is_whitespace)?;
+    match keyword == "expect" {
+        true => parse_checksum(checksum),
+        false => None,
+    }
+}
+
+/// Read a list of expected checksums, one per line. Blank lines and `#`
+/// comments are skipped.
+pub fn read_checksums<R: BufRead>(reader: R) -> impl Iterator<Item = Result<u64>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            !matches!(line, Ok(line) if line.trim().is_empty() || line.starts_with('#'))
+        })
+        .map(|(number, line)| {
+            let text = line.map_err(Error::Read)?;
+            parse_checksum(&text).ok_or(Error::InvalidChecksum {
+                line: number + 1,
+                text,
+            })
+        })
+}
+
+/// Pull the checksums a simulator reported out of its log. Each match of
+/// `pattern` is one checksum: its first capture group if it has one, the
+/// whole match otherwise.
+///
+/// ```
+/// use adler32::read_log_checksums;
+/// use regex::Regex;
+///
+/// let log = "# ** Note: CHECKSUM=11e60398\n# run 100ns\n# ** Note: CHECKSUM=024d0127\n";
+/// let pattern = Regex::new("CHECKSUM=([0-9a-f]{8})").unwrap();
+/// let checksums: Vec<_> = read_log_checksums(log.as_bytes(), &pattern)
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(checksums, [0x11e6_0398, 0x024d_0127]);
+/// ```
+pub fn read_log_checksums<'a, R: BufRead + 'a>(
+    reader: R,
+    pattern: &'a Regex,
+) -> impl Iterator<Item = Result<u64>> + 'a {
+    reader.lines().enumerate().flat_map(move |(number, line)| {
+        let text = match line {
+            Ok(text) => text,
+            Err(e) => return vec![Err(Error::Read(e))],
+        };
+        pattern
+            .captures_iter(&text)
+            .map(|captures| {
+                let found = captures
+                    .get(1)
+                    .or(captures.get(0))
+                    .map_or("", |m| m.as_str());
+                parse_checksum(found).ok_or_else(|| Error::InvalidChecksum {
+                    line: number + 1,
+                    text: found.to_owned(),
+                })
+            })
+            .collect()
+    })
+}
+
+/// Read an md5sum style manifest of `<checksum>  <filename>` lines. A `*`
+/// before the filename (binary mode marker) is accepted and dropped.
+pub fn read_manifest<R: BufRead>(reader: R) -> impl Iterator<Item = Result<(u64, String)>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            !matches!(line, Ok(line) if line.trim().is_empty() || line.starts_with('#'))
+        })
+        .map(|(number, line)| {
+            let text = line.map_err(Error::Read)?;
+            text.split_once(' ')
+                .and_then(|(checksum, path)| {
+                    let path = path.strip_prefix([' ', '*']).unwrap_or(path);
+                    Some((parse_checksum(checksum)?, path.to_owned()))
+                })
+                .filter(|(_, path)| !path.is_empty())
+                .ok_or(Error::InvalidChecksum {
+                    line: number + 1,
+                    text,
+                })
+        })
+}
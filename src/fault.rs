@@ -0,0 +1,97 @@
+//! Deliberate damage to stimulus, for checking that a DUT notices it.
+
+use std::collections::BTreeSet;
+
+use crate::{DataLine, DataStream, Rng};
+
+/// Flip `flips` distinct payload bits in `lines`, picked with `rng`, and
+/// return the indices of the packets whose checksum changed as a result.
+///
+/// Only bits carrying packet bytes are touched, never the padding in a
+/// packet's last beat or the framing. Flips every payload bit if there are
+/// fewer than `flips`.
+///
+/// ```
+/// use adler32::{encode_packet, flip_bits, Rng};
+///
+/// let mut lines: Vec<_> = encode_packet(b"ab").chain(encode_packet(b"c")).collect();
+/// let damaged = flip_bits(&mut lines, 8, 24, &mut Rng::new(0));
+/// assert_eq!(damaged, [0, 1]);
+/// assert_eq!(lines[1].data, !b'a' as u64 & 0xff);
+/// ```
+pub fn flip_bits(
+    lines: &mut [DataLine],
+    data_width: u32,
+    flips: usize,
+    rng: &mut Rng,
+) -> Vec<usize> {
+    let checksums = |lines: &[DataLine]| {
+        DataStream::with_data_width(lines.iter().copied(), data_width)
+            .with_empty_packets()
+            .map(|(checksum, _)| checksum)
+            .collect::<Vec<_>>()
+    };
+    let before = checksums(lines);
+
+    // (line, payload bits) for every beat carrying packet bytes, tracked the
+    // way DataStream consumes them
+    let mut beats = Vec::new();
+    let mut length = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if line.length_valid {
+            length = line.length;
+        }
+        if line.data_valid && length > 0 {
+            let count = length.min(data_width as u64 / 8);
+            beats.push((index, count * 8));
+            length -= count;
+        }
+    }
+
+    let total = beats.iter().map(|&(_, bits)| bits).sum::<u64>();
+    let mut picked = BTreeSet::new();
+    while picked.len() < flips.min(total as usize) {
+        picked.insert(rng.range(0..=total - 1));
+    }
+    let mut beats = beats.into_iter();
+    let mut start = 0;
+    let mut beat = beats.next();
+    for bit in picked {
+        while let Some((_, bits)) = beat.filter(|&(_, bits)| bit >= start + bits) {
+            start += bits;
+            beat = beats.next();
+        }
+        let (index, _) = beat.expect("bit is below the payload total");
+        lines[index].data ^= 1 << (bit - start);
+    }
+
+    let after = checksums(lines);
+    (0..before.len())
+        .filter(|&packet| after.get(packet) != Some(&before[packet]))
+        .collect()
+}
+
+/// Change `count` of `checksums`, picked with `rng`, to some other value, and
+/// return the indices changed in order. Changes all of them if there are
+/// fewer than `count`.
+///
+/// ```
+/// use adler32::{perturb_checksums, Rng};
+///
+/// let golden = [0x11e6_0398, 0x024d_0127, 0x0000_0001];
+/// let mut checksums = golden;
+/// let changed = perturb_checksums(&mut checksums, 1, &mut Rng::new(0));
+/// assert_eq!(changed.len(), 1);
+/// assert_ne!(checksums[changed[0]], golden[changed[0]]);
+/// ```
+pub fn perturb_checksums(checksums: &mut [u64], count: usize, rng: &mut Rng) -> Vec<usize> {
+    let mut picked = BTreeSet::new();
+    while picked.len() < count.min(checksums.len()) {
+        picked.insert(rng.range(0..=checksums.len() as u64 - 1) as usize);
+    }
+    for &index in &picked {
+        // Any nonzero mask changes the value
+        checksums[index] ^= rng.range(1..=u32::MAX as u64);
+    }
+    picked.into_iter().collect()
+}
@@ -0,0 +1,148 @@
+//! C ABI, so a simulator can call the golden model over DPI-C instead of
+//! going through files. `include/adler32.h` has the matching declarations.
+
+use std::{
+    ffi::{c_char, c_int, CStr},
+    ptr, slice,
+};
+
+use crate::{Adler32, LineFormat};
+
+/// A fresh hasher, to be released with [`adler32_free`].
+#[no_mangle]
+pub extern "C" fn adler32_init() -> *mut Adler32 {
+    Box::into_raw(Box::new(Adler32::new()))
+}
+
+/// Add `len` bytes at `data` to the running checksum.
+///
+/// # Safety
+///
+/// `state` must come from [`adler32_init`] and `data` must point at `len`
+/// readable bytes (or be null with `len` 0).
+#[no_mangle]
+pub unsafe extern "C" fn adler32_update(state: *mut Adler32, data: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    (*state).update(slice::from_raw_parts(data, len));
+}
+
+/// Add a single byte, the natural shape for a DPI `byte unsigned` argument.
+///
+/// # Safety
+///
+/// `state` must come from [`adler32_init`].
+#[no_mangle]
+pub unsafe extern "C" fn adler32_update_byte(state: *mut Adler32, byte: u8) {
+    (*state).update(&[byte]);
+}
+
+/// Checksum of everything added since the last reset. More data can follow.
+///
+/// # Safety
+///
+/// `state` must come from [`adler32_init`].
+#[no_mangle]
+pub unsafe extern "C" fn adler32_final(state: *const Adler32) -> u32 {
+    (*state).finalize()
+}
+
+/// Start over as if freshly created.
+///
+/// # Safety
+///
+/// `state` must come from [`adler32_init`].
+#[no_mangle]
+pub unsafe extern "C" fn adler32_reset(state: *mut Adler32) {
+    (*state).reset();
+}
+
+/// Release a hasher. Null is ignored.
+///
+/// # Safety
+///
+/// `state` must come from [`adler32_init`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn adler32_free(state: *mut Adler32) {
+    if !state.is_null() {
+        drop(Box::from_raw(state));
+    }
+}
+
+/// Checksum of `len` bytes at `data` in one go.
+///
+/// # Safety
+///
+/// `data` must point at `len` readable bytes (or be null with `len` 0).
+#[no_mangle]
+pub unsafe extern "C" fn adler32_checksum(data: *const u8, len: usize) -> u32 {
+    if len == 0 {
+        return crate::adler32(&[]);
+    }
+    crate::adler32(slice::from_raw_parts(data, len))
+}
+
+/// Line format from a spec like `{lv}_{len:b32}_{dv}_{data:b8}`, or the
+/// default one if `spec` is null. Null if the spec is invalid. Release it
+/// with [`adler32_format_free`].
+///
+/// # Safety
+///
+/// `spec` must be null or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn adler32_format_new(spec: *const c_char) -> *mut LineFormat {
+    if spec.is_null() {
+        return Box::into_raw(Box::default());
+    }
+    match CStr::from_ptr(spec).to_str().map(str::parse::<LineFormat>) {
+        Ok(Ok(format)) => Box::into_raw(Box::new(format)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Release a line format. Null is ignored.
+///
+/// # Safety
+///
+/// `format` must come from [`adler32_format_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn adler32_format_free(format: *mut LineFormat) {
+    if !format.is_null() {
+        drop(Box::from_raw(format));
+    }
+}
+
+/// Parse one line of encoded stimulus with `format` (the default format if
+/// null) into the out parameters. Returns 0 on success and -1 if the line
+/// doesn't parse, leaving the outputs untouched.
+///
+/// # Safety
+///
+/// `format` must be null or come from [`adler32_format_new`], `line` must be
+/// a NUL terminated string and every output must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn adler32_parse_line(
+    format: *const LineFormat,
+    line: *const c_char,
+    length_valid: *mut u8,
+    length: *mut u64,
+    data_valid: *mut u8,
+    data: *mut u64,
+) -> c_int {
+    let Ok(line) = CStr::from_ptr(line).to_str() else {
+        return -1;
+    };
+    let parsed = match format.as_ref() {
+        Some(format) => format.parse(line),
+        None => LineFormat::default().parse(line),
+    };
+    let Ok(parsed) = parsed else {
+        return -1;
+    };
+    *length_valid = parsed.length_valid as u8;
+    *length = parsed.length;
+    *data_valid = parsed.data_valid as u8;
+    *data = parsed.data;
+    0
+}
@@ -0,0 +1,204 @@
+//! Fletcher checksums: two running sums like Adler-32's, over 8, 16 or 32
+//! bit little-endian words, reduced modulo 2^8 - 1, 2^16 - 1 or 2^32 - 1
+//! and starting from 0. A trailing partial word is padded with zeros.
+
+/// The two sums over `WORD` byte words, and the bytes of a word that's
+/// still being added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sums<const WORD: usize> {
+    a: u64,
+    b: u64,
+    partial: [u8; WORD],
+    filled: usize,
+}
+
+impl<const WORD: usize> Sums<WORD> {
+    /// Both sums are reduced modulo this
+    const MODULUS: u64 = (1 << (8 * WORD)) - 1;
+
+    const fn new() -> Self {
+        Self {
+            a: 0,
+            b: 0,
+            partial: [0; WORD],
+            filled: 0,
+        }
+    }
+
+    fn add_word(a: &mut u64, b: &mut u64, bytes: &[u8]) {
+        let word = bytes
+            .iter()
+            .rev()
+            .fold(0, |word, &byte| word << 8 | byte as u64);
+        *a = (*a + word) % Self::MODULUS;
+        *b = (*b + *a) % Self::MODULUS;
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        if self.filled > 0 {
+            let take = data.len().min(WORD - self.filled);
+            self.partial[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled < WORD {
+                return;
+            }
+            Self::add_word(&mut self.a, &mut self.b, &self.partial);
+            self.filled = 0;
+        }
+        let mut words = data.chunks_exact(WORD);
+        for word in words.by_ref() {
+            Self::add_word(&mut self.a, &mut self.b, word);
+        }
+        let rest = words.remainder();
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    /// `b` above `a`, counting any partial word padded with zeros
+    fn finalize(&self) -> u64 {
+        let (mut a, mut b) = (self.a, self.b);
+        if self.filled > 0 {
+            Self::add_word(&mut a, &mut b, &self.partial[..self.filled]);
+        }
+        b << (8 * WORD) | a
+    }
+}
+
+/// Incremental Fletcher-16 hasher, over bytes.
+///
+/// ```
+/// use adler32::fletcher16;
+///
+/// assert_eq!(fletcher16(b"abcde"), 0xc8f0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fletcher16(Sums<1>);
+
+impl Fletcher16 {
+    pub fn new() -> Self {
+        Self(Sums::new())
+    }
+
+    /// Add `data` to the running checksum. Words can be split across calls.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Checksum of everything passed to [`update`](Self::update) since the
+    /// last reset. More data can follow.
+    pub fn finalize(&self) -> u32 {
+        self.0.finalize() as u32
+    }
+
+    /// Start over as if freshly created.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Fletcher16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fletcher-16 of a complete buffer.
+pub fn fletcher16(data: &[u8]) -> u32 {
+    let mut hasher = Fletcher16::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Incremental Fletcher-32 hasher, over 16 bit words.
+///
+/// ```
+/// use adler32::Fletcher32;
+///
+/// let mut hasher = Fletcher32::new();
+/// hasher.update(b"abc");
+/// hasher.update(b"de");
+/// assert_eq!(hasher.finalize(), 0xf04f_c729);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fletcher32(Sums<2>);
+
+impl Fletcher32 {
+    pub fn new() -> Self {
+        Self(Sums::new())
+    }
+
+    /// Add `data` to the running checksum. Words can be split across calls.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Checksum of everything passed to [`update`](Self::update) since the
+    /// last reset. More data can follow.
+    pub fn finalize(&self) -> u32 {
+        self.0.finalize() as u32
+    }
+
+    /// Start over as if freshly created.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Fletcher32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fletcher-32 of a complete buffer.
+pub fn fletcher32(data: &[u8]) -> u32 {
+    let mut hasher = Fletcher32::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Incremental Fletcher-64 hasher, over 32 bit words.
+///
+/// ```
+/// use adler32::fletcher64;
+///
+/// assert_eq!(fletcher64(b"abcde"), 0xc8c6_c527_6463_62c6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fletcher64(Sums<4>);
+
+impl Fletcher64 {
+    pub fn new() -> Self {
+        Self(Sums::new())
+    }
+
+    /// Add `data` to the running checksum. Words can be split across calls.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Checksum of everything passed to [`update`](Self::update) since the
+    /// last reset. More data can follow.
+    pub fn finalize(&self) -> u64 {
+        self.0.finalize()
+    }
+
+    /// Start over as if freshly created.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Fletcher64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fletcher-64 of a complete buffer.
+pub fn fletcher64(data: &[u8]) -> u64 {
+    let mut hasher = Fletcher64::new();
+    hasher.update(data);
+    hasher.finalize()
+}
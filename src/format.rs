@@ -0,0 +1,857 @@
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+    iter,
+    str::FromStr,
+};
+
+use crate::{DataLine, Error, Field, ParseLineError, Result};
+
+/// How a number is written in an encoded line, ordered by how many
+/// different digits each uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Radix {
+    Binary,
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    pub(crate) fn base(&self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Hex => 16,
+            Radix::Decimal => 10,
+        }
+    }
+
+    /// Letter used for this radix in a format spec
+    fn letter(&self) -> char {
+        match self {
+            Radix::Binary => 'b',
+            Radix::Hex => 'h',
+            Radix::Decimal => 'd',
+        }
+    }
+
+    /// Characters needed to write any `width` bit value
+    pub(crate) fn digits(&self, width: u32) -> usize {
+        match self {
+            Radix::Binary => width as usize,
+            Radix::Hex => width.div_ceil(4) as usize,
+            Radix::Decimal => (u64::MAX >> (64 - width)).to_string().len(),
+        }
+    }
+}
+
+impl Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Radix::Binary => "binary",
+            Radix::Hex => "hex",
+            Radix::Decimal => "decimal",
+        })
+    }
+}
+
+impl FromStr for Radix {
+    type Err = FormatSpecError;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "b" | "bin" | "binary" => Ok(Radix::Binary),
+            "h" | "x" | "hex" => Ok(Radix::Hex),
+            "d" | "dec" | "decimal" => Ok(Radix::Decimal),
+            _ => Err(FormatSpecError(format!("unknown radix '{value}'"))),
+        }
+    }
+}
+
+/// A piece of a [`LineFormat`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Text written as is between fields
+    Literal(String),
+    /// A field, zero padded to `width` bits worth of digits
+    Field {
+        field: Field,
+        radix: Radix,
+        width: u32,
+    },
+}
+
+impl Field {
+    /// Name of the field in a format spec
+    fn spec_name(&self) -> &'static str {
+        match self {
+            Field::LengthValid => "lv",
+            Field::Length => "len",
+            Field::DataValid => "dv",
+            Field::Data => "data",
+        }
+    }
+
+    fn is_flag(&self) -> bool {
+        matches!(self, Field::LengthValid | Field::DataValid)
+    }
+}
+
+/// Layout of an encoded line: field order, radix, widths and the separators
+/// between them.
+///
+/// The default matches the original testbench, `{lv}_{len:b32}_{dv}_{data:b8}`:
+/// a 32 bit length and one byte per beat. Formats can be written as specs
+/// like that one, where each of `lv`, `len`, `dv` and `data` appears exactly
+/// once and numbers take a radix letter (`b`inary, `h`ex or `d`ecimal) and a
+/// width in bits.
+///
+/// ```
+/// use adler32::{DataStream, LineFormat};
+///
+/// let format: LineFormat = "{dv} {data:h32} {lv} {len:d16}".parse().unwrap();
+/// let lines: Vec<_> = format.encode_packet(b"Wikipedia").unwrap().collect();
+/// // One length beat, then three words: "Wiki", "pedi", "a"
+/// assert_eq!(lines.len(), 4);
+/// assert_eq!(format.display(&lines[1]).to_string(), "1 696b6957 0 00000");
+///
+/// let (checksum, _) = DataStream::with_data_width(lines.into_iter(), 32)
+///     .next()
+///     .unwrap();
+/// assert_eq!(checksum, 0x11e6_0398);
+/// ```
+///
+/// When parsing, `_` and space are interchangeable in separators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineFormat {
+    segments: Vec<Segment>,
+}
+
+impl LineFormat {
+    /// Data widths the testbenches understand
+    pub const DATA_WIDTHS: [u32; 4] = [8, 16, 32, 64];
+
+    /// The original layout with different field widths
+    pub fn new(data_width: u32, length_width: u32) -> Self {
+        let spec = format!("{{lv}}_{{len:b{length_width}}}_{{dv}}_{{data:b{data_width}}}");
+        spec.parse().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Build a format from its pieces, checking that every field appears
+    /// exactly once with a supported width.
+    pub fn from_segments(segments: Vec<Segment>) -> std::result::Result<Self, FormatSpecError> {
+        for field in [
+            Field::LengthValid,
+            Field::Length,
+            Field::DataValid,
+            Field::Data,
+        ] {
+            let count = segments
+                .iter()
+                .filter(|segment| matches!(segment, Segment::Field { field: f, .. } if *f == field))
+                .count();
+            if count != 1 {
+                return Err(FormatSpecError(format!(
+                    "{{{}}} must appear exactly once",
+                    field.spec_name()
+                )));
+            }
+        }
+        for segment in &segments {
+            let Segment::Field {
+                field,
+                radix,
+                width,
+            } = segment
+            else {
+                continue;
+            };
+            let supported = match field {
+                Field::LengthValid | Field::DataValid => *width == 1 && *radix == Radix::Binary,
+                Field::Length => (1..=64).contains(width),
+                Field::Data => Self::DATA_WIDTHS.contains(width),
+            };
+            if !supported {
+                return Err(FormatSpecError(format!(
+                    "{{{}:{}{width}}} is not a supported width",
+                    field.spec_name(),
+                    radix.letter()
+                )));
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Same layout with the length and data fields written in `radix`
+    pub fn with_radix(mut self, radix: Radix) -> Self {
+        for segment in &mut self.segments {
+            if let Segment::Field {
+                field, radix: r, ..
+            } = segment
+            {
+                if !field.is_flag() {
+                    *r = radix;
+                }
+            }
+        }
+        self
+    }
+
+    fn width(&self, field: Field) -> u32 {
+        self.segments
+            .iter()
+            .find_map(|segment| match segment {
+                Segment::Field {
+                    field: f, width, ..
+                } if *f == field => Some(*width),
+                _ => None,
+            })
+            .expect("every field is in the format")
+    }
+
+    /// Bits of data per beat, a multiple of 8 up to 64
+    pub fn data_width(&self) -> u32 {
+        self.width(Field::Data)
+    }
+
+    /// Bits in the length field, 1 to 64
+    pub fn length_width(&self) -> u32 {
+        self.width(Field::Length)
+    }
+
+    /// Largest packet the length field can describe
+    pub fn max_length(&self) -> u64 {
+        u64::MAX >> (64 - self.length_width())
+    }
+
+    /// Bytes carried by a single data beat
+    pub fn bytes_per_beat(&self) -> usize {
+        self.data_width() as usize / 8
+    }
+
+    /// Parse a single line of encoded stimulus. Leading zeros can be left
+    /// out, and extra whitespace around fields is ignored. The length or
+    /// data field may be `x` or `z` digits while its valid bit is 0, and
+    /// reads as 0.
+    ///
+    /// ```
+    /// use adler32::LineFormat;
+    ///
+    /// let format = LineFormat::default();
+    /// let line = format.parse("  1_1011 _0_0 ").unwrap();
+    /// assert_eq!(line.length, 11);
+    /// let line = format.parse("1_1011_0_xxxxxxxx").unwrap();
+    /// assert_eq!(line.data, 0);
+    /// assert!(format.parse("0_0_1_xxxxxxxx").is_err());
+    /// ```
+    pub fn parse(&self, value: &str) -> std::result::Result<DataLine, ParseLineError> {
+        self.parse_exact(value).or_else(|error| {
+            let tidy = normalize(value);
+            match tidy != value {
+                true => self.parse_exact(&tidy).map_err(|_| error),
+                false => Err(error),
+            }
+        })
+    }
+
+    /// Parse a single line of encoded stimulus, which must be written exactly
+    /// as [`display`](Self::display) would write it, except that don't-care
+    /// fields may be `x` or `z`.
+    ///
+    /// ```
+    /// use adler32::{LineFormat, ParseLineError};
+    ///
+    /// let format = LineFormat::default();
+    /// assert!(format.parse_strict("1_00000000000000000000000000001011_0_00000000").is_ok());
+    /// assert!(matches!(
+    ///     format.parse_strict("1_1011_0_0"),
+    ///     Err(ParseLineError::NotCanonical { .. })
+    /// ));
+    /// ```
+    pub fn parse_strict(&self, value: &str) -> std::result::Result<DataLine, ParseLineError> {
+        let line = self.parse_exact(value)?;
+        if !self.is_canonical(&line, value) {
+            return Err(ParseLineError::NotCanonical {
+                expected: self.display(&line).to_string(),
+            });
+        }
+        Ok(line)
+    }
+
+    /// Whether `value` is `line` written exactly as [`display`](Self::display)
+    /// would write it, except that don't-care fields may be `x` or `z`.
+    /// Compares as it goes rather than writing the line out first.
+    ///
+    /// ```
+    /// use adler32::{DataLine, LineFormat};
+    ///
+    /// let format = LineFormat::new(8, 4);
+    /// let line = DataLine::length(3);
+    /// assert!(format.is_canonical(&line, "1_0011_0_00000000"));
+    /// assert!(format.is_canonical(&line, "1_0011_0_xxxxxxxx"));
+    /// assert!(!format.is_canonical(&line, "1_11_0_0"));
+    /// ```
+    pub fn is_canonical(&self, line: &DataLine, value: &str) -> bool {
+        /// Checks what's written against the rest of a line
+        struct Compare<'a>(std::str::Chars<'a>);
+
+        impl fmt::Write for Compare<'_> {
+            fn write_str(&mut self, canonical: &str) -> fmt::Result {
+                for want in canonical.chars() {
+                    match self.0.next() {
+                        Some(got) if want == got || (want == '0' && is_unknown(got)) => {}
+                        _ => return Err(fmt::Error),
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        let mut compare = Compare(value.chars());
+        fmt::Write::write_fmt(&mut compare, format_args!("{}", self.display(line))).is_ok()
+            && compare.0.next().is_none()
+    }
+
+    fn parse_exact(&self, value: &str) -> std::result::Result<DataLine, ParseLineError> {
+        let mut line = DataLine {
+            length_valid: false,
+            length: 0,
+            data_valid: false,
+            data: 0,
+        };
+        let mut rest = value;
+        // Fields written as x or z, only allowed if nothing reads them
+        let mut unknown = Vec::new();
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    rest =
+                        strip_separator(rest, literal).ok_or_else(|| ParseLineError::Mismatch {
+                            expected: literal.clone(),
+                        })?;
+                }
+                &Segment::Field {
+                    field,
+                    radix,
+                    width,
+                } => {
+                    // A field runs up to the next separator, or is exactly
+                    // as wide as its padding if another field follows it
+                    let end = match self.segments.get(index + 1) {
+                        Some(Segment::Literal(literal)) => {
+                            find_separator(rest, literal).unwrap_or(rest.len())
+                        }
+                        Some(Segment::Field { .. }) => radix.digits(width).min(rest.len()),
+                        None => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+                    };
+                    let (text, remainder) = rest.split_at(end);
+                    rest = remainder;
+                    if text.is_empty() {
+                        return Err(ParseLineError::Missing(field));
+                    }
+                    if !field.is_flag() && text.chars().any(is_unknown) {
+                        unknown.push((field, radix, text));
+                        continue;
+                    }
+                    let value = parse_field(field, radix, width, text)?;
+                    match field {
+                        Field::LengthValid => line.length_valid = value == 1,
+                        Field::Length => line.length = value,
+                        Field::DataValid => line.data_valid = value == 1,
+                        Field::Data => line.data = value,
+                    }
+                }
+            }
+        }
+        if !rest.trim().is_empty() {
+            return Err(ParseLineError::Trailing(rest.trim().to_owned()));
+        }
+        for (field, radix, text) in unknown {
+            let valid = match field {
+                Field::Length => line.length_valid,
+                _ => line.data_valid,
+            };
+            let digits = text
+                .chars()
+                .all(|c| is_unknown(c) || c.is_digit(radix.base()));
+            if valid || !digits {
+                return Err(ParseLineError::Invalid {
+                    field,
+                    value: text.to_owned(),
+                    radix,
+                });
+            }
+        }
+        Ok(line)
+    }
+
+    /// Writes `line` with this format's field widths.
+    pub fn display<'a>(&'a self, line: &'a DataLine) -> impl Display + 'a {
+        Formatted { format: self, line }
+    }
+
+    /// Write `lines` one per beat, each followed by a comment starting with
+    /// `prefix` saying what it carries: `# length 11` for a length beat and
+    /// the bytes of a packet for a data beat, like `# 'Wiki'`. Idle lines are
+    /// left as they are.
+    ///
+    /// ```
+    /// use adler32::LineFormat;
+    ///
+    /// let format = LineFormat::default();
+    /// let lines: Vec<_> = format.encode_packet(b"h\n").unwrap().collect();
+    /// let mut out = Vec::new();
+    /// format.write_annotated(&lines, "#", &mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "1_00000000000000000000000000000010_0_00000000  # length 2\n\
+    ///      0_00000000000000000000000000000000_1_01101000  # 'h'\n\
+    ///      0_00000000000000000000000000000000_1_00001010  # '\\n'\n"
+    /// );
+    /// ```
+    pub fn write_annotated(
+        &self,
+        lines: &[DataLine],
+        prefix: &str,
+        mut out: impl Write,
+    ) -> io::Result<()> {
+        let mut length = 0;
+        for line in lines {
+            write!(out, "{}", self.display(line))?;
+            if line.length_valid {
+                length = line.length;
+                write!(out, "  {prefix} length {length}")?;
+            }
+            if line.data_valid && length > 0 {
+                let count = length.min(self.bytes_per_beat() as u64);
+                let bytes = &line.data.to_le_bytes()[..count as usize];
+                write!(out, "  {prefix} '{}'", bytes.escape_ascii())?;
+                length -= count;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Guess the format `lines` are written in: the field order, radix and
+    /// widths, and the separators between fields. The first line, normally a
+    /// length beat, decides the layout; the digits in every line decide the
+    /// radix. Widths are read off the padding, so this works best on lines
+    /// written the way [`display`](Self::display) writes them. `None` if
+    /// the first line doesn't look like stimulus.
+    ///
+    /// ```
+    /// use adler32::LineFormat;
+    ///
+    /// let format = LineFormat::detect(["1_0000000000001011_0_00000000"]).unwrap();
+    /// assert_eq!(format.to_string(), "{lv}_{len:b16}_{dv}_{data:b8}");
+    /// let format = LineFormat::detect(["0 00000000 1 00011", "1 6c6c6568 0 00000"]).unwrap();
+    /// assert_eq!(format.to_string(), "{dv} {data:h32} {lv} {len:h20}");
+    /// let format = LineFormat::detect(["1 03 0 00", "0 00 1 61"]).unwrap();
+    /// assert_eq!(format.to_string(), "{lv} {len:h8} {dv} {data:h8}");
+    /// assert!(LineFormat::detect(["hello world"]).is_none());
+    /// ```
+    pub fn detect<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut lines = lines.into_iter();
+        let fields_of = |pieces: &[(bool, &'a str)]| {
+            pieces
+                .iter()
+                .filter(|(field, _)| *field)
+                .map(|(_, text)| *text)
+                .collect::<Vec<_>>()
+        };
+        let pieces = split_fields(lines.next()?);
+        let fields = fields_of(&pieces);
+        let [_, _, _, _] = fields[..] else {
+            return None;
+        };
+        let is_flag = |text: &str| text == "0" || text == "1";
+        // Each valid bit sits next to the value it qualifies, before or after
+        let flags_first = is_flag(fields[0]) && is_flag(fields[2]);
+        if !(flags_first || is_flag(fields[1]) && is_flag(fields[3])) {
+            return None;
+        }
+        let (first, second) = match flags_first {
+            true => ((0, 1), (2, 3)),
+            false => ((1, 0), (3, 2)),
+        };
+        // Stimulus opens with a length beat, so a set flag is the length's
+        let length_first = !(fields[first.0] == "0" && fields[second.0] == "1");
+        let (length, data) = match length_first {
+            true => (first.1, second.1),
+            false => (second.1, first.1),
+        };
+
+        // The radix with the fewest digits that covers every value seen
+        let mut radices = [looks(fields[length])?, looks(fields[data])?];
+        for line in lines {
+            let fields = fields_of(&split_fields(line));
+            if fields.len() != 4 {
+                continue;
+            }
+            for (radix, index) in radices.iter_mut().zip([length, data]) {
+                if let Some(seen) = looks(fields[index]) {
+                    *radix = (*radix).max(seen);
+                }
+            }
+        }
+        // Digits that could be binary follow the other field's radix
+        let (length_radix, data_radix) = match radices {
+            [Radix::Binary, other] | [other, Radix::Binary] => (other, other),
+            [length, data] => (length, data),
+        };
+        let matching = |radix: Radix, digits: usize| {
+            (1..=64).filter(move |&width| radix.digits(width) == digits)
+        };
+        let width = |radix: Radix, digits: usize, widths: &[u32]| {
+            matching(radix, digits)
+                .find(|width| widths.contains(width))
+                .or_else(|| matching(radix, digits).next_back())
+        };
+        // Hex without any of a-f reads as decimal, but only hex pads it to a
+        // usual width
+        let hex_if_padded = |radix: Radix, digits: usize, widths: &[u32]| {
+            let usual = |radix| matching(radix, digits).any(|width| widths.contains(&width));
+            match radix {
+                Radix::Decimal if !usual(Radix::Decimal) && usual(Radix::Hex) => Radix::Hex,
+                radix => radix,
+            }
+        };
+        let usual = [8, 16, 32, 64];
+        let length_radix = hex_if_padded(length_radix, fields[length].len(), &usual);
+        let data_radix = hex_if_padded(data_radix, fields[data].len(), &Self::DATA_WIDTHS);
+        let length_width = width(length_radix, fields[length].len(), &usual)?;
+        let data_width = width(data_radix, fields[data].len(), &Self::DATA_WIDTHS)?;
+
+        let mut field = 0;
+        let segments = pieces
+            .into_iter()
+            .map(|(is_field, text)| {
+                if !is_field {
+                    return Segment::Literal(text.to_owned());
+                }
+                field += 1;
+                let (flag, radix, width) = match field - 1 {
+                    index if index == length => (Field::Length, length_radix, length_width),
+                    index if index == data => (Field::Data, data_radix, data_width),
+                    index if (index == first.0) == length_first => {
+                        (Field::LengthValid, Radix::Binary, 1)
+                    }
+                    _ => (Field::DataValid, Radix::Binary, 1),
+                };
+                Segment::Field {
+                    field: flag,
+                    radix,
+                    width,
+                }
+            })
+            .collect();
+        Self::from_segments(segments).ok()
+    }
+
+    /// Stimulus for one packet: a length line followed by one line per beat.
+    /// The first byte of each beat is in the least significant bits.
+    ///
+    /// Fails if `data` is longer than the length field can describe.
+    pub fn encode_packet<'a>(&self, data: &'a [u8]) -> Result<impl Iterator<Item = DataLine> + 'a> {
+        let length = data.len() as u64;
+        if length > self.max_length() {
+            return Err(Error::PacketTooLong {
+                length,
+                width: self.length_width(),
+            });
+        }
+        let beats = data.chunks(self.bytes_per_beat()).map(|chunk| DataLine {
+            length_valid: false,
+            length: 0,
+            data_valid: true,
+            data: chunk
+                .iter()
+                .rev()
+                .fold(0, |word, &byte| (word << 8) | byte as u64),
+        });
+        Ok(iter::once(DataLine::length(length)).chain(beats))
+    }
+
+    /// Parse encoded stimulus, skipping `#` comment lines and comments at the
+    /// end of lines. Parse errors carry the (1-based) line number they
+    /// occurred on.
+    pub fn read_lines<R: BufRead>(&self, reader: R) -> impl Iterator<Item = Result<DataLine>> {
+        let format = self.clone();
+        reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !matches!(line, Ok(line) if line.starts_with('#'))) // Anything with a # is a comment
+            .map(move |(number, line)| {
+                let text = line.map_err(Error::Read)?;
+                format
+                    .parse(strip_comment(&text, &["#"]))
+                    .map_err(|source| Error::Parse {
+                        line: number + 1,
+                        text,
+                        source,
+                    })
+            })
+    }
+}
+
+impl Default for LineFormat {
+    fn default() -> Self {
+        Self::new(8, 32)
+    }
+}
+
+/// `text` without the comment at the end of it, if any. A comment starts
+/// with any of `prefixes`.
+///
+/// ```
+/// use adler32::strip_comment;
+///
+/// let line = "1_00001011_1_01101000  // 'h'";
+/// assert_eq!(strip_comment(line, &["#", "//"]), "1_00001011_1_01101000");
+/// assert_eq!(strip_comment(line, &["#"]), line);
+/// ```
+pub fn strip_comment<'a>(text: &'a str, prefixes: &[impl AsRef<str>]) -> &'a str {
+    prefixes
+        .iter()
+        .filter_map(|prefix| text.find(prefix.as_ref()))
+        .min()
+        .map_or(text, |start| text[..start].trim_end())
+}
+
+/// `text` without leading and trailing whitespace, whitespace next to a `_`,
+/// or runs of more than one space
+fn normalize(text: &str) -> String {
+    let mut tidy = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !tidy.is_empty() && !tidy.ends_with('_') && !word.starts_with('_') {
+            tidy.push(' ');
+        }
+        tidy.push_str(word);
+    }
+    tidy
+}
+
+/// A 4-state `x` or `z` digit from a simulator dump
+fn is_unknown(c: char) -> bool {
+    matches!(c, 'x' | 'X' | 'z' | 'Z')
+}
+
+/// `_` and space are the same thing in a separator
+fn separator_eq(a: char, b: char) -> bool {
+    a == b || (matches!(a, '_' | ' ') && matches!(b, '_' | ' '))
+}
+
+fn strip_separator<'a>(text: &'a str, separator: &str) -> Option<&'a str> {
+    let mut chars = text.char_indices();
+    for expected in separator.chars() {
+        let (_, c) = chars.next()?;
+        if !separator_eq(c, expected) {
+            return None;
+        }
+    }
+    Some(chars.as_str())
+}
+
+fn find_separator(text: &str, separator: &str) -> Option<usize> {
+    text.char_indices()
+        .map(|(index, _)| index)
+        .find(|&index| strip_separator(&text[index..], separator).is_some())
+}
+
+fn parse_field(
+    field: Field,
+    radix: Radix,
+    width: u32,
+    text: &str,
+) -> std::result::Result<u64, ParseLineError> {
+    let invalid = || ParseLineError::Invalid {
+        field,
+        value: text.to_owned(),
+        radix,
+    };
+    if field.is_flag() {
+        return match text {
+            "0" => Ok(0),
+            "1" => Ok(1),
+            _ => Err(invalid()),
+        };
+    }
+    let value = u64::from_str_radix(text, radix.base()).map_err(|_| invalid())?;
+    if value.checked_shr(width).unwrap_or(0) != 0 {
+        return Err(ParseLineError::TooWide {
+            field,
+            value: text.to_owned(),
+            width,
+        });
+    }
+    Ok(value)
+}
+
+/// `line` cut into runs of letters and digits, flagged `true`, and the text
+/// between them
+fn split_fields(line: &str) -> Vec<(bool, &str)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let field = c.is_ascii_alphanumeric();
+        match chars.peek() {
+            Some(&(index, next)) if next.is_ascii_alphanumeric() != field => {
+                pieces.push((field, &line[start..index]));
+                start = index;
+            }
+            Some(_) => {}
+            None => pieces.push((field, &line[start..])),
+        }
+    }
+    pieces
+}
+
+/// The radix a field written as `text` needs at least, `None` if it isn't a
+/// number
+fn looks(text: &str) -> Option<Radix> {
+    if text.chars().all(|c| c == '0' || c == '1') {
+        Some(Radix::Binary)
+    } else if text.chars().all(|c| c.is_ascii_digit()) {
+        Some(Radix::Decimal)
+    } else if text.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(Radix::Hex)
+    } else {
+        None
+    }
+}
+
+struct Formatted<'a> {
+    format: &'a LineFormat,
+    line: &'a DataLine,
+}
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = self.line;
+        for segment in &self.format.segments {
+            match *segment {
+                Segment::Literal(ref literal) => f.write_str(literal)?,
+                Segment::Field {
+                    field,
+                    radix,
+                    width,
+                } => {
+                    let value = match field {
+                        Field::LengthValid => line.length_valid as u64,
+                        Field::Length => line.length,
+                        Field::DataValid => line.data_valid as u64,
+                        Field::Data => line.data,
+                    };
+                    let digits = radix.digits(width);
+                    match radix {
+                        Radix::Binary => write!(f, "{value:0>digits$b}")?,
+                        Radix::Hex => write!(f, "{value:0>digits$x}")?,
+                        Radix::Decimal => write!(f, "{value:0>digits$}")?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A format spec that can't be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSpecError(String);
+
+impl Display for FormatSpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid line format: {}", self.0)
+    }
+}
+
+impl std::error::Error for FormatSpecError {}
+
+impl FromStr for LineFormat {
+    type Err = FormatSpecError;
+
+    /// Parse a spec like `{lv}_{len:b32}_{dv}_{data:b8}`
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let error = |message: String| FormatSpecError(message);
+        let mut segments = Vec::new();
+        let mut rest = spec;
+
+        while !rest.is_empty() {
+            let Some(open) = rest.find('{') else {
+                segments.push(Segment::Literal(rest.to_owned()));
+                break;
+            };
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_owned()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| error(format!("unclosed '{{' in '{spec}'")))?
+                + open;
+            let placeholder = &rest[open + 1..close];
+            rest = &rest[close + 1..];
+
+            let (name, layout) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+            let field = [
+                Field::LengthValid,
+                Field::Length,
+                Field::DataValid,
+                Field::Data,
+            ]
+            .into_iter()
+            .find(|field| field.spec_name() == name)
+            .ok_or_else(|| error(format!("unknown field '{name}'")))?;
+            let (radix, width) = if layout.is_empty() {
+                if !field.is_flag() {
+                    return Err(error(format!("{{{name}}} needs a radix and width")));
+                }
+                (Radix::Binary, 1)
+            } else {
+                let mut chars = layout.chars();
+                let radix = match chars.next() {
+                    Some('b') => Radix::Binary,
+                    Some('h' | 'x') => Radix::Hex,
+                    Some('d') => Radix::Decimal,
+                    _ => return Err(error(format!("unknown radix in '{{{placeholder}}}'"))),
+                };
+                let width = chars
+                    .as_str()
+                    .parse()
+                    .map_err(|_| error(format!("bad width in '{{{placeholder}}}'")))?;
+                (radix, width)
+            };
+            segments.push(Segment::Field {
+                field,
+                radix,
+                width,
+            });
+        }
+        Self::from_segments(segments)
+    }
+}
+
+impl Display for LineFormat {
+    /// Writes the spec this format can be parsed back from
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => f.write_str(literal)?,
+                Segment::Field { field, .. } if field.is_flag() => {
+                    write!(f, "{{{}}}", field.spec_name())?
+                }
+                Segment::Field {
+                    field,
+                    radix,
+                    width,
+                } => write!(f, "{{{}:{}{width}}}", field.spec_name(), radix.letter())?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Comments framing an encoded file: a header at the top saying how it was
+//! written, so it can be read without being told, and a trailer at the end
+//! so a damaged file is caught.
+
+use std::{fmt::Display, io::Write};
+
+use crate::{parse_checksum, Adler32, Algorithm, LineFormat};
+
+/// What the first line of a header says, followed by its version
+const MAGIC: &str = "adler32-format";
+
+/// What a trailer says, followed by the checksum
+const TRAILER: &str = "adler32-payload";
+
+/// What a header says about the file it starts:
+///
+/// ```text
+/// # adler32-format: v2
+/// # format: {lv}_{len:b32}_{dv}_{data:b8}
+/// # data-width: 8
+/// # length-width: 32
+/// # packets: 3
+/// # seed: 0
+/// # algorithm: adler32
+/// ```
+///
+/// Keys a reader doesn't know are skipped, so later versions can add more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u32,
+    pub format: Option<LineFormat>,
+    pub data_width: Option<u32>,
+    pub length_width: Option<u32>,
+    pub packets: Option<usize>,
+    /// Seed the stimulus was randomised with
+    pub seed: Option<u64>,
+    /// Checksum the expected values were computed with
+    pub algorithm: Option<Algorithm>,
+}
+
+impl Header {
+    /// Newest version this crate reads and the one it writes
+    pub const VERSION: u32 = 2;
+
+    /// Header for `packets` packets written in `format`
+    pub fn new(format: &LineFormat, packets: usize, seed: Option<u64>) -> Self {
+        Self {
+            version: Self::VERSION,
+            format: Some(format.clone()),
+            data_width: Some(format.data_width()),
+            length_width: Some(format.length_width()),
+            packets: Some(packets),
+            seed,
+            algorithm: None,
+        }
+    }
+
+    /// Write the header as comments starting with `prefix`.
+    pub fn write(&self, prefix: &str, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(out, "{prefix} {MAGIC}: v{}", self.version)?;
+        if let Some(format) = &self.format {
+            writeln!(out, "{prefix} format: {format}")?;
+        }
+        if let Some(data_width) = self.data_width {
+            writeln!(out, "{prefix} data-width: {data_width}")?;
+        }
+        if let Some(length_width) = self.length_width {
+            writeln!(out, "{prefix} length-width: {length_width}")?;
+        }
+        if let Some(packets) = self.packets {
+            writeln!(out, "{prefix} packets: {packets}")?;
+        }
+        if let Some(seed) = self.seed {
+            writeln!(out, "{prefix} seed: {seed}")?;
+        }
+        if let Some(algorithm) = self.algorithm {
+            writeln!(out, "{prefix} algorithm: {}", algorithm.name())?;
+        }
+        Ok(())
+    }
+
+    /// Read a header from the `comments` at the top of a file, with their
+    /// prefix already removed. `None` if they don't start with one.
+    ///
+    /// ```
+    /// use adler32::Header;
+    ///
+    /// let header = Header::parse([" adler32-format: v2", " packets: 3", " colour: blue"])
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(header.packets, Some(3));
+    /// assert!(Header::parse([" adler32-format: v9"]).is_err());
+    /// assert!(Header::parse([" just a comment"]).unwrap().is_none());
+    /// ```
+    pub fn parse<'a>(
+        comments: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Option<Self>, HeaderError> {
+        let mut comments = comments.into_iter().map(|comment| {
+            let (key, value) = comment.split_once(':').unwrap_or((comment, ""));
+            (key.trim(), value.trim())
+        });
+        let Some((MAGIC, version)) = comments.next() else {
+            return Ok(None);
+        };
+        let version = version
+            .strip_prefix('v')
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(|| HeaderError(format!("'{version}' is not a version")))?;
+        if version > Self::VERSION {
+            return Err(HeaderError(format!(
+                "written as {MAGIC} v{version}, newer than v{} this reads",
+                Self::VERSION
+            )));
+        }
+
+        let mut header = Self {
+            version,
+            format: None,
+            data_width: None,
+            length_width: None,
+            packets: None,
+            seed: None,
+            algorithm: None,
+        };
+        for (key, value) in comments {
+            let invalid = || HeaderError(format!("'{value}' is not a valid {key}"));
+            match key {
+                "format" => header.format = Some(value.parse().map_err(|_| invalid())?),
+                "data-width" => header.data_width = Some(value.parse().map_err(|_| invalid())?),
+                "length-width" => header.length_width = Some(value.parse().map_err(|_| invalid())?),
+                "packets" => header.packets = Some(value.parse().map_err(|_| invalid())?),
+                "seed" => header.seed = Some(value.parse().map_err(|_| invalid())?),
+                "algorithm" => {
+                    header.algorithm = Some(Algorithm::from_name(value).ok_or_else(invalid)?)
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(header))
+    }
+
+    /// The format the file is written in, from the format spec or else the
+    /// widths
+    pub fn line_format(&self) -> Option<LineFormat> {
+        match (&self.format, self.data_width, self.length_width) {
+            (Some(format), _, _) => Some(format.clone()),
+            (None, Some(data_width), Some(length_width))
+                if LineFormat::DATA_WIDTHS.contains(&data_width)
+                    && (1..=64).contains(&length_width) =>
+            {
+                Some(LineFormat::new(data_width, length_width))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checksum of the lines of an encoded file, written as a comment after
+/// them like `# adler32-payload: 32'h0c5b0289`. Covers the lines since the
+/// start of the file or the last trailer that aren't comments, each without
+/// any comment or trailing whitespace and ending in `\n`.
+///
+/// ```
+/// use adler32::Trailer;
+///
+/// let mut trailer = Trailer::new();
+/// trailer.push("1_00000000000000000000000000000001_0_00000000");
+/// trailer.push("0_00000000000000000000000000000000_1_01100001  ");
+/// let checksum = trailer.checksum();
+/// let mut out = Vec::new();
+/// trailer.write("#", &mut out).unwrap();
+/// let written = String::from_utf8(out).unwrap();
+/// assert_eq!(Trailer::parse(&written[1..]), Some(checksum));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Trailer(Adler32);
+
+impl Trailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a line that isn't a comment, with any comment after it already
+    /// removed. Trailing whitespace is left out.
+    pub fn push(&mut self, line: &str) {
+        self.0.update(line.trim_end().as_bytes());
+        self.0.update(b"\n");
+    }
+
+    /// Checksum of the lines pushed since the last trailer
+    pub fn checksum(&self) -> u32 {
+        self.0.finalize()
+    }
+
+    /// Start over, as after a trailer.
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// Write the trailer as a comment starting with `prefix`, and start over.
+    pub fn write(&mut self, prefix: &str, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(out, "{prefix} {TRAILER}: 32'h{:0>8x}", self.checksum())?;
+        self.reset();
+        Ok(())
+    }
+
+    /// The checksum a trailer comment records, with its prefix already
+    /// removed. `None` if it isn't a trailer.
+    pub fn parse(comment: &str) -> Option<u32> {
+        let (key, value) = comment.split_once(':')?;
+        match key.trim() == TRAILER {
+            true => parse_checksum(value).and_then(|checksum| checksum.try_into().ok()),
+            false => None,
+        }
+    }
+}
+
+/// A header that can't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderError(String);
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid header: {}", self.0)
+    }
+}
+
+impl std::error::Error for HeaderError {}
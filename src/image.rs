@@ -0,0 +1,218 @@
+//! Memory image formats for preloading packed stimulus into a BRAM or flash.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+use crate::Stimulus;
+
+impl Stimulus {
+    /// Write a `$readmemh` (`hex`) or `$readmemb` image, one word per line.
+    /// With `markers`, each packet is preceded by its `@address`, counting
+    /// words from `base`.
+    pub fn write_readmem(
+        &self,
+        mut out: impl Write,
+        hex: bool,
+        markers: bool,
+        base: u64,
+    ) -> io::Result<()> {
+        let mut starts = self.packet_starts().iter().peekable();
+        for (index, word) in self.words().enumerate() {
+            if starts.next_if_eq(&&index).is_some() && markers {
+                writeln!(out, "@{:x}", base + index as u64)?;
+            }
+            if hex {
+                writeln!(out, "{}", word.to_hex())?;
+            } else {
+                writeln!(out, "{}", word.to_bin())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a Xilinx `.coe` file to initialise a Vivado block memory, one
+    /// packed word per entry.
+    pub fn write_coe(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "; {} bit words, one per stimulus line",
+            self.word_width()
+        )?;
+        writeln!(out, "memory_initialization_radix=16;")?;
+        write!(out, "memory_initialization_vector=")?;
+        for (index, word) in self.words().enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            write!(out, "{separator}\n{}", word.to_hex())?;
+        }
+        writeln!(out, ";")
+    }
+
+    /// Write an Intel/Quartus `.mif` file to initialise an on-chip memory,
+    /// one packed word per address.
+    pub fn write_mif(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "-- {} bit words, one per stimulus line",
+            self.word_width()
+        )?;
+        writeln!(out, "DEPTH = {};", self.lines().len())?;
+        writeln!(out, "WIDTH = {};", self.word_width())?;
+        writeln!(out, "ADDRESS_RADIX = HEX;")?;
+        writeln!(out, "DATA_RADIX = HEX;")?;
+        writeln!(out, "CONTENT")?;
+        writeln!(out, "BEGIN")?;
+        for (address, word) in self.words().enumerate() {
+            writeln!(out, "{address:x} : {};", word.to_hex())?;
+        }
+        writeln!(out, "END;")
+    }
+
+    /// Every packed word as bytes, back to back
+    pub fn to_bytes(&self, endian: Endian) -> Vec<u8> {
+        self.words()
+            .flat_map(|word| match endian {
+                Endian::Little => word.to_le_bytes(),
+                Endian::Big => word.to_be_bytes().to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Byte order of packed words in byte oriented images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Flavour of Motorola S-record, named for its data and termination records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SrecType {
+    /// 16 bit addresses
+    S19,
+    /// 24 bit addresses
+    S28,
+    /// 32 bit addresses
+    #[default]
+    S37,
+}
+
+impl SrecType {
+    /// Bytes of address in each data record.
+    pub fn address_bytes(self) -> u8 {
+        match self {
+            SrecType::S19 => 2,
+            SrecType::S28 => 3,
+            SrecType::S37 => 4,
+        }
+    }
+}
+
+/// Write Intel HEX records of `bytes`, starting at byte address `base`, with
+/// up to `record_len` bytes each.
+pub fn write_ihex(mut out: impl Write, bytes: &[u8], base: u64, record_len: u8) -> io::Result<()> {
+    check_fits(base, bytes.len(), 32)?;
+    let mut upper = None;
+    let mut address = base as u32;
+    for chunk in bytes.chunks(record_len.max(1) as usize) {
+        // Records can't cross a 64k boundary, split them there
+        let room = (0x1_0000 - (address & 0xffff) as usize).min(chunk.len());
+        for part in [&chunk[..room], &chunk[room..]] {
+            if part.is_empty() {
+                continue;
+            }
+            if upper != Some(address >> 16) {
+                upper = Some(address >> 16);
+                let upper = (address >> 16) as u16;
+                write_ihex_record(&mut out, 0, 0x04, &upper.to_be_bytes())?;
+            }
+            write_ihex_record(&mut out, address as u16, 0x00, part)?;
+            address = address.wrapping_add(part.len() as u32);
+        }
+    }
+    write_ihex_record(&mut out, 0, 0x01, &[])
+}
+
+/// Write Motorola S-records of `bytes`, starting at byte address `base`, with
+/// up to `record_len` bytes each.
+pub fn write_srec(
+    mut out: impl Write,
+    bytes: &[u8],
+    kind: SrecType,
+    base: u64,
+    record_len: u8,
+) -> io::Result<()> {
+    let (data_kind, end_kind) = match kind {
+        SrecType::S19 => (1, 9),
+        SrecType::S28 => (2, 8),
+        SrecType::S37 => (3, 7),
+    };
+    let address_bytes = kind.address_bytes();
+    // The count byte covers address, data and checksum
+    let record_len = record_len.clamp(1, 0xff - address_bytes - 1) as usize;
+
+    check_fits(base, bytes.len(), address_bytes as u32 * 8)?;
+
+    write_srec_record(&mut out, 0, 2, 0, b"adler32")?;
+    let mut records = 0u32;
+    for (index, chunk) in bytes.chunks(record_len).enumerate() {
+        let address = base + (index * record_len) as u64;
+        write_srec_record(&mut out, data_kind, address_bytes, address, chunk)?;
+        records += 1;
+    }
+    if records <= 0xffff {
+        write_srec_record(&mut out, 5, 2, records as u64, &[])?;
+    } else if records <= 0xff_ffff {
+        write_srec_record(&mut out, 6, 3, records as u64, &[])?;
+    }
+    write_srec_record(&mut out, end_kind, address_bytes, base, &[])
+}
+
+/// `S<kind><count><address><data><checksum>`
+fn write_srec_record(
+    out: &mut impl Write,
+    kind: u8,
+    address_bytes: u8,
+    address: u64,
+    data: &[u8],
+) -> io::Result<()> {
+    let address = &address.to_be_bytes()[8 - address_bytes as usize..];
+    let count = (address.len() + data.len() + 1) as u8;
+    let sum = address
+        .iter()
+        .chain(data)
+        .fold(count, |sum, byte| sum.wrapping_add(*byte));
+    write!(out, "S{kind}{count:02X}")?;
+    for byte in address.iter().chain(data) {
+        write!(out, "{byte:02X}")?;
+    }
+    writeln!(out, "{:02X}", !sum)
+}
+
+/// `:LLAAAATT<data>CC`
+fn write_ihex_record(out: &mut impl Write, address: u16, kind: u8, data: &[u8]) -> io::Result<()> {
+    let [high, low] = address.to_be_bytes();
+    let sum = [data.len() as u8, high, low, kind]
+        .iter()
+        .chain(data)
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    write!(out, ":{:02X}{address:04X}{kind:02X}", data.len())?;
+    for byte in data {
+        write!(out, "{byte:02X}")?;
+    }
+    writeln!(out, "{:02X}", sum.wrapping_neg())
+}
+
+/// Make sure `len` bytes starting at `base` can be addressed with
+/// `address_bits`.
+fn check_fits(base: u64, len: usize, address_bits: u32) -> io::Result<()> {
+    if base + len as u64 > 1 << address_bits {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("image doesn't fit in a {address_bits} bit address space"),
+        ));
+    }
+    Ok(())
+}
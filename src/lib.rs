@@ -0,0 +1,128 @@
+//! Golden model for an FPGA Adler-32 implementation.
+//!
+//! The verilog testbench reads stimulus as a stream of [`DataLine`]s, one per
+//! clock cycle. This crate can build that stimulus from raw bytes
+//! ([`encode_packet`]), parse it back ([`read_lines`]) and replay it through
+//! the same checksum the hardware computes ([`DataStream`]).
+//!
+//! ```
+//! # #[cfg(feature = "std")] {
+//! use adler32::{adler32, encode_packet, DataStream};
+//!
+//! let lines = encode_packet(b"Wikipedia");
+//! let (checksum, content) = DataStream::new(lines).next().unwrap();
+//! assert_eq!(checksum, 0x11e6_0398);
+//! assert_eq!(checksum, adler32(b"Wikipedia").into());
+//! assert_eq!(content, b"Wikipedia");
+//! # }
+//! ```
+//!
+//! The checksum itself is computed by [`Implementation::Reference`], a byte
+//! at a time just as the hardware does, or for bulk data by faster
+//! implementations that give the same results. Those are built with the
+//! `fast` and `simd` cargo features, which are on by default, and can be
+//! picked at run time with [`Implementation::choose`].
+//!
+//! The same stimulus can be checked against other checksums a DUT computes,
+//! CRC-32 and Fletcher-16, -32 or -64, by picking an [`Algorithm`].
+//!
+//! Without the default `std` feature, only the checksums ([`Adler32`],
+//! [`RollingAdler32`], [`Crc32`], the Fletcher hashers and [`Checksum`])
+//! are built, with `#![no_std]`, for firmware that checks the same block on
+//! silicon.
+//!
+//! Built for wasm32, the crate exports a `Hasher` class and `checksum`,
+//! `encode` and `decode` functions to JavaScript through wasm-bindgen.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// The cdylib still needs std's panic handler on targets that have one
+#[cfg(all(not(feature = "std"), not(target_os = "none")))]
+extern crate std;
+
+mod algorithm;
+#[cfg(feature = "std")]
+mod avalon;
+#[cfg(feature = "std")]
+mod axis;
+mod checksum;
+#[cfg(feature = "std")]
+mod corners;
+mod crc32;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+mod expected;
+#[cfg(feature = "std")]
+mod fault;
+#[cfg(feature = "std")]
+mod ffi;
+mod fletcher;
+#[cfg(feature = "std")]
+mod format;
+#[cfg(feature = "std")]
+mod header;
+#[cfg(feature = "std")]
+mod image;
+#[cfg(feature = "std")]
+mod line;
+#[cfg(feature = "std")]
+mod lint;
+mod rolling;
+#[cfg(feature = "std")]
+mod schedule;
+mod simd;
+#[cfg(feature = "std")]
+mod stimulus;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod testbench;
+#[cfg(feature = "std")]
+mod vectors;
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+mod wasm;
+#[cfg(feature = "std")]
+mod zlib;
+
+pub use algorithm::{Algorithm, Checksum};
+#[cfg(feature = "std")]
+pub use avalon::{encode_avalon_packet, read_avalon, AvalonBeat, AvalonStream};
+#[cfg(feature = "std")]
+pub use axis::{encode_axis_packet, read_axis, AxisBeat, AxisStream};
+pub use checksum::{adler32, combine, vector_unit, Adler32, Implementation, MOD_ADLER};
+#[cfg(feature = "std")]
+pub use corners::corner_packets;
+pub use crc32::{crc32, Crc32};
+#[cfg(feature = "std")]
+pub use error::{Error, Result};
+#[cfg(feature = "std")]
+pub use expected::{
+    parse_checksum, parse_expect, read_checksums, read_log_checksums, read_manifest,
+};
+#[cfg(feature = "std")]
+pub use fault::{flip_bits, perturb_checksums};
+pub use fletcher::{fletcher16, fletcher32, fletcher64, Fletcher16, Fletcher32, Fletcher64};
+#[cfg(feature = "std")]
+pub use format::{strip_comment, FormatSpecError, LineFormat, Radix, Segment};
+#[cfg(feature = "std")]
+pub use header::{Header, HeaderError, Trailer};
+#[cfg(feature = "std")]
+pub use image::{write_ihex, write_srec, Endian, SrecType};
+#[cfg(feature = "std")]
+pub use line::{encode_packet, read_lines, DataLine, Field, ParseLineError};
+#[cfg(feature = "std")]
+pub use lint::{lint, repair, Change, Finding, Fix, Lint, Problem, Repair};
+pub use rolling::RollingAdler32;
+#[cfg(feature = "std")]
+pub use schedule::Rng;
+#[cfg(feature = "std")]
+pub use stimulus::{Stimulus, Word};
+#[cfg(feature = "std")]
+pub use stream::DataStream;
+#[cfg(feature = "std")]
+pub use testbench::{Ports, Testbench};
+#[cfg(feature = "std")]
+pub use vectors::Vectors;
+#[cfg(feature = "std")]
+pub use zlib::{read_zlib, zlib_stored, ZlibError, ZlibStream};
@@ -0,0 +1,154 @@
+use std::{fmt::Display, io::BufRead, str::FromStr};
+
+use crate::{LineFormat, Radix, Result};
+
+/// A single cycle of testbench stimulus.
+///
+/// Printed as `<length_valid>_<length:32b>_<data_valid>_<data:8b>`, e.g.
+/// `1_00000000000000000000000000001011_0_00000000`. See [`LineFormat`] for
+/// wider data buses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataLine {
+    /// `length` holds the size of the next packet
+    pub length_valid: bool,
+    pub length: u64,
+    /// `data` holds a beat of the current packet
+    pub data_valid: bool,
+    pub data: u64,
+}
+
+impl DataLine {
+    /// Line announcing a packet of `length` bytes.
+    pub fn length(length: u64) -> Self {
+        Self {
+            length_valid: true,
+            length,
+            data_valid: false,
+            data: 0,
+        }
+    }
+}
+
+/// The fields of a [`DataLine`], in the order they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    LengthValid,
+    Length,
+    DataValid,
+    Data,
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Field::LengthValid => "length valid",
+            Field::Length => "length",
+            Field::DataValid => "data valid",
+            Field::Data => "data",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseLineError {
+    /// The line ended before this field
+    Missing(Field),
+    /// The field is there but isn't a valid value
+    Invalid {
+        field: Field,
+        value: String,
+        radix: Radix,
+    },
+    /// The field's value doesn't fit in `width` bits
+    TooWide {
+        field: Field,
+        value: String,
+        width: u32,
+    },
+    /// A separator between fields isn't there
+    Mismatch { expected: String },
+    /// Text left over after the last field
+    Trailing(String),
+    /// The line reads fine but isn't written the way the format writes it
+    NotCanonical { expected: String },
+    /// A column of a protocol other than the length prefixed one is missing
+    MissingColumn(&'static str),
+    /// A column of a protocol other than the length prefixed one is invalid
+    InvalidColumn { column: &'static str, value: String },
+}
+
+impl Display for ParseLineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseLineError::Missing(field) => write!(f, "{field} field is missing"),
+            ParseLineError::Invalid {
+                field: field @ (Field::LengthValid | Field::DataValid),
+                value,
+                ..
+            } => write!(f, "{field} field '{value}' is not 0 or 1"),
+            ParseLineError::Invalid {
+                field,
+                value,
+                radix,
+            } => write!(f, "{field} field '{value}' is not valid {radix}"),
+            ParseLineError::TooWide {
+                field,
+                value,
+                width,
+            } => write!(f, "{field} field '{value}' is wider than {width} bits"),
+            ParseLineError::Mismatch { expected } => write!(f, "expected '{expected}'"),
+            ParseLineError::Trailing(rest) => write!(f, "unexpected '{rest}' at end of line"),
+            ParseLineError::NotCanonical { expected } => {
+                write!(f, "not written the way the format writes it, '{expected}'")
+            }
+            ParseLineError::MissingColumn(column) => write!(f, "{column} column is missing"),
+            ParseLineError::InvalidColumn { column, value } => {
+                write!(f, "{column} column '{value}' is invalid")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseLineError {}
+
+impl FromStr for DataLine {
+    type Err = ParseLineError;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        LineFormat::default().parse(value)
+    }
+}
+
+impl From<u8> for DataLine {
+    fn from(value: u8) -> Self {
+        Self {
+            length_valid: false,
+            length: 0,
+            data_valid: true,
+            data: value as u64,
+        }
+    }
+}
+
+impl Display for DataLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", LineFormat::default().display(self))
+    }
+}
+
+/// Stimulus for one packet: a length line followed by one line per byte.
+///
+/// # Panics
+///
+/// If `data` is too long for the 32 bit length field.
+pub fn encode_packet(data: &[u8]) -> impl Iterator<Item = DataLine> + '_ {
+    LineFormat::default()
+        .encode_packet(data)
+        .expect("packet too long for the length field")
+}
+
+/// Parse encoded stimulus, skipping `#` comment lines. Parse errors carry the
+/// (1-based) line number they occurred on.
+pub fn read_lines<R: BufRead>(reader: R) -> impl Iterator<Item = Result<DataLine>> {
+    LineFormat::default().read_lines(reader)
+}
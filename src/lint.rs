@@ -0,0 +1,386 @@
+//! Checks that encoded stimulus is well formed, without replaying it, and
+//! repairs for what can be put right.
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead},
+};
+
+use crate::{strip_comment, DataLine, LineFormat, ParseLineError, Segment, Trailer};
+
+/// Something wrong with a line of length prefixed stimulus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The line isn't UTF-8
+    NotUtf8,
+    /// The line can't be read at all
+    Unparsable(ParseLineError),
+    /// The line reads fine but isn't written the way the format writes it,
+    /// e.g. a field is missing its leading zeros
+    NotCanonical { expected: String },
+    /// A length beat arrived with `remaining` bytes of the last packet due
+    LengthMidPacket { remaining: u64 },
+    /// A data beat arrived with no packet in progress
+    DataOutsidePacket,
+    /// The input ended with `remaining` bytes of the last packet due
+    Truncated { remaining: u64 },
+    /// A trailer records `expected` but the lines above it add up to `actual`
+    Trailer { expected: u32, actual: u32 },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::NotUtf8 => f.write_str("not UTF-8"),
+            Problem::Unparsable(e) => write!(f, "{e}"),
+            Problem::NotCanonical { expected } => write!(f, "should be written '{expected}'"),
+            Problem::LengthMidPacket { remaining } => write!(
+                f,
+                "length beat with {remaining} bytes of the last packet still due"
+            ),
+            Problem::DataOutsidePacket => f.write_str("data beat outside a packet"),
+            Problem::Truncated { remaining } => {
+                write!(
+                    f,
+                    "input ends with {remaining} bytes of the last packet due"
+                )
+            }
+            Problem::Trailer { expected, actual } => write!(
+                f,
+                "trailer says 32'h{expected:0>8x} but the lines above have checksum \
+                 32'h{actual:0>8x}"
+            ),
+        }
+    }
+}
+
+/// A [`Problem`] on line number `line` (1-based)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+/// What [`lint`] found in a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lint {
+    pub findings: Vec<Finding>,
+    /// Packets announced by a length beat
+    pub packets: usize,
+    /// Lines that aren't comments
+    pub lines: usize,
+}
+
+/// Check every line of the stimulus in `reader`, skipping comments starting
+/// with any of `comment_prefixes`. Only fails if `reader` does.
+///
+/// ```
+/// use adler32::{lint, LineFormat, Problem};
+///
+/// let stimulus = "1_00000000000000000000000000000010_0_00000000\n\
+///                 0_00000000000000000000000000000000_1_01101000\n\
+///                 1_00000000000000000000000000000001_0_00000000\n";
+/// let report = lint(&LineFormat::default(), stimulus.as_bytes(), &["#"]).unwrap();
+/// assert_eq!(report.packets, 2);
+/// assert_eq!(report.findings[0].line, 3);
+/// assert_eq!(report.findings[0].problem, Problem::LengthMidPacket { remaining: 1 });
+/// ```
+pub fn lint(
+    format: &LineFormat,
+    mut reader: impl BufRead,
+    comment_prefixes: &[impl AsRef<str>],
+) -> io::Result<Lint> {
+    let mut report = Lint::default();
+    let mut trailer = Trailer::new();
+    let mut remaining = 0;
+    let mut last = 0;
+    let mut bytes = Vec::new();
+    for number in 1.. {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            break;
+        }
+        last = number;
+        let mut found = |problem| {
+            report.findings.push(Finding {
+                line: number,
+                problem,
+            })
+        };
+        let Ok(text) = std::str::from_utf8(&bytes) else {
+            found(Problem::NotUtf8);
+            continue;
+        };
+        let text = text.trim_end_matches(['\n', '\r']);
+        let comment = comment_prefixes
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix.as_ref()));
+        if let Some(comment) = comment {
+            if let Some(expected) = Trailer::parse(comment) {
+                let actual = trailer.checksum();
+                if expected != actual {
+                    found(Problem::Trailer { expected, actual });
+                }
+                trailer.reset();
+            }
+            continue;
+        }
+        report.lines += 1;
+
+        let code = strip_comment(text, comment_prefixes);
+        trailer.push(code);
+        let line = match format.parse(code) {
+            Ok(line) => line,
+            Err(e) => {
+                found(Problem::Unparsable(e));
+                continue;
+            }
+        };
+        if format.parse_strict(code).is_err() {
+            let expected = format.display(&line).to_string();
+            found(Problem::NotCanonical { expected });
+        }
+        if line.length_valid {
+            if remaining > 0 {
+                found(Problem::LengthMidPacket { remaining });
+            }
+            remaining = line.length;
+            report.packets += 1;
+        }
+        if line.data_valid {
+            if remaining == 0 {
+                found(Problem::DataOutsidePacket);
+            }
+            remaining = remaining.saturating_sub(format.bytes_per_beat() as u64);
+        }
+    }
+    if remaining > 0 {
+        report.findings.push(Finding {
+            line: last,
+            problem: Problem::Truncated { remaining },
+        });
+    }
+    Ok(report)
+}
+
+/// A change [`repair`] made to a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Characters that can't be part of the format were removed
+    Stripped(String),
+    /// The line was rewritten the way the format writes it
+    Rewritten,
+    /// The length field was changed to match the data beats that follow
+    Length { from: u64, to: u64 },
+    /// The line couldn't be read, or was a data beat before any packet, and
+    /// was left out
+    Dropped,
+    /// The trailer was changed to match the lines above it
+    Trailer { from: u32, to: u32 },
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fix::Stripped(removed) => write!(f, "removed {removed:?}"),
+            Fix::Rewritten => f.write_str("rewrote the way the format writes it"),
+            Fix::Length { from, to } => write!(f, "changed length from {from} to {to}"),
+            Fix::Dropped => f.write_str("dropped"),
+            Fix::Trailer { from, to } => {
+                write!(f, "changed trailer from 32'h{from:0>8x} to 32'h{to:0>8x}")
+            }
+        }
+    }
+}
+
+/// A [`Fix`] to line number `line` (1-based) of the original
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub line: usize,
+    pub fix: Fix,
+}
+
+/// Stimulus put right by [`repair`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Repair {
+    /// The corrected file, one entry per line
+    pub lines: Vec<String>,
+    pub changes: Vec<Change>,
+}
+
+/// A line of stimulus [`repair`] is keeping
+enum Kept {
+    Comment(String),
+    /// A trailer recording `checksum`, to be written afresh after `prefix`
+    Trailer {
+        number: usize,
+        prefix: String,
+        checksum: u32,
+    },
+    Line {
+        number: usize,
+        line: DataLine,
+        comment: String,
+    },
+}
+
+/// Fix what [`lint`] complains about where it can: strip characters that
+/// can't be part of the format, rewrite lines the way the format writes
+/// them and set each length field to cover the data beats up to the next
+/// length beat. Lines that still can't be read are dropped. Comments are
+/// kept, except that trailers get the checksum of the repaired lines. Only
+/// fails if `reader` does.
+///
+/// ```
+/// use adler32::{repair, Fix, LineFormat};
+///
+/// let stimulus = "1_101_0_0\n0_0_1_1101000!\n";
+/// let repaired = repair(&LineFormat::default(), stimulus.as_bytes(), &["#"]).unwrap();
+/// assert_eq!(
+///     repaired.lines,
+///     [
+///         "1_00000000000000000000000000000001_0_00000000",
+///         "0_00000000000000000000000000000000_1_01101000",
+///     ]
+/// );
+/// assert_eq!(repaired.changes[0].fix, Fix::Rewritten);
+/// assert_eq!(repaired.changes[1].fix, Fix::Length { from: 5, to: 1 });
+/// assert_eq!(repaired.changes[2].fix, Fix::Stripped("!".to_owned()));
+/// ```
+pub fn repair(
+    format: &LineFormat,
+    mut reader: impl BufRead,
+    comment_prefixes: &[impl AsRef<str>],
+) -> io::Result<Repair> {
+    let literals = format
+        .segments()
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Literal(literal) => Some(literal.as_str()),
+            Segment::Field { .. } => None,
+        })
+        .collect::<String>();
+    let legal = |c: char| c.is_ascii_alphanumeric() || c.is_whitespace() || literals.contains(c);
+
+    let mut repaired = Repair::default();
+    let mut kept = Vec::new();
+    let mut bytes = Vec::new();
+    for number in 1.. {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            break;
+        }
+        let mut fix = |fix| repaired.changes.push(Change { line: number, fix });
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_end_matches(['\n', '\r']);
+        let prefix = comment_prefixes
+            .iter()
+            .map(AsRef::as_ref)
+            .find(|&prefix| text.starts_with(prefix));
+        if let Some(prefix) = prefix {
+            kept.push(match Trailer::parse(&text[prefix.len()..]) {
+                Some(checksum) => Kept::Trailer {
+                    number,
+                    prefix: prefix.to_owned(),
+                    checksum,
+                },
+                None => Kept::Comment(text.to_owned()),
+            });
+            continue;
+        }
+
+        let code = strip_comment(text, comment_prefixes);
+        let comment = text[code.len()..].to_owned();
+        let (code, removed): (String, String) = code.chars().partition(|&c| legal(c));
+        if !removed.is_empty() {
+            fix(Fix::Stripped(removed));
+        }
+        let Ok(line) = format.parse(&code) else {
+            fix(Fix::Dropped);
+            continue;
+        };
+        if format.parse_strict(&code).is_err() {
+            fix(Fix::Rewritten);
+        }
+        kept.push(Kept::Line {
+            number,
+            line,
+            comment,
+        });
+    }
+
+    // Count the data beats after each length beat, up to the next one
+    let bytes_per_beat = format.bytes_per_beat() as u64;
+    let mut packet = None;
+    let mut beats = vec![0; kept.len()];
+    for (index, entry) in kept.iter().enumerate() {
+        let Kept::Line { line, .. } = entry else {
+            continue;
+        };
+        if line.length_valid {
+            packet = Some(index);
+        }
+        if let (true, Some(start)) = (line.data_valid, packet) {
+            beats[start] += 1;
+        }
+    }
+
+    let mut in_packet = false;
+    let mut trailer = Trailer::new();
+    for (entry, beats) in kept.into_iter().zip(beats) {
+        let (number, mut line, comment) = match entry {
+            Kept::Comment(text) => {
+                repaired.lines.push(text);
+                continue;
+            }
+            Kept::Trailer {
+                number,
+                prefix,
+                checksum,
+            } => {
+                if trailer.checksum() != checksum {
+                    repaired.changes.push(Change {
+                        line: number,
+                        fix: Fix::Trailer {
+                            from: checksum,
+                            to: trailer.checksum(),
+                        },
+                    });
+                }
+                let mut out = Vec::new();
+                trailer.write(&prefix, &mut out)?;
+                let out = String::from_utf8(out).expect("trailers are ASCII");
+                repaired.lines.push(out.trim_end().to_owned());
+                continue;
+            }
+            Kept::Line {
+                number,
+                line,
+                comment,
+            } => (number, line, comment),
+        };
+        let mut fix = |fix| repaired.changes.push(Change { line: number, fix });
+        if line.length_valid {
+            in_packet = true;
+            // A short last beat is fine, as long as the length needs every beat
+            let fits = (beats * bytes_per_beat).saturating_sub(bytes_per_beat - 1)
+                ..=beats * bytes_per_beat;
+            if !fits.contains(&line.length) {
+                let to = (beats * bytes_per_beat).min(format.max_length());
+                fix(Fix::Length {
+                    from: line.length,
+                    to,
+                });
+                line.length = to;
+            }
+        } else if line.data_valid && !in_packet {
+            fix(Fix::Dropped);
+            continue;
+        }
+        let code = format.display(&line).to_string();
+        trailer.push(&code);
+        repaired.lines.push(code + &comment);
+    }
+    repaired.changes.sort_by_key(|change| change.line);
+    Ok(repaired)
+}
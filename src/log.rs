@@ -0,0 +1,87 @@
+//! Diagnostics on stderr through `tracing`: warnings always, and with -v
+//! notes, a span per packet with its timing and a trace of each beat.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{
+        format::{FmtSpan, Writer},
+        FmtContext, FormatEvent, FormatFields, FormattedFields,
+    },
+    registry::LookupSpan,
+};
+
+use crate::progress;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `warning: …` lines, each after the spans it happened in
+    #[default]
+    Text,
+    /// One JSON object per event, with its level, time and spans
+    Json,
+}
+
+/// Send diagnostics to stderr in `format`: warnings, errors and what a
+/// server is doing unless `quiet`, plus notes and packet spans with -v and
+/// every beat with -vv.
+pub fn init(format: LogFormat, verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_writer(progress::stderr)
+        .with_ansi(false)
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE);
+    match format {
+        LogFormat::Text => builder.event_format(Plain).init(),
+        LogFormat::Json => builder.json().with_current_span(false).init(),
+    }
+}
+
+/// The `warning: …` lines the tools always printed, prefixed with any spans
+/// like `packet{index=3}: `
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let label = match *event.metadata().level() {
+            Level::ERROR => "error",
+            Level::WARN => "warning",
+            Level::INFO => "info",
+            Level::DEBUG => "debug",
+            Level::TRACE => "trace",
+        };
+        write!(writer, "{label}: ")?;
+        for span in ctx
+            .event_scope()
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+        {
+            write!(writer, "{}", span.name())?;
+            let extensions = span.extensions();
+            match extensions.get::<FormattedFields<N>>() {
+                Some(fields) if !fields.is_empty() => write!(writer, "{{{fields}}}: ")?,
+                _ => write!(writer, ": ")?,
+            }
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
@@ -1,238 +1,3436 @@
 use std::{
-    fmt::Display,
-    fs::OpenOptions,
-    io::{BufRead, BufReader, Write},
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    hint,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
     iter,
-    str::FromStr,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
 };
 
-use clap::{Parser, Subcommand};
+use adler32::{
+    adler32, combine, corner_packets, encode_avalon_packet, encode_axis_packet, flip_bits, lint,
+    parse_checksum, parse_expect, perturb_checksums, read_avalon, read_axis, read_checksums,
+    read_log_checksums, read_manifest, read_zlib, repair, strip_comment, vector_unit, write_ihex,
+    write_srec, zlib_stored, Adler32, Algorithm, AvalonBeat, AvalonStream, AxisBeat, AxisStream,
+    Checksum, DataLine, DataStream, Endian, Error, Header, Implementation, LineFormat,
+    ParseLineError, Radix, Result, Rng, SrecType, Stimulus, Testbench, Trailer, Vectors,
+    ZlibStream, MOD_ADLER,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use constraints::Constraints;
+use log::LogFormat;
+use memmap2::Mmap;
+use output::{ByteStats, Check, Output, OutputFormat, PacketDiff, Stats};
+use rayon::prelude::*;
+use regex::Regex;
+use timing::TimingFormat;
+use tracing::{debug, debug_span, error, info, trace, warn, Level};
+
+mod constraints;
+mod log;
+mod output;
+mod progress;
+mod timing;
+
+/// Filename standing in for stdin/stdout
+const STDIO: &str = "-";
+
+/// Output files written under a temporary name so far. They're renamed into
+/// place once the command succeeds, so a run that fails or is killed never
+/// leaves a half-written file behind.
+static STAGED: Mutex<Vec<Staged>> = Mutex::new(Vec::new());
+
+/// Map input files into memory instead of reading them, for --mmap
+static MMAP: AtomicBool = AtomicBool::new(false);
+
+/// Lines read to guess the format of a file
+const SNIFF_LINES: usize = 16;
+
+/// Lines parsed together with --jobs
+const BATCH_LINES: usize = 1 << 14;
+
+/// Unchanged beats shown either side of a change by `diff`
+const DIFF_CONTEXT: usize = 1;
+
+/// Cap on `generate --corners` packet length, so a wide length field doesn't
+/// ask for gigabytes of stimulus
+const MAX_CORNER_LENGTH: u64 = 1 << 18;
 
 #[derive(Debug, Subcommand, Clone)]
 enum Mode {
     /// Encode the file in the format to be read by the verilog
-    Encode { dest_file: String },
-    /// Decode the file to a human readable format
-    Decode { dest_file: String },
-    /// Hash the file, do not write to file
-    Hash,
+    Encode {
+        /// Packets to encode, one per line, `-` for stdin. An empty line is
+        /// a zero-length packet
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Encode everything but write nothing, only saying how much would
+        /// be written
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Merge consecutive packets of encoded stimulus into larger ones, for
+    /// bursts of long packets built from existing vectors
+    Merge {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Most bytes in a merged packet. Longer packets are left as they are
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        max_size: u64,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Join encoded files into one, reading every packet of each in turn
+    Cat {
+        /// Files to be read, `-` for stdin. Glob patterns are expanded
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Destination file, `-` for stdout
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Write each packet of an encoded file to a file of its own,
+    /// `packet_0000.txt` onwards, so one can be re-run alone
+    Split {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Directory to write the packets to, created if missing
+        #[arg(long)]
+        out_dir: PathBuf,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Show one packet of an encoded file: its checksum, content and the
+    /// lines carrying it
+    Extract {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Index of the packet, counting from 0
+        #[arg(long)]
+        packet: usize,
+        /// Write the packet as stimulus of its own here too, `-` for stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// List the packets of an encoded file whose content contains a pattern
+    Grep {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Text to look for in each packet's content
+        pattern: String,
+        /// Read the pattern as a regex instead of plain text
+        #[arg(short = 'E', long)]
+        regex: bool,
+    },
+    /// Keep only the packets of an encoded file with lengths in a range
+    Filter {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Fewest bytes in a packet kept
+        #[arg(long, default_value_t = 0)]
+        min_len: usize,
+        /// Most bytes in a packet kept
+        #[arg(long, default_value_t = usize::MAX, hide_default_value = true)]
+        max_len: usize,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Put the packets of an encoded file in a random order, the same for
+    /// the same --seed
+    Shuffle {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Put the packets of an encoded file in order
+    Sort {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// What to order packets by. Packets that tie keep their order
+        #[arg(long, value_enum, default_value_t)]
+        key: SortKey,
+        /// Largest first
+        #[arg(long)]
+        reverse: bool,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Drop packets of an encoded file that repeat an earlier one
+    Dedup {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Count packets with the same checksum as repeats, even if their
+        /// bytes differ
+        #[arg(long)]
+        by_checksum: bool,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Rewrite encoded stimulus in another line format, e.g. to widen the
+    /// length field. Idle lines and comments are kept unless the data width
+    /// changes, which means packing the packets into beats afresh
+    Transcode {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Line format to write, e.g. `{lv}_{len:b32}_{dv}_{data:b8}`
+        #[arg(long)]
+        to: LineFormat,
+        /// Transcode everything but write nothing, only saying how much
+        /// would be written
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Summarise what encoded stimulus exercises: packet lengths and how
+    /// much of the time the bus is idle
+    Stats {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Also report how often each byte value appears and the entropy
+        /// of each packet
+        #[arg(long)]
+        bytes: bool,
+    },
+    /// Compare the packets of two encoded files by index, showing the length,
+    /// checksum and beats of each one that differs
+    Diff {
+        /// First file, `-` for stdin
+        a: String,
+        /// Second file, `-` for stdin
+        b: String,
+    },
+    /// Check that encoded stimulus is well formed, listing every problem by
+    /// line number
+    Lint {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+    },
+    /// Fix what lint finds where it can, listing every change by line number
+    Repair {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+    },
+    /// Rewrite encoded stimulus with a comment on each line saying what it
+    /// carries
+    Annotate {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+    },
+    /// Decode the file to a human readable format, checking each packet
+    /// against any `expect` comment after it
+    Decode {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Decode everything but write nothing, only saying how much would
+        /// be written
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        window: Window,
+    },
+    /// Hash the files, do not write to file
+    Hash {
+        /// Files to be read, `-` for stdin. Glob patterns are expanded
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Hash the file byte for byte instead of reading it as encoded lines
+        #[arg(long, conflicts_with_all = ["skip", "take"])]
+        raw: bool,
+        /// Print `<checksum>  <filename>` lines like md5sum
+        #[arg(long, requires = "raw")]
+        manifest: bool,
+        /// Read a manifest written by --manifest and check the files it lists
+        #[arg(short, long, conflicts_with_all = ["raw", "skip", "take"])]
+        check: bool,
+        #[command(flatten)]
+        window: Window,
+    },
+    /// Hash each packet and compare against a list of expected checksums
+    Verify {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// File with one expected checksum per packet, e.g. `32'h091e01de`.
+        /// Without it, packets are checked against the `expect` comments
+        /// `encode --embed-expected` writes
+        #[arg(long)]
+        expected: Option<String>,
+        #[command(flatten)]
+        window: Window,
+    },
+    /// Check the Adler-32 trailer of zlib streams against the data they
+    /// hold. Streams of compressed blocks need the `inflate` feature
+    VerifyZlib {
+        /// Files of one zlib stream each, `-` for stdin. Glob patterns are
+        /// expanded
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Combine the Adler-32 checksums of shards of a file, hashed
+    /// separately, into the checksum of the whole file
+    Combine {
+        /// Checksum and length in bytes of each shard, in order, e.g.
+        /// `32'h11e60398:9`
+        #[arg(required = true, value_name = "CHECKSUM:LENGTH", value_parser = parse_shard)]
+        shards: Vec<(u32, u64)>,
+    },
+    /// Compare the checksums a DUT produced against the golden model's,
+    /// packet by packet
+    Compare {
+        /// Checksums from the DUT, one per packet, e.g. `32'h091e01de`, or a
+        /// simulator log with --pattern
+        dut_results: String,
+        /// Encoded stimulus the DUT was fed, `-` for stdin
+        golden: String,
+        /// Read the golden file as one packet's content per line instead of
+        /// as encoded stimulus
+        #[arg(long)]
+        raw: bool,
+        /// Regex finding the checksums the DUT reported in a simulator log,
+        /// e.g. `CHECKSUM=([0-9a-f]{8})`. The first capture group (or the
+        /// whole match) is the checksum
+        #[arg(long)]
+        pattern: Option<Regex>,
+    },
+    /// Encode packets, run a simulator on them and check the checksums it
+    /// reports against the golden model, all in one go
+    Run {
+        /// Packets to send, one per line, `-` for stdin
+        filename: String,
+        /// Where to write stimulus for the simulator
+        #[arg(long, default_value = "stimulus.txt")]
+        stimulus: String,
+        #[command(flatten)]
+        options: EncodeArgs,
+        /// Regex finding each checksum the DUT reports in the simulator's
+        /// output. The first capture group (or the whole match) is the
+        /// checksum
+        #[arg(long, default_value = "CHECKSUM=([0-9a-fA-F]{8})")]
+        pattern: Regex,
+        /// Look for checksums in this file once the simulator finishes,
+        /// instead of in its stdout
+        #[arg(long)]
+        log: Option<String>,
+        /// Simulator command line, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Write stimulus for random packets, with their golden checksums
+    Generate {
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Write known-tricky packets instead of random ones: empty, single
+        /// byte, lengths around the modulus, long all-0xff runs and the
+        /// longest packet the length field allows (up to 256 KiB)
+        #[arg(long, conflicts_with_all = ["packets", "min_len", "max_len"])]
+        corners: bool,
+        /// JSON file constraining packet count, lengths and byte values, and
+        /// the --bubbles and --ready-duty to use unless given here
+        #[arg(long, conflicts_with_all = ["corners", "packets", "min_len", "max_len"])]
+        constraints: Option<String>,
+        /// Number of packets
+        #[arg(long, default_value_t = 1)]
+        packets: usize,
+        /// Fewest bytes in a packet
+        #[arg(long, default_value_t = 0)]
+        min_len: usize,
+        /// Most bytes in a packet
+        #[arg(long, default_value_t = 256)]
+        max_len: usize,
+        #[command(flatten)]
+        options: EncodeArgs,
+    },
+    /// Flip random payload bits in encoded stimulus, for checking that the
+    /// DUT catches corrupted packets
+    Corrupt {
+        /// Source file to be read, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Number of bits to flip
+        #[arg(long, default_value_t = 1)]
+        flips: usize,
+        /// Seed picking the bits
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Write the index of every packet whose checksum the flips changed
+        /// here, one per line. Exactly these should fail
+        #[arg(long)]
+        manifest: String,
+    },
+    /// Change some checksums in an expected results file, for checking that
+    /// the testbench reports mismatches instead of passing everything
+    PerturbExpected {
+        /// Expected checksums, one per line, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Percentage of checksums to change, rounded to the nearest whole
+        /// checksum
+        #[arg(long, value_parser = parse_percent)]
+        percent: f64,
+        /// Seed picking the checksums
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Write the index of every changed checksum here, one per line.
+        /// Exactly these should fail
+        #[arg(long)]
+        manifest: String,
+    },
+    /// Write a self-checking testbench for the packets in a file, replaying
+    /// stimulus written by `encode --target readmemh`
+    GenTb {
+        #[command(flatten)]
+        testbench: TestbenchArgs,
+        /// Path the testbench reads stimulus from
+        #[arg(long, default_value = "stimulus.hex")]
+        stimulus: String,
+        /// HDL to write the testbench in
+        #[arg(long, value_enum, default_value_t)]
+        language: Language,
+    },
+    /// Write a cocotb test module for the packets in a file, replaying
+    /// stimulus written by `encode`
+    GenCocotb {
+        #[command(flatten)]
+        testbench: TestbenchArgs,
+        /// Path the test reads stimulus from
+        #[arg(long, default_value = "stimulus.txt")]
+        stimulus: String,
+    },
+    /// Write the packets in a file and their golden checksums as source code,
+    /// for test suites that build the vectors in
+    Export {
+        /// Encoded stimulus to read packets from, `-` for stdin
+        filename: String,
+        /// Destination file, `-` for stdout
+        dest_file: String,
+        /// Language to write
+        #[arg(long, value_enum)]
+        lang: Lang,
+        /// Read each line as a packet's content instead of as encoded
+        /// stimulus
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Listen for stimulus over TCP and answer with each packet's checksum as
+    /// soon as it completes, for co-simulation without files
+    Serve {
+        /// Port to listen on
+        #[arg(long)]
+        port: u16,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Read each line as a packet's content instead of as encoded
+        /// stimulus
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Read stimulus from one named pipe and write each packet's result to
+    /// another as soon as it completes, so a simulation can stream through
+    /// without files on disk
+    Bridge {
+        /// Pipe (or file) to read stimulus from
+        input: String,
+        /// Pipe (or file) to write results to
+        output: String,
+        /// Read each line as a packet's content instead of as encoded
+        /// stimulus
+        #[arg(long)]
+        raw: bool,
+        /// Make the named pipes if they don't exist
+        #[arg(long)]
+        create: bool,
+        /// Stop when the first writer closes the input, instead of waiting
+        /// for the next one
+        #[arg(long)]
+        once: bool,
+    },
+    /// Hash random data in memory with each checksum implementation this CPU
+    /// can run, or the one given with --impl, and report how fast each one
+    /// went
+    Bench {
+        /// Bytes of data to hash
+        #[arg(long, default_value_t = 16 << 20, value_parser = parse_number)]
+        size: u64,
+        /// Times to hash the data with each implementation
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        iterations: u64,
+    },
+}
+
+/// How `encode`, `generate` and `run` write stimulus
+#[derive(Debug, clap::Args, Clone)]
+struct EncodeArgs {
+    /// What to write
+    #[arg(long, value_enum, default_value_t)]
+    target: Target,
+    /// Put an `@address` before each packet in memory images
+    #[arg(long)]
+    address_markers: bool,
+    /// Address of the first word in memory images. Counts words for
+    /// `$readmem` images and bytes otherwise
+    #[arg(long, default_value_t = 0, value_parser = parse_number)]
+    base_address: u64,
+    /// Data bytes per Intel HEX or S-record
+    #[arg(long, default_value_t = 16)]
+    record_length: u8,
+    /// Address size of S-records
+    #[arg(long, value_enum, default_value_t)]
+    srec_type: SrecType,
+    /// Byte order of packed words in binary, Intel HEX and S-record output.
+    /// Words are padded to whole bytes
+    #[arg(long, value_enum, default_value_t)]
+    endian: Endian,
+    /// Fraction of cycles the DUT is ready, between 0 (exclusive) and 1.
+    /// Lines on the other cycles are stalls with nothing valid
+    #[arg(long, value_parser = parse_duty)]
+    ready_duty: Option<f64>,
+    /// Also write the ready schedule here, one `0` or `1` per line
+    #[arg(long, requires = "ready_duty")]
+    ready_file: Option<String>,
+    /// Chance of an idle line before each data beat, from 0 up to (but
+    /// not including) 1. Several can follow each other
+    #[arg(long, value_parser = parse_probability)]
+    bubbles: Option<f64>,
+    /// Seed for --ready-duty, --bubbles, generated packets and shuffle
+    /// order
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Also write each packet's checksum here, one `32'h` literal per
+    /// line, for the testbench to check against
+    #[arg(long)]
+    expected: Option<String>,
+    /// Follow each line with a comment saying what it carries. Only for
+    /// --target lines
+    #[arg(long)]
+    annotate: bool,
+    /// Start with comments saying how the file is written, so it can be
+    /// read back without format options. Only for --target lines
+    #[arg(long)]
+    header: bool,
+    /// End with a comment holding the checksum of the lines above, so
+    /// damage to the file is caught when it's read. Only for --target lines
+    #[arg(long)]
+    trailer: bool,
+    /// Follow each packet's last beat with an `expect 32'h…` comment holding
+    /// its checksum, for decode and verify to check against. Only for
+    /// --target lines
+    #[arg(long)]
+    embed_expected: bool,
+    /// Split packets longer than this many bytes into several packets
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_packet: Option<u64>,
+    /// Send each packet wrapped in this container, checksums and all,
+    /// instead of as it is
+    #[arg(long, value_enum)]
+    container: Option<Container>,
+    /// Wrap all the packets together in one container, sent as a single
+    /// packet
+    #[arg(long, requires = "container")]
+    whole_file: bool,
+}
+
+impl EncodeArgs {
+    /// `packets` as they're sent, in --container if there is one
+    fn contain<'a>(&self, packets: &'a [impl AsRef<[u8]>]) -> Vec<Cow<'a, [u8]>> {
+        match self.container {
+            None => packets
+                .iter()
+                .map(|packet| Cow::Borrowed(packet.as_ref()))
+                .collect(),
+            Some(container) if self.whole_file => {
+                let whole = packets.iter().flat_map(AsRef::as_ref).copied();
+                vec![Cow::Owned(container.wrap(&whole.collect::<Vec<_>>()))]
+            }
+            Some(container) => packets
+                .iter()
+                .map(|packet| Cow::Owned(container.wrap(packet.as_ref())))
+                .collect(),
+        }
+    }
+}
+
+/// Which packets of an encoded file to look at
+#[derive(Debug, clap::Args, Clone, Default, PartialEq, Eq)]
+struct Window {
+    /// Skip this many packets first
+    #[arg(long, default_value_t = 0)]
+    skip: usize,
+    /// Stop after this many packets
+    #[arg(long)]
+    take: Option<usize>,
+}
+
+impl Window {
+    fn contains(&self, index: usize) -> bool {
+        index >= self.skip && self.end().is_none_or(|end| index < end)
+    }
+
+    /// Index of the first packet past the window
+    fn end(&self) -> Option<usize> {
+        self.take.map(|take| self.skip.saturating_add(take))
+    }
+}
+
+/// Options shared by the testbench generators
+#[derive(Debug, clap::Args, Clone)]
+struct TestbenchArgs {
+    /// Packets to check for, one per line like encode reads, `-` for stdin
+    filename: String,
+    /// Destination file, `-` for stdout
+    dest_file: String,
+    /// Module name of the DUT
+    #[arg(long, default_value = "adler32")]
+    dut: String,
+    /// Rename a DUT port, e.g. `--port data=s_axis_tdata`. Roles are
+    /// clock, reset, length_valid, length, data_valid, data, checksum_valid
+    /// and checksum
+    #[arg(long, value_name = "ROLE=NAME")]
+    port: Vec<String>,
+    /// Clock cycles to wait for checksums after the last stimulus line
+    #[arg(long, default_value_t = 10_000)]
+    timeout: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum Target {
+    /// One line per beat, for the verilog file reader
+    #[default]
+    Lines,
+    /// Each line packed into a word, as binary for `$readmemb`
+    Readmemb,
+    /// Each line packed into a word, as hex for `$readmemh`
+    Readmemh,
+    /// Packed words in Intel HEX records
+    Ihex,
+    /// Packed words in Motorola S-records
+    Srec,
+    /// Packed words in a Xilinx coefficients file
+    Coe,
+    /// Packed words in a Quartus memory initialization file
+    Mif,
+    /// Packed words as raw binary, for `$fread`
+    Binary,
 }
 
 #[derive(Parser, Debug)]
 struct Args {
     #[clap(subcommand)]
     pub mode: Mode,
-    /// Source file to be read
-    pub filename: String,
-}
-
-#[derive(Debug)]
-struct DataLine {
-    length_valid: bool,
-    length: u32,
-    data_valid: bool,
-    data: u8,
-}
-
-impl FromStr for DataLine {
-    type Err = ();
-
-    fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let mut split = value.split(&[' ', '_']);
-        let length_valid = split
-            .next()
-            .unwrap()
-            .parse::<u8>()
-            .expect("length valid invalid")
-            == 1;
-        let length = u32::from_str_radix(split.next().unwrap(), 2).expect("Data Length invalid");
-        let data_valid = split
-            .next()
-            .unwrap()
-            .parse::<u8>()
-            .expect("Data Valid value invalid")
-            == 1;
-        let data =
-            u8::from_str_radix(split.next().unwrap(), 2).expect("Failed to read Data in line");
-        Ok(DataLine {
-            length_valid,
-            length,
-            data_valid,
-            data,
+    /// How results are printed
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub output_format: OutputFormat,
+    /// Also write a JUnit XML report of verify, compare, run and hash
+    /// --check results here, one test case per packet or file
+    #[arg(long, global = true)]
+    pub junit: Option<String>,
+    /// Trace encoded lines to stderr as they're read: each packet's length
+    /// beat and timing with -v, every beat with -vv. -v also says what
+    /// format a file was read in when it wasn't given
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// How warnings and -v traces are written to stderr
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+    /// At the end, say on stderr how long the run took and how many bytes
+    /// and packets it got through a second
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub timing: Option<TimingFormat>,
+    /// Print only failures and summaries, not a line per packet
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Bits of data per beat in encoded files [default: 8]
+    #[arg(long, global = true, value_parser = parse_data_width)]
+    pub data_width: Option<u32>,
+    /// Bits in the length field of encoded files [default: 32]
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub length_width: Option<u32>,
+    /// Layout of encoded lines, e.g. `{lv}_{len:b32}_{dv}_{data:b8}`.
+    /// Overrides --data-width and --length-width. Without any of these or
+    /// --radix, length prefixed files are read in the format their first
+    /// line looks to be written in
+    #[arg(long, global = true)]
+    pub format: Option<LineFormat>,
+    /// Radix of the length and data fields: bin, hex or dec
+    #[arg(long, global = true, conflicts_with = "format")]
+    pub radix: Option<Radix>,
+    /// How packets are delimited in encoded files
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub protocol: Protocol,
+    /// What to do with zero-length packets in encoded files
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub zero_length: ZeroLength,
+    /// What to do when an encoded file ends in the middle of a packet
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub on_truncated: OnTruncated,
+    /// Start of a comment in encoded files, e.g. `//`. Can be given more
+    /// than once; annotations use the first
+    #[arg(long, global = true, default_value = "#")]
+    pub comment_prefix: Vec<String>,
+    /// Parse encoded files on this many threads, a batch of lines at a
+    /// time. Packets still come out in order
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub jobs: usize,
+    /// Map input files into memory and parse them in place instead of
+    /// reading them through a buffer, which is faster for large files. They
+    /// mustn't change while they're being read
+    #[arg(long, global = true)]
+    pub mmap: bool,
+    /// How checksums are computed. All give the same checksums; without
+    /// it, the quickest for the length of each packet is used
+    #[arg(long = "impl", global = true, value_enum)]
+    pub implementation: Option<Checksummer>,
+    /// Checksum the DUT computes over each packet [default: adler32]. Files
+    /// with a header saying which they were written for are read with that
+    /// one unless this is given. `hash` takes a comma separated list, to
+    /// compute several in one pass
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    pub algorithm: Vec<ChecksumAlgorithm>,
+    /// Start each packet's Adler-32 `a` sum here instead of at 1, as a DUT
+    /// continuing a checksum across packets does
+    #[arg(long, global = true, value_parser = parse_sum)]
+    pub init_a: Option<u32>,
+    /// Start each packet's Adler-32 `b` sum here instead of at 0
+    #[arg(long, global = true, value_parser = parse_sum)]
+    pub init_b: Option<u32>,
+    /// Start each packet's Adler-32 from this checksum, `b` above `a`,
+    /// e.g. `0x091e01de`. The same as --init-a and --init-b together
+    #[arg(
+        long,
+        global = true,
+        value_name = "CHECKSUM",
+        conflicts_with_all = ["init_a", "init_b"],
+        value_parser = parse_init
+    )]
+    pub init: Option<u32>,
+    /// Reduce both Adler-32 sums modulo this instead of 65521, e.g. 65536,
+    /// for experimental variants of the DUT
+    #[arg(long, global = true, default_value_t = MOD_ADLER, value_parser = parse_modulus)]
+    pub modulus: u32,
+    /// Reject encoded lines that aren't written exactly the way --format
+    /// writes them, instead of warning about them
+    #[arg(long, global = true)]
+    pub strict: bool,
+    /// Add to output files that already have something in them, instead of
+    /// refusing to write
+    #[arg(long, global = true, conflicts_with = "force")]
+    pub append: bool,
+    /// Overwrite output files that already have something in them, instead
+    /// of refusing to write
+    #[arg(long, global = true)]
+    pub force: bool,
+    /// Keep a copy of each output file that already exists, named with this
+    /// suffix, before it is rewritten. Implies --force unless --append is
+    /// given
+    #[arg(
+        long,
+        global = true,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak",
+        value_parser = clap::builder::NonEmptyStringValueParser::new()
+    )]
+    pub backup: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Checksummer {
+    /// Reduce both sums after every byte, as the hardware does
+    Reference,
+    /// Reduce the sums once every few thousand bytes
+    Fast,
+    /// Use the CPU's vector instructions
+    Simd,
+}
+
+impl From<Checksummer> for Implementation {
+    fn from(value: Checksummer) -> Self {
+        match value {
+            Checksummer::Reference => Implementation::Reference,
+            Checksummer::Fast => Implementation::Fast,
+            Checksummer::Simd => Implementation::Simd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ChecksumAlgorithm {
+    /// Adler-32, as zlib ends its streams with
+    Adler32,
+    /// CRC-32 with the polynomial zlib, Ethernet and PNG use
+    Crc32,
+    /// Fletcher-16, over bytes
+    Fletcher16,
+    /// Fletcher-32, over 16 bit little-endian words
+    Fletcher32,
+    /// Fletcher-64, over 32 bit little-endian words, written 64 bits wide
+    Fletcher64,
+}
+
+impl From<ChecksumAlgorithm> for Algorithm {
+    fn from(value: ChecksumAlgorithm) -> Self {
+        match value {
+            ChecksumAlgorithm::Adler32 => Algorithm::Adler32,
+            ChecksumAlgorithm::Crc32 => Algorithm::Crc32,
+            ChecksumAlgorithm::Fletcher16 => Algorithm::Fletcher16,
+            ChecksumAlgorithm::Fletcher32 => Algorithm::Fletcher32,
+            ChecksumAlgorithm::Fletcher64 => Algorithm::Fletcher64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Container {
+    /// A zlib stream of stored deflate blocks, ending in the Adler-32 of
+    /// the packet
+    Zlib,
+}
+
+impl Container {
+    fn wrap(self, packet: &[u8]) -> Vec<u8> {
+        match self {
+            Container::Zlib => zlib_stored(packet),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Lang {
+    /// A C header of `static const uint8_t` arrays
+    C,
+    /// A Rust file to `include!`, defining `VECTORS`
+    Rust,
+    /// A Python module defining `VECTORS`
+    Python,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum Language {
+    /// SystemVerilog
+    #[default]
+    Sv,
+    /// VHDL-2008
+    Vhdl,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum Protocol {
+    /// A length beat followed by the data beats
+    #[default]
+    Length,
+    /// AXI4-Stream TVALID/TDATA/TLAST/TKEEP columns
+    Axis,
+    /// Avalon-ST valid/data/startofpacket/endofpacket/empty columns
+    Avalon,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum SortKey {
+    /// Bytes in the packet
+    #[default]
+    Length,
+    /// The packet's checksum
+    Checksum,
+    /// Position in the file
+    Index,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum ZeroLength {
+    /// Treat it as a packet with checksum 32'h00000001 and no content
+    #[default]
+    Emit,
+    /// Leave it out, with a warning
+    Skip,
+    /// Stop with an error
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OnTruncated {
+    /// Stop with an error
+    Error,
+    /// Drop the partial packet, with a warning
+    #[default]
+    Warn,
+    /// Pass on the checksum of the bytes received, with a warning
+    Flush,
+}
+
+/// What to do with an output file that already has something in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Existing {
+    Refuse,
+    Overwrite,
+    Append,
+}
+
+/// How output files are written, from the global options
+#[derive(Debug, Clone)]
+struct Writing {
+    existing: Existing,
+    /// Suffix of the copy kept of a file that is rewritten
+    backup: Option<String>,
+    /// Throw away everything written, for --dry-run
+    dry_run: bool,
+}
+
+/// Writer counting the bytes that go through it
+struct Counted<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> Counted<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// An output file being written under a temporary name
+struct Staged {
+    temp: PathBuf,
+    path: PathBuf,
+    /// Where to copy what was at `path` before replacing it
+    backup: Option<PathBuf>,
+}
+
+/// How encoded files are laid out and written, from the global options
+#[derive(Clone)]
+struct Encoding {
+    line_format: LineFormat,
+    protocol: Protocol,
+    zero_length: ZeroLength,
+    on_truncated: OnTruncated,
+    strict: bool,
+    comment_prefixes: Vec<String>,
+    /// Read length prefixed files in the format they look to be written in,
+    /// as no format was given
+    detect: bool,
+    /// Threads to parse length prefixed files on
+    jobs: usize,
+    writing: Writing,
+    algorithm: Algorithm,
+    /// Checksum length prefixed files with the algorithm their header gives,
+    /// as none was given
+    detect_algorithm: bool,
+    /// Checksum each packet's Adler-32 starts from
+    init: u32,
+    /// What Adler-32 sums are reduced modulo
+    modulus: u32,
+}
+
+impl Encoding {
+    /// The same encoding, writing nothing if `dry_run`
+    fn dry_run(&self, dry_run: bool) -> Self {
+        let mut encoding = self.clone();
+        encoding.writing.dry_run |= dry_run;
+        encoding
+    }
+
+    /// A hasher for each packet by `algorithm`, starting from `init` and
+    /// reducing modulo `modulus` if it's Adler-32
+    fn hasher_for(&self, algorithm: Algorithm) -> Checksum {
+        match algorithm {
+            Algorithm::Adler32 => {
+                Checksum::Adler32(Adler32::resume(self.init).with_modulus(self.modulus))
+            }
+            _ => Checksum::new(algorithm),
+        }
+    }
+
+    /// A hasher for each packet
+    fn hasher(&self) -> Checksum {
+        self.hasher_for(self.algorithm)
+    }
+
+    /// Checksum of a whole packet
+    fn checksum(&self, packet: &[u8]) -> u64 {
+        let mut hasher = self.hasher();
+        hasher.update(packet);
+        hasher.finalize()
+    }
+}
+
+impl TestbenchArgs {
+    /// The testbench these options describe, reading `stimulus`, and where
+    /// to write it
+    fn build(self, encoding: &Encoding, stimulus: String) -> Result<(Testbench, Box<dyn Write>)> {
+        if encoding.protocol != Protocol::Length {
+            return Err(Error::Usage(
+                "testbenches are only generated for --protocol length".to_owned(),
+            ));
+        }
+        let expected = open_source(&self.filename)?
+            .lines()
+            .map(|packet| packet.map(|packet| encoding.checksum(packet.as_bytes())))
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(Error::Read)?;
+
+        let mut testbench = Testbench::new(encoding.line_format.clone(), expected);
+        testbench.dut = self.dut;
+        testbench.stimulus = stimulus;
+        testbench.timeout = self.timeout;
+        testbench.checksum_width = encoding.algorithm.bits();
+        for port in self.port {
+            let (role, name) = port
+                .split_once('=')
+                .ok_or_else(|| Error::Usage(format!("expected ROLE=NAME, not '{port}'")))?;
+            testbench.ports.set(role, name).map_err(Error::Usage)?;
+        }
+        Ok((testbench, open_dest(&self.dest_file, &encoding.writing)?))
+    }
+}
+
+/// A fraction in (0, 1]
+fn parse_duty(value: &str) -> std::result::Result<f64, String> {
+    match value.parse() {
+        Ok(duty) if duty > 0.0 && duty <= 1.0 => Ok(duty),
+        _ => Err(format!(
+            "expected a fraction above 0 and up to 1, not {value}"
+        )),
+    }
+}
+
+/// A probability in [0, 1)
+fn parse_probability(value: &str) -> std::result::Result<f64, String> {
+    match value.parse() {
+        Ok(p) if (0.0..1.0).contains(&p) => Ok(p),
+        _ => Err(format!(
+            "expected a probability from 0 up to 1, not {value}"
+        )),
+    }
+}
+
+/// A percentage from 0 to 100
+fn parse_percent(value: &str) -> std::result::Result<f64, String> {
+    match value.parse() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!("expected a percentage from 0 to 100, not {value}")),
+    }
+}
+
+/// Decimal, or hex with a `0x` prefix
+fn parse_number(value: &str) -> std::result::Result<u64, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// One of the Adler-32 sums, decimal or hex, in 16 bits
+fn parse_sum(value: &str) -> std::result::Result<u32, String> {
+    match parse_number(value)? {
+        sum if sum <= 0xffff => Ok(sum as u32),
+        _ => Err("must fit in 16 bits".to_owned()),
+    }
+}
+
+fn parse_init(value: &str) -> std::result::Result<u32, String> {
+    parse_checksum(value)
+        .and_then(|checksum| checksum.try_into().ok())
+        .ok_or_else(|| format!("'{value}' is not a 32 bit checksum"))
+}
+
+/// A modulus the Adler-32 sums can be packed into a checksum under
+fn parse_modulus(value: &str) -> std::result::Result<u32, String> {
+    match parse_number(value)? {
+        modulus if (2..=1 << 16).contains(&modulus) => Ok(modulus as u32),
+        _ => Err("must be between 2 and 65536".to_owned()),
+    }
+}
+
+/// `CHECKSUM:LENGTH`, e.g. `32'h11e60398:9`
+fn parse_shard(value: &str) -> std::result::Result<(u32, u64), String> {
+    let (checksum, length) = value
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected CHECKSUM:LENGTH, not '{value}'"))?;
+    let checksum = parse_checksum(checksum)
+        .and_then(|checksum| checksum.try_into().ok())
+        .ok_or_else(|| format!("'{checksum}' is not a 32 bit checksum"))?;
+    Ok((checksum, parse_number(length)?))
+}
+
+fn parse_data_width(value: &str) -> std::result::Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|width| LineFormat::DATA_WIDTHS.contains(width))
+        .ok_or_else(|| format!("must be one of {:?}", LineFormat::DATA_WIDTHS))
+}
+
+fn open_source(filename: &str) -> Result<Box<dyn BufRead>> {
+    if filename == STDIO {
+        return Ok(Box::new(timing::Metered(io::stdin().lock())));
+    }
+    let open_error = |source| Error::Open {
+        path: filename.to_owned(),
+        source,
+    };
+    let file = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .map_err(open_error)?;
+    let meta = file.metadata().map_err(open_error)?;
+    let source: Box<dyn BufRead> = match MMAP.load(Ordering::Relaxed) && meta.is_file() {
+        // SAFETY: the map is only read, and --mmap asks for the file not to
+        // change while it is
+        true => Box::new(io::Cursor::new(
+            unsafe { Mmap::map(&file) }.map_err(open_error)?,
+        )),
+        false => Box::new(BufReader::new(file)),
+    };
+    Ok(Box::new(timing::Metered(progress::track(
+        filename,
+        meta.len(),
+        source,
+    ))))
+}
+
+/// Open `filename` for writing the way `writing` says, in particular if it's
+/// a file with something in it already. Pipes and devices are written as
+/// they are.
+fn open_dest(filename: &str, writing: &Writing) -> Result<Box<dyn Write>> {
+    if writing.dry_run {
+        return Ok(Box::new(io::sink()));
+    }
+    if filename == STDIO {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    let occupied = std::fs::metadata(filename).is_ok_and(|meta| meta.is_file() && meta.len() > 0);
+    if occupied && writing.existing == Existing::Refuse {
+        return Err(Error::Open {
+            path: filename.to_owned(),
+            source: io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "file already exists, pass --force to overwrite it or --append to add to it",
+            ),
+        });
+    }
+    let file = stage(Path::new(filename), writing).map_err(|source| Error::Open {
+        path: filename.to_owned(),
+        source,
+    })?;
+    Ok(Box::new(file))
+}
+
+/// Open a temporary file next to `path` to be renamed over it by
+/// [`commit_staged`], starting with what's in `path` if appending. Pipes and
+/// devices are opened as they are.
+fn stage(path: &Path, writing: &Writing) -> io::Result<File> {
+    let append = writing.existing == Existing::Append;
+    let open = |path: &Path| {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+    };
+    let meta = fs::metadata(path).ok();
+    if meta.as_ref().is_some_and(|meta| !meta.is_file()) {
+        return open(path);
+    }
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+
+    let mut staged = STAGED.lock().expect("no thread panics while staging");
+    // If it was opened before in this run, carry on with the same temporary file
+    if !staged.iter().any(|staged| staged.path == path) {
+        if append && meta.is_some() {
+            fs::copy(path, &temp)?;
+        }
+        let backup = match (&writing.backup, &meta) {
+            (Some(suffix), Some(_)) => Some(path.with_file_name(format!("{name}{suffix}"))),
+            _ => None,
+        };
+        staged.push(Staged {
+            temp: temp.clone(),
+            path: path.to_owned(),
+            backup,
+        });
+    }
+    let file = open(&temp)?;
+    if let Some(meta) = meta {
+        file.set_permissions(meta.permissions())?;
+    }
+    Ok(file)
+}
+
+/// Rename every file staged so far into place, first backing up what was
+/// there if asked to.
+fn commit_staged() -> Result<()> {
+    let staged = std::mem::take(&mut *STAGED.lock().expect("no thread panics while staging"));
+    for Staged { temp, path, backup } in staged {
+        if let Some(backup) = backup {
+            fs::copy(&path, &backup).map_err(Error::Write)?;
+        }
+        fs::rename(&temp, &path).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+/// Delete every file staged so far, leaving the files they were to replace
+/// as they were.
+fn discard_staged() {
+    let staged = std::mem::take(&mut *STAGED.lock().expect("no thread panics while staging"));
+    for Staged { temp, .. } in staged {
+        let _ = fs::remove_file(temp);
+    }
+}
+
+/// Where to print progress messages so they don't end up mixed into the
+/// output when it is going to stdout.
+fn report_to(dest_file: &str) -> Box<dyn Write> {
+    if dest_file == STDIO {
+        Box::new(progress::stderr())
+    } else {
+        Box::new(progress::stdout())
+    }
+}
+
+/// Expand glob patterns (for when the shell didn't) in the list of input
+/// files. Anything that isn't a pattern is passed through as is.
+fn expand_inputs(patterns: &[String]) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        if pattern == STDIO || !pattern.contains(['*', '?', '[']) {
+            files.push(pattern.clone());
+            continue;
+        }
+        let no_match = || Error::Open {
+            path: pattern.clone(),
+            source: io::Error::new(io::ErrorKind::NotFound, "no files match pattern"),
+        };
+        let matches = glob::glob(pattern)
+            .map_err(|_| no_match())?
+            .filter_map(|path| path.ok())
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return Err(no_match());
+        }
+        files.extend(matches);
+    }
+    Ok(files)
+}
+
+/// Checksum of the raw contents of `path`, by `hasher`
+fn hash_file(mut hasher: Checksum, path: &str) -> Result<u64> {
+    io::copy(&mut open_source(path)?, &mut hasher).map_err(Error::Read)?;
+    Ok(hasher.finalize())
+}
+
+/// `encoding` with the line format from the header of `source`, or else
+/// guessed from its first lines, if no format was given, and the checksum
+/// algorithm from the header if none was given. Also returns the header,
+/// and `source` from the start again.
+fn sniff(
+    encoding: &Encoding,
+    mut source: impl BufRead,
+) -> Result<(Encoding, Option<Header>, impl BufRead)> {
+    let mut encoding = encoding.clone();
+    let mut consumed = Vec::new();
+    let mut comments = Vec::new();
+    let mut lines = Vec::new();
+    let prefixes = &encoding.comment_prefixes;
+    let wanted = if encoding.detect { SNIFF_LINES } else { 1 };
+    while encoding.protocol == Protocol::Length && lines.len() < wanted {
+        let start = consumed.len();
+        if source
+            .read_until(b'\n', &mut consumed)
+            .map_err(Error::Read)?
+            == 0
+        {
+            break;
+        }
+        let Ok(text) = std::str::from_utf8(&consumed[start..]) else {
+            break;
+        };
+        let text = text.trim_end();
+        let comment = prefixes
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix.as_str()));
+        match comment {
+            // Only the comments before the first line can be a header
+            Some(comment) if lines.is_empty() => comments.push(comment.to_owned()),
+            Some(_) => {}
+            None if text.is_empty() => {}
+            None => lines.push(strip_comment(text, prefixes).trim_end().to_owned()),
+        }
+    }
+    let header = Header::parse(comments.iter().map(String::as_str))
+        .map_err(|e| Error::Usage(e.to_string()))?;
+    if encoding.detect {
+        let format = match header.as_ref().and_then(Header::line_format) {
+            Some(format) => Some(format),
+            None => LineFormat::detect(lines.iter().map(String::as_str)),
+        };
+        if let Some(format) = format {
+            debug!("reading lines as {format}");
+            encoding.line_format = format;
+        }
+    }
+    encoding.detect = false;
+    if let Some(algorithm) = header.as_ref().and_then(|header| header.algorithm) {
+        if encoding.detect_algorithm {
+            debug!("checksumming packets with {}", algorithm.name());
+            encoding.algorithm = algorithm;
+        }
+    }
+    encoding.detect_algorithm = false;
+    Ok((encoding, header, io::Cursor::new(consumed).chain(source)))
+}
+
+/// Parse length prefixed stimulus, skipping comments and checking the lines
+/// against any trailer. Lines that aren't written the way the format writes
+/// them are errors with --strict, and warnings otherwise. The checksums of
+/// any `expect` comments go in `expects`, by packet. With --jobs, lines are
+/// parsed a batch at a time on that many threads.
+fn read_lines<'a>(
+    encoding: &'a Encoding,
+    mut source: impl BufRead + 'a,
+    expects: Option<&'a RefCell<Vec<Option<u64>>>>,
+) -> impl Iterator<Item = Result<DataLine>> + 'a {
+    let mut trailer = Trailer::new();
+    let mut packets = 0;
+    let mut remaining = 0;
+    let mut accept = move |number: usize, text: &str, parsed: Parsed| {
+        let (code, parsed, canonical) = match parsed {
+            Parsed::Comment(start) => {
+                let comment = &text[start..];
+                // An expect comment follows the last beat of the packet it's for
+                if let (Some(expects), Some(checksum)) = (expects, parse_expect(comment)) {
+                    let mut expects = expects.borrow_mut();
+                    let packet = packets.max(1) - 1;
+                    if expects.len() <= packet {
+                        expects.resize(packet + 1, None);
+                    }
+                    expects[packet] = Some(checksum);
+                    return None;
+                }
+                let expected = Trailer::parse(comment)?;
+                let actual = trailer.checksum();
+                trailer.reset();
+                return (expected != actual).then_some(Err(Error::Trailer {
+                    line: number,
+                    expected,
+                    actual,
+                }));
+            }
+            Parsed::Code {
+                end,
+                line,
+                canonical,
+            } => (&text[..end], line, canonical),
+        };
+        trailer.push(code);
+        let line = parsed.map_err(|source| Error::Parse {
+            line: number,
+            text: text.to_owned(),
+            source,
+        });
+        if let Ok(line) = &line {
+            packets += line.length_valid as usize;
+            if tracing::enabled!(Level::DEBUG) {
+                trace_line(encoding, number, line, &mut remaining);
+            }
+            if !canonical {
+                let canonical = encoding.line_format.display(line);
+                warn!("line {number}: read '{code}' as '{canonical}'");
+            }
+        }
+        Some(line)
+    };
+
+    let mut partial = Vec::new();
+    let mut number = 0;
+    // Lines read and parsed ahead with --jobs, and why reading stopped
+    let mut batch = VecDeque::new();
+    let mut stopped = None;
+    iter::from_fn(move || loop {
+        if encoding.jobs <= 1 {
+            number += 1;
+            let line = with_next_line(&mut source, &mut partial, |text| {
+                accept(number, text, Parsed::new(encoding, text))
+            });
+            match line {
+                Ok(Some(Some(line))) => return Some(line),
+                Ok(Some(None)) => continue,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(Error::Read(e))),
+            }
+        }
+
+        if batch.is_empty() && stopped.is_none() {
+            let mut texts = Vec::with_capacity(BATCH_LINES);
+            while texts.len() < BATCH_LINES {
+                match with_next_line(&mut source, &mut partial, str::to_owned) {
+                    Ok(Some(text)) => texts.push(text),
+                    Ok(None) => {
+                        stopped = Some(None);
+                        break;
+                    }
+                    Err(e) => {
+                        stopped = Some(Some(e));
+                        break;
+                    }
+                }
+            }
+            batch = texts
+                .into_par_iter()
+                .map(|text| {
+                    let parsed = Parsed::new(encoding, &text);
+                    (text, parsed)
+                })
+                .collect::<Vec<_>>()
+                .into();
+        }
+        let Some((text, parsed)) = batch.pop_front() else {
+            // Whatever stopped the reading comes after every line read
+            return stopped
+                .as_mut()
+                .and_then(Option::take)
+                .map(|e| Err(Error::Read(e)));
+        };
+        number += 1;
+        if let Some(line) = accept(number, &text, parsed) {
+            return Some(line);
+        }
+    })
+}
+
+/// What a line of length prefixed stimulus holds, as far as can be told
+/// from the line alone, so lines can be parsed in parallel
+enum Parsed {
+    /// A comment, starting at this byte after its prefix
+    Comment(usize),
+    /// Beat fields, ending at byte `end` where any comment starts.
+    /// `canonical` is whether they're written the way the format writes them
+    Code {
+        end: usize,
+        line: std::result::Result<DataLine, ParseLineError>,
+        canonical: bool,
+    },
+}
+
+impl Parsed {
+    fn new(encoding: &Encoding, text: &str) -> Self {
+        let prefixes = &encoding.comment_prefixes;
+        let format = &encoding.line_format;
+        let prefix = prefixes
+            .iter()
+            .find(|prefix| text.starts_with(prefix.as_str()));
+        if let Some(prefix) = prefix {
+            return Self::Comment(prefix.len());
+        }
+        let code = strip_comment(text, prefixes);
+        let line = match encoding.strict {
+            true => format.parse_strict(code),
+            false => format.parse(code),
+        };
+        let canonical = encoding.strict
+            || line
+                .as_ref()
+                .is_ok_and(|line| format.is_canonical(line, code));
+        Self::Code {
+            end: code.len(),
+            line,
+            canonical,
+        }
+    }
+}
+
+/// Call `f` with the next line of `source`, without its line ending. The
+/// line is read straight out of the buffer of `source` if it's all there,
+/// as it always is for a mapped file, and gathered in `partial` otherwise.
+/// `None` at the end of the input.
+fn with_next_line<T>(
+    source: &mut impl BufRead,
+    partial: &mut Vec<u8>,
+    f: impl FnOnce(&str) -> T,
+) -> io::Result<Option<T>> {
+    fn text(line: &[u8]) -> io::Result<&str> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        std::str::from_utf8(line).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
         })
     }
+
+    partial.clear();
+    loop {
+        let available = source.fill_buf()?;
+        if available.is_empty() {
+            if partial.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        match available.iter().position(|&byte| byte == b'\n') {
+            Some(end) if partial.is_empty() => {
+                let result = text(&available[..end]).map(f);
+                source.consume(end + 1);
+                return result.map(Some);
+            }
+            Some(end) => {
+                partial.extend_from_slice(&available[..end]);
+                source.consume(end + 1);
+                break;
+            }
+            None => {
+                let read = available.len();
+                partial.extend_from_slice(available);
+                source.consume(read);
+            }
+        }
+    }
+    text(partial).map(f).map(Some)
+}
+
+/// Log what `line`, number `number`, carries: length beats at debug level
+/// and every beat at trace level. `remaining` counts down the bytes of the
+/// packet in progress.
+fn trace_line(encoding: &Encoding, number: usize, line: &DataLine, remaining: &mut u64) {
+    if line.length_valid {
+        *remaining = line.length;
+        debug!("line {number}: length {}", line.length);
+    }
+    if line.data_valid && *remaining > 0 {
+        let count = (*remaining).min(encoding.line_format.bytes_per_beat() as u64);
+        let bytes = &line.data.to_le_bytes()[..count as usize];
+        trace!("line {number}: data '{}'", bytes.escape_ascii());
+        *remaining -= count;
+    } else if !line.length_valid {
+        trace!("line {number}: idle");
+    }
 }
 
-impl From<u8> for DataLine {
-    fn from(value: u8) -> Self {
-        Self {
-            length_valid: false,
-            length: 0,
-            data_valid: true,
-            data: value,
+/// Replay the encoded stimulus in `source`, calling `f` with each packet's
+/// index, checksum and content. Stops at the first line that can't be read.
+fn for_each_packet(
+    encoding: &Encoding,
+    source: impl BufRead,
+    f: impl FnMut(usize, u64, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+    for_each_packet_in(encoding, source, &Window::default(), f).map(|_| ())
+}
+
+/// [`for_each_packet`], only calling `f` for packets in `window` and reading
+/// no further than the line after its end. Returns the checksums the file's `expect`
+/// comments give for its packets, by index.
+fn for_each_packet_in(
+    encoding: &Encoding,
+    source: impl BufRead,
+    window: &Window,
+    mut f: impl FnMut(usize, u64, Vec<u8>) -> Result<()>,
+) -> Result<Vec<Option<u64>>> {
+    let (encoding, header, source) = sniff(encoding, source)?;
+    let encoding = &encoding;
+    let mut f = |index, checksum, content| match window.contains(index) {
+        true => f(index, checksum, content),
+        false => Ok(()),
+    };
+    let mut error = None;
+    let mut index = 0;
+    let data_width = encoding.line_format.data_width();
+    let owned = |(checksum, content): (u64, &[u8])| (checksum, content.to_vec());
+    let expects = RefCell::new(Vec::new());
+    let truncated = match encoding.protocol {
+        Protocol::Length => {
+            let mut lines = read_lines(encoding, source, Some(&expects));
+            let data = lines
+                .by_ref()
+                .map_while(|line| line.map_err(|e| error = Some(e)).ok());
+            let mut stream = DataStream::with_data_width(data, data_width)
+                .with_empty_packets()
+                .with_hasher(encoding.hasher());
+            replay(encoding, stream.by_ref(), window, &mut index, &mut f)?;
+            let truncated = stream.truncated().map(owned);
+            // The last packet's expect comment comes after it, so read on to
+            // the next line. Whatever is wrong with that line is past the window
+            if window.end().is_some_and(|end| index >= end) {
+                let _ = lines.next();
+            }
+            truncated
+        }
+        Protocol::Axis => {
+            let beats = read_axis(source, data_width)
+                .map_while(|beat| beat.map_err(|e| error = Some(e)).ok());
+            let mut stream = AxisStream::new(beats).with_hasher(encoding.hasher());
+            replay(encoding, stream.by_ref(), window, &mut index, &mut f)?;
+            stream.truncated().map(owned)
+        }
+        Protocol::Avalon => {
+            let beats = read_avalon(source, data_width)
+                .map_while(|beat| beat.map_err(|e| error = Some(e)).ok());
+            let mut stream = AvalonStream::new(beats, data_width).with_hasher(encoding.hasher());
+            replay(encoding, stream.by_ref(), window, &mut index, &mut f)?;
+            stream.truncated().map(owned)
+        }
+    };
+    if let Some(e) = error {
+        return Err(e);
+    }
+    let announced = header.and_then(|header| header.packets);
+    let read_all = window.end().is_none_or(|end| index < end);
+    if let Some(packets) = announced.filter(|&packets| read_all && packets != index) {
+        warn!("the header says there are {packets} packets, but there are {index}");
+    }
+
+    let expects = expects.into_inner();
+    let Some((checksum, content)) = truncated else {
+        return Ok(expects);
+    };
+    match encoding.on_truncated {
+        OnTruncated::Error => return Err(Error::Truncated { packet: index }),
+        OnTruncated::Warn => {
+            warn!("input ended in the middle of packet {index}, dropped it");
+        }
+        OnTruncated::Flush => {
+            warn!("packet {index} is truncated, its checksum covers the bytes received");
+            f(index, checksum, content)?;
         }
     }
+    Ok(expects)
 }
 
-impl Display for DataLine {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}_{:0>32b}_{}_{:0>8b}",
-            self.length_valid as u8, self.length, self.data_valid as u8, self.data
+/// Call `f` with every complete packet up to the end of `window`, counting
+/// them in `index` and applying the zero-length policy.
+fn replay(
+    encoding: &Encoding,
+    mut packets: impl Iterator<Item = (u64, Vec<u8>)>,
+    window: &Window,
+    index: &mut usize,
+    f: &mut impl FnMut(usize, u64, Vec<u8>) -> Result<()>,
+) -> Result<()> {
+    // Checked before taking the next packet, so nothing past the window is read
+    while window.end().is_none_or(|end| *index < end) {
+        // Covers reading the packet's beats as well as `f`
+        let span = debug_span!(
+            "packet",
+            index = *index,
+            length = tracing::field::Empty,
+            checksum = tracing::field::Empty,
         )
+        .entered();
+        let Some((checksum, content)) = packets.next() else {
+            break;
+        };
+        span.record("length", content.len());
+        span.record("checksum", format_args!("{checksum:0>8x}"));
+        // Only zero-length packets have no content
+        if content.is_empty() {
+            match encoding.zero_length {
+                ZeroLength::Emit => {}
+                ZeroLength::Skip => {
+                    warn!("skipped a zero-length packet before packet {index}");
+                    continue;
+                }
+                ZeroLength::Error => return Err(Error::ZeroLength { packet: *index }),
+            }
+        }
+        f(*index, checksum, content)?;
+        *index += 1;
+        progress::packet();
+        timing::packets(1);
+    }
+    Ok(())
+}
+
+/// Content of every packet of the encoded file `filename`
+fn read_packets(encoding: &Encoding, filename: &str) -> Result<Vec<Vec<u8>>> {
+    let mut packets = Vec::new();
+    for_each_packet(encoding, open_source(filename)?, |_, _, content| {
+        packets.push(content);
+        Ok(())
+    })?;
+    Ok(packets)
+}
+
+/// How many of the lines in `source` are idle, with nothing valid on them,
+/// and how many lines there are.
+fn count_idle(encoding: &Encoding, source: impl BufRead) -> Result<(usize, usize)> {
+    let (encoding, _, source) = sniff(encoding, source)?;
+    let encoding = &encoding;
+    let idle: Vec<bool> = match encoding.protocol {
+        Protocol::Length => read_lines(encoding, source, None)
+            .map(|line| line.map(|line| !line.length_valid && !line.data_valid))
+            .collect::<Result<_>>()?,
+        Protocol::Axis => read_axis(source, encoding.line_format.data_width())
+            .map(|beat| beat.map(|beat| !beat.valid))
+            .collect::<Result<_>>()?,
+        Protocol::Avalon => read_avalon(source, encoding.line_format.data_width())
+            .map(|beat| beat.map(|beat| !beat.valid))
+            .collect::<Result<_>>()?,
+    };
+    Ok((idle.iter().filter(|&&idle| idle).count(), idle.len()))
+}
+
+/// `packets` cut into pieces of at most `max_packet` bytes. Empty packets are
+/// kept as they are.
+fn split_packets(packets: &[impl AsRef<[u8]>], max_packet: Option<u64>) -> Vec<&[u8]> {
+    let Some(max_packet) = max_packet else {
+        return packets.iter().map(AsRef::as_ref).collect();
+    };
+    packets
+        .iter()
+        .flat_map(|packet| match packet.as_ref() {
+            [] => vec![&[][..]],
+            packet => packet.chunks(max_packet as usize).collect(),
+        })
+        .collect()
+}
+
+/// Content of a packet to show: as text if it's UTF-8, otherwise with one
+/// char per byte
+fn content(packet: &[u8]) -> String {
+    match std::str::from_utf8(packet) {
+        Ok(text) => text.to_owned(),
+        Err(_) => packet.iter().map(|&byte| byte as char).collect(),
     }
 }
 
-struct DataStream<I>
-where
-    I: Iterator<Item = DataLine>,
-{
-    data: I,
-    length: u32,
-    content: String,
-    a: u16,
-    b: u16,
+/// Join runs of consecutive `packets` while they fit in `max_size` bytes.
+fn merge_packets(packets: Vec<Vec<u8>>, max_size: u64) -> Vec<Vec<u8>> {
+    let mut merged: Vec<Vec<u8>> = Vec::new();
+    for packet in packets {
+        match merged.last_mut() {
+            Some(last) if (last.len() + packet.len()) as u64 <= max_size => last.extend(packet),
+            _ => merged.push(packet),
+        }
+    }
+    merged
 }
 
-impl<I> DataStream<I>
-where
-    I: Iterator<Item = DataLine>,
-{
-    fn new(data: I) -> Self {
-        Self {
-            data,
-            content: String::new(),
-            length: 0,
-            a: 1,
-            b: 0,
+/// Write stimulus for `packets` to `dest` the way `options` asks, returning
+/// the number of lines written.
+fn encode(
+    encoding: &Encoding,
+    packets: &[impl AsRef<[u8]>],
+    options: EncodeArgs,
+    dest: &mut impl Write,
+) -> Result<usize> {
+    let contained = options.contain(packets);
+    let EncodeArgs {
+        target,
+        address_markers,
+        base_address,
+        record_length,
+        srec_type,
+        endian,
+        ready_duty,
+        ready_file,
+        bubbles,
+        seed,
+        expected,
+        annotate,
+        header,
+        trailer,
+        embed_expected,
+        max_packet,
+        container: _,
+        whole_file: _,
+    } = options;
+    let packets = &split_packets(&contained, max_packet)[..];
+    timing::packets(packets.len());
+    let mut dest = BufWriter::new(dest);
+    let dest = &mut dest;
+
+    if let Some(expected) = expected {
+        let mut expected = open_dest(&expected, &encoding.writing)?;
+        for packet in packets {
+            let checksum = encoding.checksum(packet);
+            writeln!(expected, "{}", encoding.algorithm.literal(checksum)).map_err(Error::Write)?;
         }
     }
 
-    fn reset(&mut self) {
-        self.a = 1;
-        self.b = 0;
-        self.content.clear();
-        self.length = 0;
+    let comments = annotate || header || trailer || embed_expected;
+    if comments && target != Target::Lines {
+        return Err(Error::Usage(
+            "--annotate, --header, --trailer and --embed-expected need --target lines".to_owned(),
+        ));
+    }
+    if encoding.protocol != Protocol::Length {
+        if ready_duty.is_some() || bubbles.is_some() || comments {
+            return Err(Error::Usage(
+                "--ready-duty, --bubbles, --annotate, --header, --trailer and \
+                 --embed-expected need --protocol length"
+                    .to_owned(),
+            ));
+        }
+        if target != Target::Lines {
+            return Err(Error::Usage(
+                "AXI4-Stream and Avalon-ST stimulus can only be written as lines".to_owned(),
+            ));
+        }
+        let lines = encode_framed(encoding, packets, dest)?;
+        dest.flush().map_err(Error::Write)?;
+        return Ok(lines);
+    }
+
+    let mut stimulus = Stimulus::new(encoding.line_format.clone());
+    for packet in packets {
+        stimulus.push_packet(encoding.line_format.encode_packet(packet)?);
+    }
+    let mut rng = Rng::new(seed);
+    if let Some(p) = bubbles {
+        stimulus.insert_idle(|line| line.data_valid && rng.chance(p));
+    }
+    if let Some(duty) = ready_duty {
+        let stalls = stimulus.insert_idle(|_| !rng.chance(duty));
+        if let Some(ready_file) = ready_file {
+            let mut ready = open_dest(&ready_file, &encoding.writing)?;
+            for stall in stalls {
+                writeln!(ready, "{}", !stall as u8).map_err(Error::Write)?;
+            }
+        }
+    }
+
+    if header {
+        let mut header = Header::new(&encoding.line_format, packets.len(), Some(seed));
+        header.algorithm = Some(encoding.algorithm);
+        header
+            .write(&encoding.comment_prefixes[0], &mut *dest)
+            .map_err(Error::Write)?;
+    }
+    match target {
+        Target::Lines if embed_expected => write_expecting(
+            &stimulus,
+            packets,
+            encoding.hasher(),
+            annotate,
+            &encoding.comment_prefixes[0],
+            &mut *dest,
+        ),
+        Target::Lines if annotate => {
+            stimulus.write_annotated(&encoding.comment_prefixes[0], &mut *dest)
+        }
+        Target::Lines => stimulus.write_lines(&mut *dest),
+        Target::Readmemb | Target::Readmemh => stimulus.write_readmem(
+            &mut *dest,
+            target == Target::Readmemh,
+            address_markers,
+            base_address,
+        ),
+        Target::Coe => stimulus.write_coe(&mut *dest),
+        Target::Mif => stimulus.write_mif(&mut *dest),
+        Target::Ihex => write_ihex(
+            &mut *dest,
+            &stimulus.to_bytes(endian),
+            base_address,
+            record_length,
+        ),
+        Target::Srec => write_srec(
+            &mut *dest,
+            &stimulus.to_bytes(endian),
+            srec_type,
+            base_address,
+            record_length,
+        ),
+        Target::Binary => dest.write_all(&stimulus.to_bytes(endian)),
+    }
+    .map_err(Error::Write)?;
+    if trailer {
+        let mut trailer = Trailer::new();
+        for line in stimulus.lines() {
+            trailer.push(&encoding.line_format.display(line).to_string());
+        }
+        trailer
+            .write(&encoding.comment_prefixes[0], &mut *dest)
+            .map_err(Error::Write)?;
+    }
+    dest.flush().map_err(Error::Write)?;
+    Ok(stimulus.lines().len())
+}
+
+/// Whether [`encode_stream`] can write what `options` asks for. Headers
+/// need the packets counted first, and bubbles and stalls together are
+/// drawn over the whole file in turn.
+fn streams(encoding: &Encoding, options: &EncodeArgs) -> bool {
+    options.target == Target::Lines
+        && encoding.protocol == Protocol::Length
+        && !options.header
+        && !options.whole_file
+        && !(options.bubbles.is_some() && options.ready_duty.is_some())
+}
+
+/// [`encode`] a packet at a time as they're read from `source`, one per
+/// line, so memory use doesn't grow with the file. Only for what
+/// [`streams`]. Returns the number of packets and lines written.
+fn encode_stream(
+    encoding: &Encoding,
+    source: impl BufRead,
+    options: EncodeArgs,
+    dest: &mut impl Write,
+) -> Result<(usize, usize)> {
+    let EncodeArgs {
+        ready_duty,
+        ready_file,
+        bubbles,
+        seed,
+        expected,
+        annotate,
+        trailer,
+        embed_expected,
+        max_packet,
+        container,
+        ..
+    } = options;
+    let format = &encoding.line_format;
+    let prefix = &encoding.comment_prefixes[0];
+    let open = |file: Option<String>| {
+        file.map(|file| open_dest(&file, &encoding.writing).map(BufWriter::new))
+            .transpose()
+    };
+    let mut expected = open(expected)?;
+    let mut ready = open(ready_file)?;
+    let mut dest = BufWriter::new(dest);
+    let mut rng = Rng::new(seed);
+    let mut sum = Trailer::new();
+    let (mut packets, mut lines) = (0, 0);
+    let mut beats = Vec::new();
+    for line in source.lines() {
+        let line = line.map_err(Error::Read)?;
+        let line = match container {
+            Some(container) => container.wrap(line.as_bytes()),
+            None => line.into_bytes(),
+        };
+        for packet in split_packets(&[line], max_packet) {
+            beats.clear();
+            for beat in format.encode_packet(packet)? {
+                // The same draws as Stimulus::insert_idle makes over the whole file
+                match (bubbles, ready_duty) {
+                    (Some(p), _) => {
+                        while beat.data_valid && rng.chance(p) {
+                            beats.push(DataLine::default());
+                        }
+                    }
+                    (_, Some(duty)) => {
+                        while !rng.chance(duty) {
+                            beats.push(DataLine::default());
+                            if let Some(ready) = &mut ready {
+                                writeln!(ready, "0").map_err(Error::Write)?;
+                            }
+                        }
+                        if let Some(ready) = &mut ready {
+                            writeln!(ready, "1").map_err(Error::Write)?;
+                        }
+                    }
+                    (None, None) => {}
+                }
+                beats.push(beat);
+            }
+
+            if annotate {
+                format.write_annotated(&beats, prefix, &mut dest)
+            } else {
+                beats
+                    .iter()
+                    .try_for_each(|beat| writeln!(dest, "{}", format.display(beat)))
+            }
+            .map_err(Error::Write)?;
+            if embed_expected {
+                let checksum = encoding.checksum(packet);
+                writeln!(
+                    dest,
+                    "{prefix} expect {}",
+                    encoding.algorithm.literal(checksum)
+                )
+                .map_err(Error::Write)?;
+            }
+            if let Some(expected) = &mut expected {
+                let checksum = encoding.checksum(packet);
+                writeln!(expected, "{}", encoding.algorithm.literal(checksum))
+                    .map_err(Error::Write)?;
+            }
+            if trailer {
+                for beat in &beats {
+                    sum.push(&format.display(beat).to_string());
+                }
+            }
+            packets += 1;
+            lines += beats.len();
+            timing::packets(1);
+        }
+    }
+    if trailer {
+        sum.write(prefix, &mut dest).map_err(Error::Write)?;
+    }
+    for out in [expected.as_mut(), ready.as_mut()].into_iter().flatten() {
+        out.flush().map_err(Error::Write)?;
     }
+    dest.flush().map_err(Error::Write)?;
+    Ok((packets, lines))
+}
 
-    fn checksum(&self) -> u32 {
-        let b = (self.b as u32).wrapping_shl(16);
-        let a = self.a as u32;
-        b | a
+/// Write `stimulus` as lines, annotated if `annotate`, following the last
+/// beat of each of `packets` with an `expect` comment holding its checksum
+/// by `hasher`.
+fn write_expecting<W: Write>(
+    stimulus: &Stimulus,
+    packets: &[&[u8]],
+    hasher: Checksum,
+    annotate: bool,
+    prefix: &str,
+    dest: &mut W,
+) -> io::Result<()> {
+    let format = stimulus.format();
+    let write = |lines: &[DataLine], dest: &mut W| match annotate {
+        true => format.write_annotated(lines, prefix, dest),
+        false => lines
+            .iter()
+            .try_for_each(|line| writeln!(dest, "{}", format.display(line))),
+    };
+    let lines = stimulus.lines();
+    let mut start = 0;
+    for (end, packet) in stimulus.packet_ends().into_iter().zip(packets) {
+        write(&lines[start..=end], dest)?;
+        let mut checksum = hasher;
+        checksum.update(packet);
+        writeln!(
+            dest,
+            "{prefix} expect {}",
+            hasher.algorithm().literal(checksum.finalize())
+        )?;
+        start = end + 1;
     }
+    write(&lines[start..], dest)
 }
 
-impl<I> Iterator for DataStream<I>
-where
-    I: Iterator<Item = DataLine>,
-{
-    type Item = (u32, String);
+/// Lines `packet` is encoded as on its own, without idle lines.
+fn packet_beats(encoding: &Encoding, packet: &[u8]) -> Result<Vec<String>> {
+    let format = &encoding.line_format;
+    let data_width = format.data_width();
+    Ok(match encoding.protocol {
+        Protocol::Length => format
+            .encode_packet(packet)?
+            .map(|line| format.display(&line).to_string())
+            .collect(),
+        Protocol::Axis => encode_axis_packet(packet, data_width)
+            .map(|beat| beat.display(data_width).to_string())
+            .collect(),
+        Protocol::Avalon => encode_avalon_packet(packet, data_width)
+            .map(|beat| beat.display(data_width).to_string())
+            .collect(),
+    })
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        for next in self.data.by_ref() {
-            if next.length_valid {
-                self.length = next.length;
+/// Unified diff of beats `a` against `b`, paired up by position, with
+/// [`DIFF_CONTEXT`] unchanged beats either side of each change.
+fn diff_beats(a: &[String], b: &[String]) -> Vec<String> {
+    let len = a.len().max(b.len());
+    let changed = (0..len).map(|i| a.get(i) != b.get(i)).collect::<Vec<_>>();
+    let shown = |i: usize| {
+        changed[i.saturating_sub(DIFF_CONTEXT)..(i + DIFF_CONTEXT + 1).min(len)].contains(&true)
+    };
+    let mut lines = Vec::new();
+    let mut last = None;
+    for i in (0..len).filter(|&i| shown(i)) {
+        if last.is_none_or(|last| i != last + 1) {
+            lines.push(format!("@@ beat {i} @@"));
+        }
+        last = Some(i);
+        match (a.get(i), b.get(i)) {
+            (Some(a), Some(b)) if a == b => lines.push(format!(" {a}")),
+            (a, b) => {
+                lines.extend(a.map(|a| format!("-{a}")));
+                lines.extend(b.map(|b| format!("+{b}")));
             }
+        }
+    }
+    lines
+}
 
-            if next.data_valid && self.length > 0 {
-                self.content.push(next.data as char);
-                self.a = (self.a + next.data as u16) % 65521;
-                self.b = self.b.overflowing_add(self.a).0 % 65521;
-                self.length -= 1;
-                if self.length == 0 {
-                    let retval = (self.checksum(), self.content.clone());
-                    self.reset();
-                    return Some(retval);
+/// Rewrite each line of length prefixed stimulus in `source` in format `to`,
+/// which has the same data width, keeping comments. A header or trailer is
+/// written afresh for the new lines. Returns the number of packets and lines
+/// written.
+fn transcode_lines(
+    encoding: &Encoding,
+    to: &LineFormat,
+    source: impl BufRead,
+    dest: &mut impl Write,
+) -> Result<(usize, usize)> {
+    let (encoding, header, source) = sniff(encoding, source)?;
+    let prefixes = &encoding.comment_prefixes;
+    // Every comment before the first line is part of the header, if any
+    let mut in_header = header.is_some();
+    if let Some(header) = header {
+        let mut rewritten = Header::new(to, header.packets.unwrap_or_default(), header.seed);
+        rewritten.algorithm = header.algorithm;
+        rewritten
+            .write(&prefixes[0], &mut *dest)
+            .map_err(Error::Write)?;
+    }
+    let (mut read, mut written) = (Trailer::new(), Trailer::new());
+    let (mut packets, mut count) = (0, 0);
+    for (number, text) in source.lines().enumerate() {
+        let text = text.map_err(Error::Read)?;
+        let comment = prefixes
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix.as_str()));
+        if let Some(comment) = comment {
+            if in_header {
+                continue;
+            }
+            match Trailer::parse(comment) {
+                None => writeln!(dest, "{text}").map_err(Error::Write)?,
+                Some(expected) if expected != read.checksum() => {
+                    return Err(Error::Trailer {
+                        line: number + 1,
+                        expected,
+                        actual: read.checksum(),
+                    });
+                }
+                Some(_) => {
+                    read.reset();
+                    written
+                        .write(&prefixes[0], &mut *dest)
+                        .map_err(Error::Write)?;
                 }
             }
+            continue;
+        }
+        in_header = false;
+        let code = strip_comment(&text, prefixes);
+        read.push(code);
+        let comment = &text[code.len()..];
+        let parsed = match encoding.strict {
+            true => encoding.line_format.parse_strict(code),
+            false => encoding.line_format.parse(code),
+        };
+        let line = parsed.map_err(|source| Error::Parse {
+            line: number + 1,
+            text: text.clone(),
+            source,
+        })?;
+        if line.length_valid && line.length > to.max_length() {
+            return Err(Error::PacketTooLong {
+                length: line.length,
+                width: to.length_width(),
+            });
         }
-        None
+        let rewritten = to.display(&line).to_string();
+        written.push(&rewritten);
+        writeln!(dest, "{rewritten}{comment}").map_err(Error::Write)?;
+        packets += line.length_valid as usize;
+        count += 1;
     }
+    Ok((packets, count))
 }
 
-fn main() {
-    let args = Args::parse();
+/// Write AXI4-Stream or Avalon-ST beats for every one of `packets`,
+/// returning how many were written.
+fn encode_framed(
+    encoding: &Encoding,
+    packets: &[impl AsRef<[u8]>],
+    dest: &mut impl Write,
+) -> Result<usize> {
+    let header = match encoding.protocol {
+        Protocol::Axis => AxisBeat::HEADER,
+        Protocol::Avalon => AvalonBeat::HEADER,
+        Protocol::Length => unreachable!("length framed stimulus goes through Stimulus"),
+    };
+    writeln!(dest, "{header}").map_err(Error::Write)?;
+
+    let mut count = 0;
+    for packet in packets {
+        let beats = packet_beats(encoding, packet.as_ref())?;
+        for beat in &beats {
+            writeln!(dest, "{beat}").map_err(Error::Write)?;
+        }
+        count += beats.len();
+    }
+    Ok(count)
+}
+
+/// Hash a single file, labelling the output with its name if `prefix` is set.
+fn hash(
+    output: &mut Output,
+    encoding: &Encoding,
+    filename: &str,
+    raw: bool,
+    manifest: bool,
+    prefix: bool,
+    window: &Window,
+) -> Result<()> {
+    if raw {
+        return output.file(
+            filename,
+            hash_file(encoding.hasher(), filename)?,
+            manifest,
+            prefix,
+        );
+    }
+
+    let (encoding, _, source) = sniff(encoding, open_source(filename)?)?;
+    output.set_algorithm(encoding.algorithm);
+    let label = prefix.then_some(filename);
+    for_each_packet_in(&encoding, source, window, |index, checksum, packet| {
+        output.listed(label, index, checksum, &content(&packet))
+    })?;
+    Ok(())
+}
+
+/// [`hash`] with each of `algorithms`, reading the file once. The first
+/// one replays the stimulus and the rest are computed over each packet it
+/// yields.
+fn hash_all(
+    output: &mut Output,
+    encoding: &Encoding,
+    algorithms: &[Algorithm],
+    filename: &str,
+    raw: bool,
+    prefix: bool,
+    window: &Window,
+) -> Result<()> {
+    if raw {
+        let mut hashers: Vec<_> = algorithms.iter().map(|&a| encoding.hasher_for(a)).collect();
+        let mut source = open_source(filename)?;
+        loop {
+            let buf = source.fill_buf().map_err(Error::Read)?;
+            if buf.is_empty() {
+                break;
+            }
+            hashers.iter_mut().for_each(|hasher| hasher.update(buf));
+            let read = buf.len();
+            source.consume(read);
+        }
+        let checksums: Vec<_> = hashers
+            .iter()
+            .map(|hasher| (hasher.algorithm(), hasher.finalize()))
+            .collect();
+        return output.file_checksums(filename, &checksums, prefix);
+    }
+
+    let label = prefix.then_some(filename);
+    for_each_packet_in(
+        encoding,
+        open_source(filename)?,
+        window,
+        |index, checksum, packet| {
+            let checksums: Vec<_> = std::iter::once((algorithms[0], checksum))
+                .chain(algorithms[1..].iter().map(|&a| {
+                    let mut hasher = encoding.hasher_for(a);
+                    hasher.update(&packet);
+                    (a, hasher.finalize())
+                }))
+                .collect();
+            output.listed_checksums(label, index, &checksums, &content(&packet))
+        },
+    )?;
+    Ok(())
+}
+
+/// Answer stimulus from `source` until it ends, writing a result to `dest`
+/// as soon as each packet completes.
+fn answer(
+    encoding: &Encoding,
+    format: OutputFormat,
+    source: impl BufRead,
+    dest: Box<dyn Write>,
+    raw: bool,
+) -> Result<()> {
+    if !raw {
+        let (encoding, _, source) = sniff(encoding, source)?;
+        let mut output = Output::new(format, dest).with_algorithm(encoding.algorithm);
+        return for_each_packet(&encoding, source, |index, checksum, packet| {
+            output.packet(None, index, checksum, &content(&packet))
+        });
+    }
+    let mut output = Output::new(format, dest).with_algorithm(encoding.algorithm);
+    for (index, packet) in source.lines().enumerate() {
+        let packet = packet.map_err(Error::Read)?;
+        output.packet(None, index, encoding.checksum(packet.as_bytes()), &packet)?;
+    }
+    Ok(())
+}
+
+/// Check the `results` a DUT produced against the `golden` checksum and
+/// content of each packet, matching them up by index.
+fn score(output: &mut Output, golden: &[(u64, String)], results: &[u64]) -> Result<()> {
+    let total = golden.len().max(results.len());
+    let mut failed = 0;
+    for index in 0..total {
+        let golden = golden.get(index);
+        let check = Check {
+            file: None,
+            packet: Some(index),
+            expected: golden.map(|(checksum, _)| *checksum),
+            checksum: results.get(index).copied(),
+            content: golden.map(|(_, content)| content.as_str()),
+        };
+        if !check.passed() {
+            failed += 1;
+        }
+        output.check(&check)?;
+    }
+    output.summary(total - failed, total)?;
+    output.finish()?;
+    if failed > 0 {
+        return Err(Error::Verify { failed, total });
+    }
+    Ok(())
+}
+
+/// Make a named pipe at `path` unless something is already there.
+#[cfg(unix)]
+fn create_fifo(path: &str) -> Result<()> {
+    use std::{ffi::CString, path::Path};
+
+    if Path::new(path).exists() {
+        return Ok(());
+    }
+    let error = |source| Error::Open {
+        path: path.to_owned(),
+        source,
+    };
+    let c_path = CString::new(path).map_err(|e| error(e.into()))?;
+    // SAFETY: c_path is a valid NUL terminated string
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        return Err(error(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_fifo(_path: &str) -> Result<()> {
+    Err(Error::Usage(
+        "--create needs a system with named pipes".to_owned(),
+    ))
+}
+
+/// Check every file listed in the md5sum style `manifests` against its
+/// checksum by `hasher`.
+fn check(output: &mut Output, hasher: Checksum, manifests: &[String]) -> Result<()> {
+    let mut total = 0;
+    let mut failed = 0;
+    for manifest in manifests {
+        for entry in read_manifest(open_source(manifest)?) {
+            let (want, path) = entry?;
+            let checksum = hash_file(hasher, &path).map_err(|e| error!("{e}")).ok();
+            let check = Check {
+                file: Some(&path),
+                packet: None,
+                expected: Some(want),
+                checksum,
+                content: None,
+            };
+            total += 1;
+            if !check.passed() {
+                failed += 1;
+            }
+            output.check(&check)?;
+        }
+    }
+    output.finish()?;
+    if failed > 0 {
+        return Err(Error::Verify { failed, total });
+    }
+    Ok(())
+}
+
+/// Check the trailer of the zlib stream in each of `files` against the
+/// data it holds, like [`check`].
+fn verify_zlib(output: &mut Output, files: &[String]) -> Result<()> {
+    let mut failed = 0;
+    for path in files {
+        let read = |path: &str| -> std::result::Result<ZlibStream, String> {
+            let mut stream = Vec::new();
+            open_source(path)
+                .and_then(|mut source| source.read_to_end(&mut stream).map_err(Error::Read))
+                .map_err(|e| e.to_string())?;
+            read_zlib(&stream).map_err(|e| e.to_string())
+        };
+        let stream = read(path).map_err(|e| error!("{path}: {e}")).ok();
+        let check = Check {
+            file: Some(path),
+            packet: None,
+            expected: stream.as_ref().map(|stream| stream.trailer.into()),
+            checksum: stream.as_ref().map(|stream| stream.checksum().into()),
+            content: None,
+        };
+        if !check.passed() {
+            failed += 1;
+        }
+        output.check(&check)?;
+    }
+    output.finish()?;
+    if failed > 0 {
+        return Err(Error::Verify {
+            failed,
+            total: files.len(),
+        });
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<()> {
+    let format = args.output_format;
+    let quiet = args.quiet;
+    let algorithms: Vec<Algorithm> = args.algorithm.into_iter().map(Algorithm::from).collect();
+    let algorithm = algorithms.first().copied().unwrap_or_default();
+    let output_to = move |out: Box<dyn Write>| {
+        Output::new(format, out)
+            .quiet(quiet)
+            .with_algorithm(algorithm)
+    };
+    let existing = match (args.append, args.force || args.backup.is_some()) {
+        (true, _) => Existing::Append,
+        (false, true) => Existing::Overwrite,
+        (false, false) => Existing::Refuse,
+    };
+    let writing = Writing {
+        existing,
+        backup: args.backup,
+        dry_run: false,
+    };
+    MMAP.store(args.mmap, Ordering::Relaxed);
+    if let Some(implementation) = args.implementation.map(Implementation::from) {
+        if !implementation.available() {
+            return Err(Error::Usage(format!(
+                "--impl {} isn't available in this build or on this CPU",
+                implementation.name()
+            )));
+        }
+        implementation.choose();
+    }
+    if args.jobs > 1 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.jobs)
+            .build_global()
+            .map_err(|e| Error::Usage(e.to_string()))?;
+    }
+    let mut output = output_to(Box::new(progress::stdout()));
+    if let Some(junit) = &args.junit {
+        output = output.with_junit(open_dest(junit, &writing)?);
+    }
+    let detect = args.format.is_none()
+        && args.data_width.is_none()
+        && args.length_width.is_none()
+        && args.radix.is_none();
+    let line_format = args.format.unwrap_or_else(|| {
+        LineFormat::new(
+            args.data_width.unwrap_or(8),
+            args.length_width.unwrap_or(32),
+        )
+    });
+    let line_format = match args.radix {
+        Some(radix) => line_format.with_radix(radix),
+        None => line_format,
+    };
+    let takes_several = matches!(
+        args.mode,
+        Mode::Hash {
+            check: false,
+            manifest: false,
+            ..
+        }
+    );
+    if algorithms.len() > 1 && !takes_several {
+        return Err(Error::Usage(
+            "only hash, without --check or --manifest, takes more than one --algorithm".to_owned(),
+        ));
+    }
+    let init = args
+        .init
+        .unwrap_or(args.init_b.unwrap_or(0) << 16 | args.init_a.unwrap_or(1));
+    let resumed = args.init.is_some() || args.init_a.is_some() || args.init_b.is_some();
+    let varied = resumed || args.modulus != MOD_ADLER;
+    if varied && !algorithms.is_empty() && !algorithms.contains(&Algorithm::Adler32) {
+        return Err(Error::Usage(
+            "--init, --init-a, --init-b and --modulus only apply to --algorithm adler32".to_owned(),
+        ));
+    }
+    if init & 0xffff >= args.modulus || init >> 16 >= args.modulus {
+        return Err(Error::Usage(format!(
+            "both halves of the initial checksum must be below the modulus, {}",
+            args.modulus
+        )));
+    }
+    let encoding = Encoding {
+        line_format,
+        protocol: args.protocol,
+        zero_length: args.zero_length,
+        on_truncated: args.on_truncated,
+        strict: args.strict,
+        comment_prefixes: args.comment_prefix,
+        detect,
+        jobs: args.jobs,
+        writing,
+        algorithm,
+        detect_algorithm: algorithms.is_empty(),
+        init,
+        modulus: args.modulus,
+    };
 
     match args.mode {
-        Mode::Hash => {
-            let file = OpenOptions::new()
-                .read(true)
-                .open(args.filename)
-                .expect("Failed to open file");
-            // Read the lines
-            let line_iter = std::io::BufReader::new(file).lines();
-            let data = line_iter
-                .map(|x| x.expect("Failed to read line"))
-                .filter(|x| !x.starts_with("#")) // Anything with a # is a comment
-                .map(|x| x.parse::<DataLine>().expect("Failed to parse line"));
-
-            DataStream::new(data)
+        Mode::Hash {
+            files, check: true, ..
+        } => check(&mut output, encoding.hasher(), &expand_inputs(&files)?)?,
+        Mode::Hash {
+            files,
+            raw,
+            manifest,
+            check: false,
+            window,
+        } => {
+            let files = expand_inputs(&files)?;
+            for filename in &files {
+                let prefix = files.len() > 1;
+                match algorithms.len() > 1 {
+                    true => hash_all(
+                        &mut output,
+                        &encoding,
+                        &algorithms,
+                        filename,
+                        raw,
+                        prefix,
+                        &window,
+                    )?,
+                    false => hash(
+                        &mut output,
+                        &encoding,
+                        filename,
+                        raw,
+                        manifest,
+                        prefix,
+                        &window,
+                    )?,
+                }
+            }
+            output.tally()?;
+        }
+        Mode::Encode {
+            filename,
+            dest_file,
+            dry_run,
+            options,
+        } => {
+            let source = open_source(&filename)?;
+            let encoding = encoding.dry_run(dry_run);
+            let mut dest = Counted::new(open_dest(&dest_file, &encoding.writing)?);
+            let mut report = output_to(report_to(&dest_file));
+
+            let (count, lines) = if streams(&encoding, &options) {
+                encode_stream(&encoding, source, options, &mut dest)?
+            } else {
+                let packets = source
+                    .lines()
+                    .collect::<io::Result<Vec<_>>>()
+                    .map_err(Error::Read)?;
+                let count = split_packets(&options.contain(&packets), options.max_packet).len();
+                (count, encode(&encoding, &packets, options, &mut dest)?)
+            };
+            match dry_run {
+                true => report.dry_run(count, lines, dest.bytes)?,
+                false => report.wrote(lines)?,
+            }
+        }
+        Mode::Merge {
+            filename,
+            dest_file,
+            max_size,
+            options,
+        } => {
+            let packets = merge_packets(read_packets(&encoding, &filename)?, max_size);
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.wrote(lines)?;
+        }
+        Mode::Cat {
+            files,
+            output: dest_file,
+            options,
+        } => {
+            let mut packets = Vec::new();
+            for file in expand_inputs(&files)? {
+                packets.extend(read_packets(&encoding, &file)?);
+            }
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.wrote(lines)?;
+        }
+        Mode::Split {
+            filename,
+            out_dir,
+            options,
+        } => {
+            if options.expected.is_some() || options.ready_file.is_some() {
+                return Err(Error::Usage(
+                    "split writes a file per packet, so --expected and --ready-file \
+                     can't be used"
+                        .to_owned(),
+                ));
+            }
+            std::fs::create_dir_all(&out_dir).map_err(|source| Error::Open {
+                path: out_dir.display().to_string(),
+                source,
+            })?;
+            let mut report = output_to(Box::new(progress::stdout()));
+
+            let mut lines = 0;
+            for (index, packet) in read_packets(&encoding, &filename)?.iter().enumerate() {
+                let path = out_dir.join(format!("packet_{index:04}.txt"));
+                let mut dest = open_dest(&path.to_string_lossy(), &encoding.writing)?;
+                lines += encode(&encoding, &[packet], options.clone(), &mut dest)?;
+            }
+            report.wrote(lines)?;
+        }
+        Mode::Extract {
+            filename,
+            packet,
+            output: dest_file,
+            options,
+        } => {
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            output.set_algorithm(encoding.algorithm);
+            let mut found = None;
+            let mut count = 0;
+            for_each_packet(&encoding, source, |index, checksum, content| {
+                if index == packet {
+                    found = Some((checksum, content));
+                }
+                count += 1;
+                Ok(())
+            })?;
+            let Some((checksum, bytes)) = found else {
+                return Err(Error::Usage(format!(
+                    "{filename} has {count} packets, no packet {packet}"
+                )));
+            };
+            if let Some(dest_file) = dest_file {
+                let mut dest = open_dest(&dest_file, &encoding.writing)?;
+                let mut report = output_to(report_to(&dest_file));
+                let lines = encode(&encoding, &[&bytes], options, &mut dest)?;
+                report.wrote(lines)?;
+            } else {
+                output.packet(None, packet, checksum, &content(&bytes))?;
+                output.beats(&packet_beats(&encoding, &bytes)?)?;
+            }
+        }
+        Mode::Grep {
+            filename,
+            pattern,
+            regex,
+        } => {
+            let pattern = match regex {
+                true => pattern,
+                false => regex::escape(&pattern),
+            };
+            let pattern =
+                regex::bytes::Regex::new(&pattern).map_err(|e| Error::Usage(e.to_string()))?;
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            output.set_algorithm(encoding.algorithm);
+            for_each_packet(&encoding, source, |index, checksum, packet| {
+                if !pattern.is_match(&packet) {
+                    return Ok(());
+                }
+                output.matched(index, checksum, &content(&packet))
+            })?;
+        }
+        Mode::Filter {
+            filename,
+            dest_file,
+            min_len,
+            max_len,
+            options,
+        } => {
+            if min_len > max_len {
+                return Err(Error::Usage(
+                    "--min-len can't be more than --max-len".to_owned(),
+                ));
+            }
+            let mut packets = read_packets(&encoding, &filename)?;
+            packets.retain(|packet| (min_len..=max_len).contains(&packet.len()));
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.wrote(lines)?;
+        }
+        Mode::Shuffle {
+            filename,
+            dest_file,
+            options,
+        } => {
+            let mut packets = read_packets(&encoding, &filename)?;
+            Rng::new(options.seed).shuffle(&mut packets);
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.wrote(lines)?;
+        }
+        Mode::Sort {
+            filename,
+            dest_file,
+            key,
+            reverse,
+            options,
+        } => {
+            let mut packets = read_packets(&encoding, &filename)?
                 .into_iter()
-                .for_each(|(checksum, content)| {
-                    println!("Checksum: 32'h{:0>8x} Content: {:?}", checksum, content);
+                .enumerate()
+                .map(|(index, packet)| {
+                    let key = match key {
+                        SortKey::Length => packet.len() as u64,
+                        SortKey::Checksum => encoding.checksum(&packet),
+                        SortKey::Index => index as u64,
+                    };
+                    (key, packet)
+                })
+                .collect::<Vec<_>>();
+            // Stable either way, so ties keep their order
+            match reverse {
+                true => packets.sort_by_key(|&(key, _)| Reverse(key)),
+                false => packets.sort_by_key(|&(key, _)| key),
+            }
+            let packets = packets
+                .into_iter()
+                .map(|(_, packet)| packet)
+                .collect::<Vec<_>>();
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.wrote(lines)?;
+        }
+        Mode::Dedup {
+            filename,
+            dest_file,
+            by_checksum,
+            options,
+        } => {
+            let mut packets = read_packets(&encoding, &filename)?;
+            let before = packets.len();
+            let mut seen_bytes = HashSet::new();
+            let mut seen_checksums = HashSet::new();
+            packets.retain(|packet| match by_checksum {
+                true => seen_checksums.insert(encoding.checksum(packet)),
+                false => seen_bytes.insert(packet.clone()),
+            });
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            report.removed(before - packets.len())?;
+            report.wrote(lines)?;
+        }
+        Mode::Transcode {
+            filename,
+            dest_file,
+            to,
+            dry_run,
+        } => {
+            let encoding = encoding.dry_run(dry_run);
+            let mut dest = Counted::new(open_dest(&dest_file, &encoding.writing)?);
+            let mut report = output_to(report_to(&dest_file));
+
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            let same_width = to.data_width() == encoding.line_format.data_width();
+            let (packets, lines) = if encoding.protocol == Protocol::Length && same_width {
+                transcode_lines(&encoding, &to, source, &mut dest)?
+            } else {
+                let mut packets = Vec::new();
+                for_each_packet(&encoding, source, |_, _, content| {
+                    packets.push(content);
+                    Ok(())
+                })?;
+                let lines = match encoding.protocol {
+                    Protocol::Length => {
+                        let mut stimulus = Stimulus::new(to.clone());
+                        for packet in &packets {
+                            stimulus.push_packet(to.encode_packet(packet)?);
+                        }
+                        stimulus.write_lines(&mut dest).map_err(Error::Write)?;
+                        stimulus.lines().len()
+                    }
+                    Protocol::Axis | Protocol::Avalon => {
+                        let encoding = Encoding {
+                            line_format: to,
+                            ..encoding
+                        };
+                        encode_framed(&encoding, &packets, &mut dest)?
+                    }
+                };
+                (packets.len(), lines)
+            };
+            match dry_run {
+                true => report.dry_run(packets, lines, dest.bytes)?,
+                false => report.wrote(lines)?,
+            }
+        }
+        Mode::Generate {
+            dest_file,
+            corners,
+            constraints,
+            packets,
+            min_len,
+            max_len,
+            mut options,
+        } => {
+            if min_len > max_len {
+                return Err(Error::Usage(
+                    "--min-len can't be more than --max-len".to_owned(),
+                ));
+            }
+            let packets = if corners {
+                let max_length = encoding.line_format.max_length().min(MAX_CORNER_LENGTH);
+                corner_packets(max_length as usize)
+            } else {
+                let constraints = match constraints {
+                    Some(path) => Constraints::load(&path, open_source(&path)?)?,
+                    None => Constraints::new(packets, min_len, max_len),
+                };
+                options.bubbles = options.bubbles.or(constraints.bubbles);
+                options.ready_duty = options.ready_duty.or(constraints.ready_duty);
+                constraints.generate(&mut Rng::new(options.seed))
+            };
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            let mut report = output_to(report_to(&dest_file));
+
+            let sent = options.contain(&packets);
+            let sent = split_packets(&sent, options.max_packet);
+            let lines = encode(&encoding, &packets, options, &mut dest)?;
+            for (index, packet) in sent.into_iter().enumerate() {
+                let checksum = encoding.checksum(packet);
+                report.listed(None, index, checksum, &content(packet))?;
+            }
+            report.wrote(lines)?;
+        }
+        Mode::Corrupt {
+            filename,
+            dest_file,
+            flips,
+            seed,
+            manifest,
+        } => {
+            if encoding.protocol != Protocol::Length {
+                return Err(Error::Usage(
+                    "only --protocol length stimulus can be corrupted".to_owned(),
+                ));
+            }
+            let line_format = &encoding.line_format;
+            let mut lines =
+                read_lines(&encoding, open_source(&filename)?, None).collect::<Result<Vec<_>>>()?;
+            let damaged = flip_bits(
+                &mut lines,
+                line_format.data_width(),
+                flips,
+                &mut Rng::new(seed),
+            );
+
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            for line in &lines {
+                writeln!(dest, "{}", line_format.display(line)).map_err(Error::Write)?;
+            }
+            let mut manifest = open_dest(&manifest, &encoding.writing)?;
+            for packet in damaged {
+                writeln!(manifest, "{packet}").map_err(Error::Write)?;
+            }
+            output_to(report_to(&dest_file)).wrote(lines.len())?;
+        }
+        Mode::PerturbExpected {
+            filename,
+            dest_file,
+            percent,
+            seed,
+            manifest,
+        } => {
+            let mut checksums =
+                read_checksums(open_source(&filename)?).collect::<Result<Vec<_>>>()?;
+            let count = (checksums.len() as f64 * percent / 100.0).round() as usize;
+            let changed = perturb_checksums(&mut checksums, count, &mut Rng::new(seed));
+
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            for checksum in &checksums {
+                writeln!(dest, "{}", encoding.algorithm.literal(*checksum))
+                    .map_err(Error::Write)?;
+            }
+            let mut manifest = open_dest(&manifest, &encoding.writing)?;
+            for index in changed {
+                writeln!(manifest, "{index}").map_err(Error::Write)?;
+            }
+            output_to(report_to(&dest_file)).wrote(checksums.len())?;
+        }
+        Mode::Stats { filename, bytes } => {
+            let mut stimulus = Vec::new();
+            open_source(&filename)?
+                .read_to_end(&mut stimulus)
+                .map_err(Error::Read)?;
+            let mut packets = Vec::new();
+            for_each_packet(&encoding, &stimulus[..], |_, _, content| {
+                packets.push(content);
+                Ok(())
+            })?;
+            let (idle_lines, lines) = count_idle(&encoding, &stimulus[..])?;
+            let mut stats = Stats::new(&packets, lines, idle_lines);
+            if bytes {
+                stats.byte_values = Some(ByteStats::new(&packets));
+            }
+            output.stats(&stats)?;
+        }
+        Mode::Diff { a, b } => {
+            let read = |filename: &str| {
+                let (encoding, _, source) = sniff(&encoding, open_source(filename)?)?;
+                let mut packets = Vec::new();
+                for_each_packet(&encoding, source, |_, checksum, content| {
+                    packets.push((checksum, content));
+                    Ok(())
+                })?;
+                Ok::<_, Error>((encoding.algorithm, packets))
+            };
+            let ((algorithm, a_packets), (_, b_packets)) = (read(&a)?, read(&b)?);
+            // Written as the first file's checksums
+            output.set_algorithm(algorithm);
+            let total = a_packets.len().max(b_packets.len());
+            let mut differing = 0;
+            for packet in 0..total {
+                let (a_packet, b_packet) = (a_packets.get(packet), b_packets.get(packet));
+                let beats = |side: Option<&(u64, Vec<u8>)>| match side {
+                    Some((_, bytes)) => packet_beats(&encoding, bytes),
+                    None => Ok(Vec::new()),
+                };
+                let first_difference = match (a_packet, b_packet) {
+                    (Some((_, a)), Some((_, b))) if a != b => Some(
+                        a.iter()
+                            .zip(b)
+                            .position(|(a, b)| a != b)
+                            .unwrap_or(a.len().min(b.len())),
+                    ),
+                    _ => None,
+                };
+                let diff = PacketDiff {
+                    packet,
+                    a: a_packet.map(|(checksum, bytes)| (bytes.len(), *checksum)),
+                    b: b_packet.map(|(checksum, bytes)| (bytes.len(), *checksum)),
+                    first_difference,
+                    beats: diff_beats(&beats(a_packet)?, &beats(b_packet)?),
+                };
+                if diff.differs() {
+                    differing += 1;
+                }
+                output.diff([&a, &b], &diff)?;
+            }
+            output.finish()?;
+            if differing > 0 {
+                return Err(Error::Differ { differing, total });
+            }
+        }
+        Mode::Lint { filename } => {
+            if encoding.protocol != Protocol::Length {
+                return Err(Error::Usage(
+                    "only --protocol length stimulus can be linted".to_owned(),
+                ));
+            }
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            let report = lint(&encoding.line_format, source, &encoding.comment_prefixes)
+                .map_err(Error::Read)?;
+            for finding in &report.findings {
+                output.finding(&filename, finding.line, &finding.problem.to_string())?;
+            }
+            output.linted(report.packets, report.lines)?;
+            output.finish()?;
+            if !report.findings.is_empty() {
+                return Err(Error::Lint {
+                    problems: report.findings.len(),
                 });
+            }
         }
-        Mode::Encode { dest_file } => {
-            let source = OpenOptions::new()
-                .read(true)
-                .open(args.filename)
-                .expect("Failed to open source file");
-            let source = BufReader::new(source);
-            let mut dest = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .append(true)
-                .open(dest_file)
-                .expect("Failed to open destination file");
+        Mode::Repair {
+            filename,
+            dest_file,
+        } => {
+            if encoding.protocol != Protocol::Length {
+                return Err(Error::Usage(
+                    "only --protocol length stimulus can be repaired".to_owned(),
+                ));
+            }
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            let repaired = repair(&encoding.line_format, source, &encoding.comment_prefixes)
+                .map_err(Error::Read)?;
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            for line in &repaired.lines {
+                writeln!(dest, "{line}").map_err(Error::Write)?;
+            }
+            let mut report = output_to(report_to(&dest_file));
+            for change in &repaired.changes {
+                report.change(&filename, change.line, &change.fix.to_string())?;
+            }
+            report.repaired(repaired.changes.len())?;
+        }
+        Mode::Annotate {
+            filename,
+            dest_file,
+        } => {
+            if encoding.protocol != Protocol::Length {
+                return Err(Error::Usage(
+                    "only --protocol length stimulus can be annotated".to_owned(),
+                ));
+            }
+            let lines =
+                read_lines(&encoding, open_source(&filename)?, None).collect::<Result<Vec<_>>>()?;
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            encoding
+                .line_format
+                .write_annotated(&lines, &encoding.comment_prefixes[0], &mut dest)
+                .map_err(Error::Write)?;
+        }
+        Mode::Decode {
+            filename,
+            dest_file,
+            dry_run,
+            window,
+        } => {
+            // Sniffed here too, so checksums are written as the header's
+            // algorithm
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            let encoding = encoding.dry_run(dry_run);
+            let mut dest = Counted::new(open_dest(&dest_file, &encoding.writing)?);
+            let mut report = output_to(report_to(&dest_file)).with_algorithm(encoding.algorithm);
+            let mut checksums = Vec::new();
 
-            let source_lines: Vec<DataLine> = source
-                .lines()
-                .map(|l| l.expect("Failed to read line"))
-                .map(|line| {
-                    iter::once(DataLine {
-                        length_valid: true,
-                        length: line.len() as u32,
-                        data_valid: false,
-                        data: 0,
-                    })
-                    .chain(line.bytes().map(|character| DataLine::from(character)))
-                    .collect::<Vec<_>>() // This could be avoided maybe. I'm .... rusty
-                })
-                .flatten()
-                .collect();
+            let expects =
+                for_each_packet_in(&encoding, source, &window, |index, checksum, packet| {
+                    dest.write_all(&packet).map_err(Error::Write)?;
+                    dest.write_all(b"\n").map_err(Error::Write)?;
+                    checksums.push((index, checksum));
+                    report.listed(None, index, checksum, &content(&packet))
+                })?;
+            report.tally()?;
+            if dry_run {
+                report.dry_run(checksums.len(), checksums.len(), dest.bytes)?;
+            }
+            let mut total = 0;
+            let mut failed = 0;
+            for (index, actual) in checksums {
+                let Some(expected) = expects.get(index).copied().flatten() else {
+                    continue;
+                };
+                total += 1;
+                if expected != actual {
+                    failed += 1;
+                    error!(
+                        "packet {index}: {}",
+                        Error::ChecksumMismatch {
+                            algorithm: encoding.algorithm,
+                            expected,
+                            actual
+                        }
+                    );
+                }
+            }
+            if failed > 0 {
+                return Err(Error::Verify { failed, total });
+            }
+        }
+        Mode::VerifyZlib { files } => verify_zlib(&mut output, &expand_inputs(&files)?)?,
+        Mode::Combine { shards } => {
+            if encoding.algorithm != Algorithm::Adler32 {
+                return Err(Error::Usage(format!(
+                    "combine only combines Adler-32 checksums, not {}",
+                    encoding.algorithm.title()
+                )));
+            }
+            if encoding.modulus != MOD_ADLER {
+                return Err(Error::Usage(
+                    "combine only combines checksums with the standard modulus".to_owned(),
+                ));
+            }
+            let (checksum, length) = shards.into_iter().fold(
+                (adler32(b""), 0u64),
+                |(checksum, length), (shard, shard_length)| {
+                    (
+                        combine(checksum, shard, shard_length),
+                        length + shard_length,
+                    )
+                },
+            );
+            output.combined(checksum, length)?;
+        }
+        Mode::Verify {
+            filename,
+            expected,
+            window,
+        } => {
+            let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+            output.set_algorithm(encoding.algorithm);
+            let expected = match &expected {
+                Some(expected) => Some(
+                    read_checksums(open_source(expected)?)
+                        .map(|checksum| checksum.map(Some))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                None => None,
+            };
+            let mut results = Vec::new();
+            let expects =
+                for_each_packet_in(&encoding, source, &window, |index, checksum, packet| {
+                    results.push((index, checksum, content(&packet)));
+                    Ok(())
+                })?;
+            // Embedded checksums only arrive after their packet, so the checks
+            // wait until the whole file is read
+            let expected = match expected {
+                Some(expected) => expected,
+                None if expects.iter().any(Option::is_some) => expects,
+                None => {
+                    return Err(Error::Usage(
+                        "the file has no expect comments, so --expected is needed".to_owned(),
+                    ))
+                }
+            };
+            let packets = results.len();
+            let mut failed = 0;
+            for (index, checksum, content) in &results {
+                let check = Check {
+                    file: None,
+                    packet: Some(*index),
+                    expected: expected.get(*index).copied().flatten(),
+                    checksum: Some(*checksum),
+                    content: Some(content),
+                };
+                if !check.passed() {
+                    failed += 1;
+                }
+                output.check(&check)?;
+            }
+            let expected = expected
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| window.contains(index))
+                .collect::<Vec<_>>();
+            for &(index, &want) in expected.iter().skip(packets) {
+                failed += 1;
+                output.check(&Check {
+                    file: None,
+                    packet: Some(index),
+                    expected: want,
+                    checksum: None,
+                    content: None,
+                })?;
+            }
 
-            for line in &source_lines {
-                dest.write_fmt(format_args!("{line}\n"))
-                    .expect("failed to write to file");
+            let total = packets.max(expected.len());
+            output.summary(total - failed, total)?;
+            output.finish()?;
+            if failed > 0 {
+                return Err(Error::Verify { failed, total });
             }
-            println!("Wrote {} lines", source_lines.len());
         }
-        Mode::Decode { dest_file } => {
-            let file = OpenOptions::new()
-                .read(true)
-                .open(args.filename)
-                .expect("Failed to open file");
-            let mut dest = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(dest_file)
-                .expect("Failed to open destination file");
-            // Read the lines
-            let line_iter = std::io::BufReader::new(file).lines();
-            let data = line_iter
-                .map(|x| x.expect("Failed to read line"))
-                .filter(|x| !x.starts_with("#")) // Anything with a # is a comment
-                .map(|x| x.parse::<DataLine>().expect("Failed to parse line"));
-
-            DataStream::new(data)
+        Mode::Compare {
+            dut_results,
+            golden,
+            raw,
+            pattern,
+        } => {
+            let source = open_source(&dut_results)?;
+            let results = match &pattern {
+                Some(pattern) => read_log_checksums(source, pattern).collect::<Result<Vec<_>>>()?,
+                None => read_checksums(source).collect::<Result<Vec<_>>>()?,
+            };
+            let mut packets = Vec::new();
+            if raw {
+                for packet in open_source(&golden)?.lines() {
+                    let packet = packet.map_err(Error::Read)?;
+                    packets.push((encoding.checksum(packet.as_bytes()), packet));
+                }
+            } else {
+                let (encoding, _, source) = sniff(&encoding, open_source(&golden)?)?;
+                output.set_algorithm(encoding.algorithm);
+                for_each_packet(&encoding, source, |_, checksum, packet| {
+                    packets.push((checksum, content(&packet)));
+                    Ok(())
+                })?;
+            }
+
+            score(&mut output, &packets, &results)?;
+        }
+        Mode::Run {
+            filename,
+            stimulus,
+            options,
+            pattern,
+            log,
+            command,
+        } => {
+            let packets = open_source(&filename)?
+                .lines()
+                .collect::<io::Result<Vec<_>>>()
+                .map_err(Error::Read)?;
+            let sent = options.contain(&packets);
+            let golden = split_packets(&sent, options.max_packet)
                 .into_iter()
-                .for_each(|(checksum, content)| {
-                    dest.write_fmt(format_args!("{}\n", content))
-                        .expect("Failed to write to file");
-                    println!("Checksum: 32'h{:0>8x} Content: {:?}", checksum, content);
+                .map(|packet| (encoding.checksum(packet), content(packet)))
+                .collect::<Vec<_>>();
+            let mut dest = open_dest(&stimulus, &encoding.writing)?;
+            encode(&encoding, &packets, options, &mut dest)?;
+            drop(dest);
+            // The simulator reads the stimulus, so it has to be in place now
+            commit_staged()?;
+
+            let program = &command[0];
+            let run = Command::new(program)
+                .args(&command[1..])
+                .stdin(Stdio::null())
+                .stderr(Stdio::inherit())
+                .output()
+                .map_err(|source| Error::Open {
+                    path: program.clone(),
+                    source,
+                })?;
+            if !run.status.success() {
+                io::stderr().write_all(&run.stdout).map_err(Error::Write)?;
+                return Err(Error::Command {
+                    command: program.clone(),
+                    status: run.status.to_string(),
                 });
+            }
+
+            let transcript: Box<dyn BufRead> = match &log {
+                Some(log) => open_source(log)?,
+                None => Box::new(&run.stdout[..]),
+            };
+            let results = read_log_checksums(transcript, &pattern).collect::<Result<Vec<_>>>()?;
+            score(&mut output, &golden, &results)?;
+        }
+        Mode::GenTb {
+            testbench,
+            stimulus,
+            language,
+        } => {
+            let (testbench, mut dest) = testbench.build(&encoding, stimulus)?;
+            match language {
+                Language::Sv => testbench.write_sv(&mut dest),
+                Language::Vhdl => testbench.write_vhdl(&mut dest),
+            }
+            .map_err(Error::Write)?;
+        }
+        Mode::Export {
+            filename,
+            dest_file,
+            lang,
+            raw,
+        } => {
+            let mut vectors = Vec::new();
+            // Raw packets are only text, with no header to read
+            let encoding = if raw {
+                for packet in open_source(&filename)?.lines() {
+                    let packet = packet.map_err(Error::Read)?.into_bytes();
+                    vectors.push((packet.clone(), encoding.checksum(&packet)));
+                }
+                encoding
+            } else {
+                let (encoding, _, source) = sniff(&encoding, open_source(&filename)?)?;
+                for_each_packet(&encoding, source, |_, checksum, content| {
+                    vectors.push((content, checksum));
+                    Ok(())
+                })?;
+                encoding
+            };
+            let vectors = Vectors::new(vectors).with_algorithm(encoding.algorithm);
+            let mut dest = open_dest(&dest_file, &encoding.writing)?;
+            match lang {
+                Lang::C => vectors.write_c(&mut dest),
+                Lang::Rust => vectors.write_rust(&mut dest),
+                Lang::Python => vectors.write_python(&mut dest),
+            }
+            .map_err(Error::Write)?;
+        }
+        Mode::Serve { port, bind, raw } => {
+            let listener =
+                TcpListener::bind((bind.as_str(), port)).map_err(|source| Error::Open {
+                    path: format!("{bind}:{port}"),
+                    source,
+                })?;
+            info!("listening on {bind}:{port}");
+            for stream in listener.incoming() {
+                let Ok(stream) = stream.map_err(|e| error!("{e}")) else {
+                    continue;
+                };
+                let Ok(dest) = stream.try_clone().map_err(|e| error!("{e}")) else {
+                    continue;
+                };
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "client".to_owned(), |peer| peer.to_string());
+                let encoding = encoding.clone();
+                thread::spawn(move || {
+                    let source = BufReader::new(stream);
+                    if let Err(e) = answer(&encoding, format, source, Box::new(dest), raw) {
+                        error!("{peer}: {e}");
+                    }
+                });
+            }
+        }
+        Mode::Bridge {
+            input,
+            output: results,
+            raw,
+            create,
+            once,
+        } => {
+            if create {
+                create_fifo(&input)?;
+                create_fifo(&results)?;
+            }
+            // Opening a pipe blocks until the other end is opened too
+            let open_error = |path: &str| {
+                let path = path.to_owned();
+                move |source| Error::Open { path, source }
+            };
+            let dest = OpenOptions::new()
+                .write(true)
+                .open(&results)
+                .map_err(open_error(&results))?;
+            loop {
+                let source = File::open(&input).map_err(open_error(&input))?;
+                let dest = dest.try_clone().map_err(Error::Write)?;
+                answer(
+                    &encoding,
+                    format,
+                    BufReader::new(source),
+                    Box::new(dest),
+                    raw,
+                )?;
+                // The writer closed its end; wait for the next one
+                if once {
+                    break;
+                }
+            }
+        }
+        Mode::GenCocotb {
+            testbench,
+            stimulus,
+        } => {
+            let (testbench, mut dest) = testbench.build(&encoding, stimulus)?;
+            testbench.write_cocotb(&mut dest).map_err(Error::Write)?;
+        }
+        Mode::Bench { size, iterations } => {
+            let data = Rng::new(0).bytes(size as usize);
+            let chosen = Implementation::chosen();
+            for implementation in Implementation::ALL {
+                if chosen.is_some_and(|chosen| chosen != implementation) {
+                    continue;
+                }
+                if !implementation.available() {
+                    warn!(
+                        "{}: not available in this build or on this CPU",
+                        implementation.name()
+                    );
+                    continue;
+                }
+                let start = Instant::now();
+                let mut checksum = 0;
+                for _ in 0..iterations {
+                    let mut hasher = Adler32::new();
+                    hasher.update_with(implementation, hint::black_box(&data));
+                    checksum = hint::black_box(hasher.finalize());
+                }
+                let name = match (implementation, vector_unit()) {
+                    (Implementation::Simd, Some(unit)) => format!("simd ({unit})"),
+                    _ => implementation.name().to_owned(),
+                };
+                let bytes = size * iterations;
+                output.throughput(&name, bytes, start.elapsed().as_secs_f64(), checksum)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let start = Instant::now();
+    let args = Args::parse();
+    log::init(args.log_format, args.verbose, args.quiet);
+    progress::init(args.quiet);
+    let timing = args.timing;
+    let code = match run(args).and_then(|()| commit_staged()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            discard_staged();
+            error!("{e}");
+            ExitCode::from(e.exit_code())
         }
+    };
+    if let Some(format) = timing {
+        // Too late to fail the run over
+        let _ = timing::report(format, start, io::stderr());
     }
-    // println!("Checksum: 32'h{:x}", v);
+    code
 }
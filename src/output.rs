@@ -0,0 +1,1196 @@
+//! How results are printed, selected with `--output-format`.
+
+use std::{collections::BTreeMap, io::Write};
+
+use adler32::{Algorithm, Error, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+    /// Test Anything Protocol, one test point per check
+    Tap,
+}
+
+/// `0x091e01de`, the way checksums appear in structured output, with as
+/// many digits as `algorithm`'s are wide
+fn hex(algorithm: Algorithm, checksum: u64) -> String {
+    let digits = algorithm.bits() as usize / 4;
+    format!("0x{checksum:0>digits$x}")
+}
+
+/// `Adler-32: 32'h091e01de CRC-32: 32'hcbf43926`, for text output
+fn checksums_text(checksums: &[(Algorithm, u64)]) -> String {
+    checksums
+        .iter()
+        .map(|&(algorithm, checksum)| {
+            format!("{}: {}", algorithm.title(), algorithm.literal(checksum))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checksums in structured output, by algorithm name
+fn checksums_by_name(checksums: &[(Algorithm, u64)]) -> BTreeMap<&'static str, String> {
+    checksums
+        .iter()
+        .map(|&(algorithm, checksum)| (algorithm.name(), hex(algorithm, checksum)))
+        .collect()
+}
+
+/// `1 packet`, `2 packets`
+fn plural(count: u64, noun: &str) -> String {
+    match count {
+        1 => format!("{count} {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// How a check reads in text output: `PASS packet 0: 32'h...`, or md5sum
+/// style for whole files
+fn check_text(check: &Check, algorithm: Algorithm) -> String {
+    let passed = check.passed();
+    match (check.file, check.packet) {
+        // md5sum --check style
+        (Some(file), None) => match (passed, check.checksum) {
+            (true, _) => format!("{file}: OK"),
+            (false, Some(_)) => format!("{file}: FAILED"),
+            (false, None) => format!("{file}: FAILED open or read"),
+        },
+        (_, packet) => {
+            let packet = packet.unwrap_or_default();
+            let content = check
+                .content
+                .map(|content| format!(" Content: {content:?}"))
+                .unwrap_or_default();
+            let literal = |checksum| algorithm.literal(checksum);
+            match (check.expected, check.checksum) {
+                (Some(checksum), _) if passed => {
+                    format!("PASS packet {packet}: {}", literal(checksum))
+                }
+                (Some(want), Some(got)) => format!(
+                    "FAIL packet {packet}: expected {}, got {}{content}",
+                    literal(want),
+                    literal(got)
+                ),
+                (None, Some(got)) => format!(
+                    "FAIL packet {packet}: no expected checksum, got {}{content}",
+                    literal(got)
+                ),
+                (Some(want), None) => {
+                    format!(
+                        "FAIL packet {packet}: expected {}, packet missing",
+                        literal(want)
+                    )
+                }
+                (None, None) => format!("FAIL packet {packet}"),
+            }
+        }
+    }
+}
+
+/// What a check is about: `packet 0`, or the file checked
+fn check_name(check: &Check) -> String {
+    match (check.file, check.packet) {
+        (Some(file), Some(packet)) => format!("{file} packet {packet}"),
+        (Some(file), None) => file.to_owned(),
+        (None, packet) => format!("packet {}", packet.unwrap_or_default()),
+    }
+}
+
+/// Escape `text` for an XML attribute or text node
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&apos;".to_owned(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Checks collected for a JUnit XML report, written once the run is over
+struct JUnit {
+    out: Box<dyn Write>,
+    /// Test case name and, if it failed, why
+    cases: Vec<(String, Option<String>)>,
+}
+
+impl JUnit {
+    fn write(&mut self) -> std::io::Result<()> {
+        let failures = self
+            .cases
+            .iter()
+            .filter(|(_, failure)| failure.is_some())
+            .count();
+        writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            self.out,
+            r#"<testsuite name="adler32" tests="{}" failures="{failures}" errors="0">"#,
+            self.cases.len()
+        )?;
+        for (name, failure) in &self.cases {
+            let name = xml_escape(name);
+            match failure {
+                None => writeln!(
+                    self.out,
+                    r#"  <testcase classname="adler32" name="{name}"/>"#
+                )?,
+                Some(message) => {
+                    let message = xml_escape(message);
+                    writeln!(
+                        self.out,
+                        r#"  <testcase classname="adler32" name="{name}">"#
+                    )?;
+                    writeln!(
+                        self.out,
+                        r#"    <failure message="{message}">{message}</failure>"#
+                    )?;
+                    writeln!(self.out, "  </testcase>")?;
+                }
+            }
+        }
+        writeln!(self.out, "</testsuite>")?;
+        self.out.flush()
+    }
+}
+
+#[derive(Serialize)]
+struct PacketRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    packet: usize,
+    checksum: String,
+    length: usize,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct FileRecord<'a> {
+    file: &'a str,
+    checksum: String,
+}
+
+/// Several checksums of a packet, or of a whole file if `packet` is `None`
+#[derive(Serialize)]
+struct ChecksumsRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    packet: Option<usize>,
+    /// By algorithm name
+    checksums: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct CheckRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    packet: Option<usize>,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct SummaryRecord {
+    passed: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct BeatsRecord<'a> {
+    beats: &'a [String],
+}
+
+#[derive(Serialize)]
+struct RemovedRecord {
+    removed: usize,
+}
+
+#[derive(Serialize)]
+struct TallyRecord {
+    packets: usize,
+}
+
+#[derive(Serialize)]
+struct WroteRecord {
+    lines: usize,
+}
+
+#[derive(Serialize)]
+struct DryRunRecord {
+    packets: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct FindingRecord<'a> {
+    file: &'a str,
+    line: usize,
+    problem: &'a str,
+}
+
+#[derive(Serialize)]
+struct LintedRecord {
+    packets: usize,
+    lines: usize,
+}
+
+#[derive(Serialize)]
+struct ChangeRecord<'a> {
+    file: &'a str,
+    line: usize,
+    fix: &'a str,
+}
+
+#[derive(Serialize)]
+struct RepairedRecord {
+    changes: usize,
+}
+
+#[derive(Serialize)]
+struct CombinedRecord {
+    checksum: String,
+    length: u64,
+}
+
+#[derive(Serialize)]
+struct ThroughputRecord<'a> {
+    implementation: &'a str,
+    bytes: u64,
+    seconds: f64,
+    bytes_per_second: f64,
+    checksum: String,
+}
+
+#[derive(Serialize)]
+struct DiffRecord<'a> {
+    packet: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_difference: Option<usize>,
+    beats: &'a [String],
+}
+
+/// Packet number `packet` of two files compared by `diff`
+pub struct PacketDiff {
+    pub packet: usize,
+    /// Length and checksum of the packet in each file, `None` if the file
+    /// has fewer packets
+    pub a: Option<(usize, u64)>,
+    pub b: Option<(usize, u64)>,
+    /// Offset of the first byte that differs, `None` if the bytes match
+    pub first_difference: Option<usize>,
+    /// Unified diff of the beats carrying the packet in each file
+    pub beats: Vec<String>,
+}
+
+impl PacketDiff {
+    pub fn differs(&self) -> bool {
+        self.a.is_none() || self.b.is_none() || self.first_difference.is_some()
+    }
+
+    fn text(&self, files: [&str; 2], algorithm: Algorithm) -> String {
+        let mut text = format!("@@ packet {}", self.packet);
+        if let Some(offset) = self.first_difference {
+            text += &format!(", first difference at byte {offset}");
+        }
+        text += " @@";
+        for ((sign, file), side) in ['-', '+'].into_iter().zip(files).zip([self.a, self.b]) {
+            text += &match side {
+                Some((length, checksum)) => format!(
+                    "\n{sign} {file}: {length} bytes, checksum {}",
+                    algorithm.literal(checksum)
+                ),
+                None => format!("\n{sign} {file}: no such packet"),
+            };
+        }
+        for beat in &self.beats {
+            text += "\n";
+            text += beat;
+        }
+        text
+    }
+}
+
+/// Packets with lengths from `min` to `max` bytes
+#[derive(Serialize)]
+pub struct Bucket {
+    pub min: usize,
+    pub max: usize,
+    pub packets: usize,
+}
+
+/// What a stimulus file exercises, from `stats`
+#[derive(Serialize)]
+pub struct Stats {
+    pub packets: usize,
+    /// Data bytes across every packet
+    pub bytes: usize,
+    pub min_length: usize,
+    pub mean_length: f64,
+    pub max_length: usize,
+    /// Packet lengths in power of two buckets: 0, 1, 2-3, 4-7, ...
+    pub histogram: Vec<Bucket>,
+    pub lines: usize,
+    /// Lines with nothing valid on them
+    pub idle_lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_values: Option<ByteStats>,
+}
+
+/// Which byte values stimulus exercises
+#[derive(Serialize)]
+pub struct ByteStats {
+    /// Times each byte value appears, indexed by value
+    pub counts: Vec<usize>,
+    /// Shannon entropy of all the data, in bits per byte
+    pub entropy: f64,
+    /// Shannon entropy of each packet, in bits per byte
+    pub packet_entropy: Vec<f64>,
+}
+
+/// Shannon entropy in bits per byte of data with these byte value `counts`
+fn entropy(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Times each byte value appears in `data`
+fn byte_counts(data: &[u8]) -> Vec<usize> {
+    let mut counts = vec![0; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    counts
+}
+
+impl ByteStats {
+    pub fn new(packets: &[Vec<u8>]) -> Self {
+        let mut counts = vec![0; 256];
+        let mut packet_entropy = Vec::with_capacity(packets.len());
+        for packet in packets {
+            let packet_counts = byte_counts(packet);
+            packet_entropy.push(entropy(&packet_counts));
+            for (total, count) in counts.iter_mut().zip(packet_counts) {
+                *total += count;
+            }
+        }
+        Self {
+            entropy: entropy(&counts),
+            counts,
+            packet_entropy,
+        }
+    }
+
+    fn text(&self) -> String {
+        let seen = self.counts.iter().filter(|&&count| count > 0).count();
+        let mut text = format!(
+            "Byte values: {seen} of 256 seen, entropy {:.3} bits per byte",
+            self.entropy
+        );
+        for (row, counts) in self.counts.chunks(16).enumerate() {
+            let counts = counts
+                .iter()
+                .map(|count| format!("{count:>6}"))
+                .collect::<String>();
+            text += &format!("\n  {:02x}:{counts}", row * 16);
+        }
+        text += "\nPacket entropy:";
+        for (packet, entropy) in self.packet_entropy.iter().enumerate() {
+            text += &format!("\n  packet {packet}: {entropy:.3} bits per byte");
+        }
+        text
+    }
+}
+
+impl Stats {
+    /// Stats for `packets` spread over `lines` lines, of which `idle_lines`
+    /// are idle
+    pub fn new(packets: &[Vec<u8>], lines: usize, idle_lines: usize) -> Self {
+        let lengths = packets.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut histogram: Vec<Bucket> = Vec::new();
+        for &length in &lengths {
+            let bucket = (usize::BITS - length.leading_zeros()) as usize;
+            while histogram.len() <= bucket {
+                let (min, max) = match histogram.len() {
+                    0 => (0, 0),
+                    n => (1 << (n - 1), (1 << n) - 1),
+                };
+                histogram.push(Bucket {
+                    min,
+                    max,
+                    packets: 0,
+                });
+            }
+            histogram[bucket].packets += 1;
+        }
+        let bytes = lengths.iter().sum::<usize>();
+        Self {
+            packets: lengths.len(),
+            bytes,
+            min_length: lengths.iter().copied().min().unwrap_or_default(),
+            mean_length: bytes as f64 / lengths.len().max(1) as f64,
+            max_length: lengths.iter().copied().max().unwrap_or_default(),
+            histogram,
+            lines,
+            idle_lines,
+            byte_values: None,
+        }
+    }
+
+    /// Fraction of lines that are idle
+    fn idle_ratio(&self) -> f64 {
+        self.idle_lines as f64 / self.lines.max(1) as f64
+    }
+
+    fn text(&self) -> String {
+        let mut text = format!(
+            "Packets: {}\nData bytes: {}\nPacket length: min {}, mean {:.1}, max {}\n\
+             Idle lines: {} of {} ({:.1}%)\nLength histogram:",
+            self.packets,
+            self.bytes,
+            self.min_length,
+            self.mean_length,
+            self.max_length,
+            self.idle_lines,
+            self.lines,
+            self.idle_ratio() * 100.0,
+        );
+        let widest = self.histogram.iter().map(|bucket| bucket.packets).max();
+        for bucket in &self.histogram {
+            // Bars scaled to at most 40 characters
+            let bar = bucket.packets * 40 / widest.unwrap_or(1).max(1);
+            let row = format!(
+                "\n  {:>10} - {:<10} {:>8} {}",
+                bucket.min,
+                bucket.max,
+                bucket.packets,
+                "#".repeat(bar)
+            );
+            text += row.trim_end();
+        }
+        if let Some(byte_values) = &self.byte_values {
+            text += "\n";
+            text += &byte_values.text();
+        }
+        text
+    }
+}
+
+/// Result of checking one packet or file against its expected checksum
+pub struct Check<'a> {
+    pub file: Option<&'a str>,
+    pub packet: Option<usize>,
+    pub expected: Option<u64>,
+    pub checksum: Option<u64>,
+    pub content: Option<&'a str>,
+}
+
+impl Check<'_> {
+    pub fn passed(&self) -> bool {
+        self.expected.is_some() && self.expected == self.checksum
+    }
+}
+
+pub struct Output {
+    format: OutputFormat,
+    out: Box<dyn Write>,
+    /// Last CSV header row written, so it's only repeated when the columns
+    /// change
+    header: Option<&'static str>,
+    /// TAP test points written so far, for the plan at the end
+    tap_tests: usize,
+    junit: Option<JUnit>,
+    /// Leave out a line per packet, see [`quiet`](Self::quiet)
+    quiet: bool,
+    /// Packets left out by `quiet`, for [`tally`](Self::tally)
+    unprinted: usize,
+    /// What single checksums are, for how wide to write them
+    algorithm: Algorithm,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, out: Box<dyn Write>) -> Self {
+        Self {
+            format,
+            out,
+            header: None,
+            tap_tests: 0,
+            junit: None,
+            quiet: false,
+            unprinted: 0,
+            algorithm: Algorithm::default(),
+        }
+    }
+
+    /// Write single checksums as `algorithm` ones, e.g. 64 bits wide for
+    /// Fletcher-64, rather than Adler-32 ones
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// [`with_algorithm`](Self::with_algorithm) for an `Output` already
+    /// made, e.g. once a file's header names the algorithm it was made with
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Print only failed checks and summaries if `quiet`, not every packet
+    /// and passed check. TAP keeps every test point, for the plan.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Also collect every check into a JUnit XML report, written to `out` by
+    /// [`finish`](Self::finish)
+    pub fn with_junit(mut self, out: Box<dyn Write>) -> Self {
+        self.junit = Some(JUnit {
+            out,
+            cases: Vec::new(),
+        });
+        self
+    }
+
+    /// Write out the TAP plan and any report collected over the run
+    pub fn finish(&mut self) -> Result<()> {
+        if self.format == OutputFormat::Tap {
+            writeln!(self.out, "1..{}", self.tap_tests).map_err(Error::Write)?;
+        }
+        match &mut self.junit {
+            Some(junit) => junit.write().map_err(Error::Write),
+            None => Ok(()),
+        }
+    }
+
+    /// A TAP diagnostic line
+    fn tap_comment(&mut self, text: &str) -> Result<()> {
+        writeln!(self.out, "# {text}").map_err(Error::Write)
+    }
+
+    fn csv(&mut self, header: &'static str, fields: &[&str]) -> Result<()> {
+        if self.header != Some(header) {
+            writeln!(self.out, "{header}").map_err(Error::Write)?;
+            self.header = Some(header);
+        }
+        let row = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(self.out, "{row}").map_err(Error::Write)
+    }
+
+    fn json(&mut self, record: &impl Serialize) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record).map_err(|e| Error::Write(e.into()))?;
+        writeln!(self.out).map_err(Error::Write)
+    }
+
+    /// A packet replayed from encoded stimulus
+    pub fn packet(
+        &mut self,
+        file: Option<&str>,
+        packet: usize,
+        checksum: u64,
+        content: &str,
+    ) -> Result<()> {
+        let label = file.map(|file| format!("{file}: ")).unwrap_or_default();
+        let algorithm = self.algorithm;
+        match self.format {
+            OutputFormat::Text => writeln!(
+                self.out,
+                "{label}Checksum: {} Content: {content:?}",
+                algorithm.literal(checksum)
+            )
+            .map_err(Error::Write),
+            OutputFormat::Tap => self.tap_comment(&format!(
+                "{label}Checksum: {} Content: {content:?}",
+                hex(algorithm, checksum)
+            )),
+            OutputFormat::Json => self.json(&PacketRecord {
+                file,
+                packet,
+                checksum: hex(algorithm, checksum),
+                length: content.len(),
+                content,
+            }),
+            OutputFormat::Csv => {
+                let (packet, length, checksum) = (
+                    packet.to_string(),
+                    content.len().to_string(),
+                    hex(algorithm, checksum),
+                );
+                match file {
+                    Some(file) => self.csv(
+                        "file,packet,length,checksum,content",
+                        &[file, &packet, &length, &checksum, content],
+                    ),
+                    None => self.csv(
+                        "packet,length,checksum,content",
+                        &[&packet, &length, &checksum, content],
+                    ),
+                }
+            }
+        }
+    }
+
+    /// One of every packet in a file, which [`packet`](Self::packet) prints
+    /// unless `quiet`
+    pub fn listed(
+        &mut self,
+        file: Option<&str>,
+        packet: usize,
+        checksum: u64,
+        content: &str,
+    ) -> Result<()> {
+        if self.quiet {
+            self.unprinted += 1;
+            return Ok(());
+        }
+        self.packet(file, packet, checksum, content)
+    }
+
+    /// A packet's checksum by each of several algorithms, printed unless
+    /// `quiet`. CSV has a row per algorithm.
+    pub fn listed_checksums(
+        &mut self,
+        file: Option<&str>,
+        packet: usize,
+        checksums: &[(Algorithm, u64)],
+        content: &str,
+    ) -> Result<()> {
+        if self.quiet {
+            self.unprinted += 1;
+            return Ok(());
+        }
+        let label = file.map(|file| format!("{file}: ")).unwrap_or_default();
+        match self.format {
+            OutputFormat::Text => writeln!(
+                self.out,
+                "{label}{} Content: {content:?}",
+                checksums_text(checksums)
+            )
+            .map_err(Error::Write),
+            OutputFormat::Tap => self.tap_comment(&format!(
+                "{label}{} Content: {content:?}",
+                checksums_text(checksums)
+            )),
+            OutputFormat::Json => self.json(&ChecksumsRecord {
+                file,
+                packet: Some(packet),
+                checksums: checksums_by_name(checksums),
+                length: Some(content.len()),
+                content: Some(content),
+            }),
+            OutputFormat::Csv => {
+                let (packet, length) = (packet.to_string(), content.len().to_string());
+                for &(algorithm, checksum) in checksums {
+                    let (algorithm, checksum) = (algorithm.name(), hex(algorithm, checksum));
+                    match file {
+                        Some(file) => self.csv(
+                            "file,packet,algorithm,length,checksum,content",
+                            &[file, &packet, algorithm, &length, &checksum, content],
+                        ),
+                        None => self.csv(
+                            "packet,algorithm,length,checksum,content",
+                            &[&packet, algorithm, &length, &checksum, content],
+                        ),
+                    }?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// A packet picked out by index, e.g. by grep
+    pub fn matched(&mut self, packet: usize, checksum: u64, content: &str) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => writeln!(
+                self.out,
+                "packet {packet}: Checksum: {} Content: {content:?}",
+                self.algorithm.literal(checksum)
+            )
+            .map_err(Error::Write),
+            _ => self.packet(None, packet, checksum, content),
+        }
+    }
+
+    /// Checksum of a whole file. `manifest` prints it the way md5sum does.
+    pub fn file(&mut self, file: &str, checksum: u64, manifest: bool, prefix: bool) -> Result<()> {
+        let algorithm = self.algorithm;
+        match self.format {
+            OutputFormat::Text if manifest => {
+                let digits = algorithm.bits() as usize / 4;
+                writeln!(self.out, "{checksum:0>digits$x}  {file}").map_err(Error::Write)
+            }
+            OutputFormat::Text => {
+                let label = if prefix {
+                    format!("{file}: ")
+                } else {
+                    String::new()
+                };
+                writeln!(self.out, "{label}Checksum: {}", algorithm.literal(checksum))
+                    .map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&FileRecord {
+                file,
+                checksum: hex(algorithm, checksum),
+            }),
+            OutputFormat::Csv => self.csv("file,checksum", &[file, &hex(algorithm, checksum)]),
+            OutputFormat::Tap => {
+                self.tap_comment(&format!("{file}: Checksum: {}", hex(algorithm, checksum)))
+            }
+        }
+    }
+
+    /// Checksums of a whole file by each of several algorithms. CSV has a
+    /// row per algorithm.
+    pub fn file_checksums(
+        &mut self,
+        file: &str,
+        checksums: &[(Algorithm, u64)],
+        prefix: bool,
+    ) -> Result<()> {
+        let label = match prefix {
+            true => format!("{file}: "),
+            false => String::new(),
+        };
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "{label}{}", checksums_text(checksums)).map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&ChecksumsRecord {
+                file: Some(file),
+                packet: None,
+                checksums: checksums_by_name(checksums),
+                length: None,
+                content: None,
+            }),
+            OutputFormat::Csv => checksums.iter().try_for_each(|&(algorithm, checksum)| {
+                self.csv(
+                    "file,algorithm,checksum",
+                    &[file, algorithm.name(), &hex(algorithm, checksum)],
+                )
+            }),
+            OutputFormat::Tap => {
+                self.tap_comment(&format!("{file}: {}", checksums_text(checksums)))
+            }
+        }
+    }
+
+    /// Outcome of comparing a packet or file against its expected checksum
+    pub fn check(&mut self, check: &Check) -> Result<()> {
+        let passed = check.passed();
+        let algorithm = self.algorithm;
+        let hex = |checksum| hex(algorithm, checksum);
+        if let Some(junit) = &mut self.junit {
+            let failure = (!passed).then(|| check_text(check, algorithm));
+            junit.cases.push((check_name(check), failure));
+        }
+        if self.quiet && passed && self.format != OutputFormat::Tap {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Text => {
+                let line = check_text(check, algorithm);
+                writeln!(self.out, "{line}").map_err(Error::Write)
+            }
+            OutputFormat::Tap => {
+                self.tap_tests += 1;
+                let status = if passed { "ok" } else { "not ok" };
+                let name = check_name(check);
+                match check.checksum {
+                    Some(checksum) => writeln!(
+                        self.out,
+                        "{status} {} - {name} checksum {}",
+                        self.tap_tests,
+                        hex(checksum)
+                    ),
+                    None => writeln!(self.out, "{status} {} - {name} missing", self.tap_tests),
+                }
+                .map_err(Error::Write)?;
+                if passed {
+                    return Ok(());
+                }
+                self.tap_comment(&check_text(check, algorithm))
+            }
+            OutputFormat::Json => self.json(&CheckRecord {
+                file: check.file,
+                packet: check.packet,
+                status: if passed { "pass" } else { "fail" },
+                expected: check.expected.map(hex),
+                checksum: check.checksum.map(hex),
+                content: check.content,
+            }),
+            OutputFormat::Csv => self.csv(
+                "file,packet,status,expected,checksum,content",
+                &[
+                    check.file.unwrap_or_default(),
+                    &check.packet.map(|p| p.to_string()).unwrap_or_default(),
+                    if passed { "pass" } else { "fail" },
+                    &check.expected.map(hex).unwrap_or_default(),
+                    &check.checksum.map(hex).unwrap_or_default(),
+                    check.content.unwrap_or_default(),
+                ],
+            ),
+        }
+    }
+
+    /// Final tally of a verification run
+    pub fn summary(&mut self, passed: usize, total: usize) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "{passed} of {total} packets passed").map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&SummaryRecord { passed, total }),
+            // Would break the table, the exit code says it all
+            OutputFormat::Csv => Ok(()),
+            OutputFormat::Tap => self.tap_comment(&format!("{passed} of {total} packets passed")),
+        }
+    }
+
+    /// Number of packets read, if `quiet` left them out
+    pub fn tally(&mut self) -> Result<()> {
+        let packets = self.unprinted;
+        if !self.quiet {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "Read {packets} packets").map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&TallyRecord { packets }),
+            OutputFormat::Csv => self.csv("packets", &[&packets.to_string()]),
+            OutputFormat::Tap => self.tap_comment(&format!("Read {packets} packets")),
+        }
+    }
+
+    /// What a stimulus file exercises
+    pub fn stats(&mut self, stats: &Stats) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{}", stats.text()).map_err(Error::Write),
+            OutputFormat::Json => self.json(stats),
+            OutputFormat::Csv => {
+                self.csv(
+                    "packets,bytes,min_length,mean_length,max_length,lines,idle_lines",
+                    &[
+                        &stats.packets.to_string(),
+                        &stats.bytes.to_string(),
+                        &stats.min_length.to_string(),
+                        &stats.mean_length.to_string(),
+                        &stats.max_length.to_string(),
+                        &stats.lines.to_string(),
+                        &stats.idle_lines.to_string(),
+                    ],
+                )?;
+                for bucket in &stats.histogram {
+                    self.csv(
+                        "min_length,max_length,packets",
+                        &[
+                            &bucket.min.to_string(),
+                            &bucket.max.to_string(),
+                            &bucket.packets.to_string(),
+                        ],
+                    )?;
+                }
+                let Some(byte_values) = &stats.byte_values else {
+                    return Ok(());
+                };
+                for (byte, count) in byte_values.counts.iter().enumerate() {
+                    self.csv("byte,count", &[&byte.to_string(), &count.to_string()])?;
+                }
+                for (packet, entropy) in byte_values.packet_entropy.iter().enumerate() {
+                    self.csv(
+                        "packet,entropy",
+                        &[&packet.to_string(), &entropy.to_string()],
+                    )?;
+                }
+                Ok(())
+            }
+            OutputFormat::Tap => stats
+                .text()
+                .lines()
+                .try_for_each(|line| self.tap_comment(line)),
+        }
+    }
+
+    /// Encoded lines carrying a packet
+    pub fn beats(&mut self, beats: &[String]) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => beats
+                .iter()
+                .try_for_each(|beat| writeln!(self.out, "{beat}"))
+                .map_err(Error::Write),
+            OutputFormat::Json => self.json(&BeatsRecord { beats }),
+            OutputFormat::Csv => beats.iter().try_for_each(|beat| self.csv("beat", &[beat])),
+            OutputFormat::Tap => beats.iter().try_for_each(|beat| self.tap_comment(beat)),
+        }
+    }
+
+    /// A packet compared between `files`. Only packets that differ are
+    /// shown, except as TAP test points.
+    pub fn diff(&mut self, files: [&str; 2], diff: &PacketDiff) -> Result<()> {
+        let differs = diff.differs();
+        if self.format == OutputFormat::Tap {
+            self.tap_tests += 1;
+            let status = if differs { "not ok" } else { "ok" };
+            writeln!(
+                self.out,
+                "{status} {} - packet {}",
+                self.tap_tests, diff.packet
+            )
+            .map_err(Error::Write)?;
+        }
+        if !differs {
+            return Ok(());
+        }
+        let (a_length, a_checksum) = diff.a.unzip();
+        let (b_length, b_checksum) = diff.b.unzip();
+        let algorithm = self.algorithm;
+        let hex = |checksum| hex(algorithm, checksum);
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "{}", diff.text(files, algorithm)).map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&DiffRecord {
+                packet: diff.packet,
+                a_length,
+                b_length,
+                a_checksum: a_checksum.map(hex),
+                b_checksum: b_checksum.map(hex),
+                first_difference: diff.first_difference,
+                beats: &diff.beats,
+            }),
+            OutputFormat::Csv => {
+                let number = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or_default();
+                self.csv(
+                    "packet,a_length,b_length,a_checksum,b_checksum,first_difference",
+                    &[
+                        &diff.packet.to_string(),
+                        &number(a_length),
+                        &number(b_length),
+                        &a_checksum.map(hex).unwrap_or_default(),
+                        &b_checksum.map(hex).unwrap_or_default(),
+                        &number(diff.first_difference),
+                    ],
+                )
+            }
+            OutputFormat::Tap => diff
+                .text(files, algorithm)
+                .lines()
+                .try_for_each(|line| self.tap_comment(line)),
+        }
+    }
+
+    /// Number of packets left out as duplicates
+    pub fn removed(&mut self, removed: usize) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "Removed {removed} duplicate packets").map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&RemovedRecord { removed }),
+            OutputFormat::Csv => self.csv("removed", &[&removed.to_string()]),
+            OutputFormat::Tap => self.tap_comment(&format!("Removed {removed} duplicate packets")),
+        }
+    }
+
+    /// Number of encoded lines written
+    pub fn wrote(&mut self, lines: usize) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "Wrote {lines} lines").map_err(Error::Write),
+            OutputFormat::Json => self.json(&WroteRecord { lines }),
+            OutputFormat::Csv => self.csv("lines", &[&lines.to_string()]),
+            OutputFormat::Tap => self.tap_comment(&format!("Wrote {lines} lines")),
+        }
+    }
+
+    /// What would have been written without --dry-run
+    pub fn dry_run(&mut self, packets: usize, lines: usize, bytes: u64) -> Result<()> {
+        let text = format!(
+            "Would write {} in {}, {}",
+            plural(packets as u64, "packet"),
+            plural(lines as u64, "line"),
+            plural(bytes, "byte")
+        );
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{text}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&DryRunRecord {
+                packets,
+                lines,
+                bytes,
+            }),
+            OutputFormat::Csv => self.csv(
+                "packets,lines,bytes",
+                &[&packets.to_string(), &lines.to_string(), &bytes.to_string()],
+            ),
+            OutputFormat::Tap => self.tap_comment(&text),
+        }
+    }
+
+    /// A problem lint found on `line` of `file`. A TAP test point that
+    /// fails, as there are none for lines without problems.
+    pub fn finding(&mut self, file: &str, line: usize, problem: &str) -> Result<()> {
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(self.out, "{file}:{line}: {problem}").map_err(Error::Write)
+            }
+            OutputFormat::Json => self.json(&FindingRecord {
+                file,
+                line,
+                problem,
+            }),
+            OutputFormat::Csv => self.csv("file,line,problem", &[file, &line.to_string(), problem]),
+            OutputFormat::Tap => {
+                self.tap_tests += 1;
+                writeln!(
+                    self.out,
+                    "not ok {} - {file}:{line}: {problem}",
+                    self.tap_tests
+                )
+                .map_err(Error::Write)
+            }
+        }
+    }
+
+    /// How much of a file lint read
+    pub fn linted(&mut self, packets: usize, lines: usize) -> Result<()> {
+        let text = format!(
+            "{} in {}",
+            plural(packets as u64, "packet"),
+            plural(lines as u64, "line")
+        );
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{text}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&LintedRecord { packets, lines }),
+            // Would break the table, the exit code says it all
+            OutputFormat::Csv => Ok(()),
+            OutputFormat::Tap => self.tap_comment(&text),
+        }
+    }
+
+    /// A change repair made to `line` of `file`, printed unless `quiet`
+    pub fn change(&mut self, file: &str, line: usize, fix: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{file}:{line}: {fix}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&ChangeRecord { file, line, fix }),
+            OutputFormat::Csv => self.csv("file,line,fix", &[file, &line.to_string(), fix]),
+            OutputFormat::Tap => self.tap_comment(&format!("{file}:{line}: {fix}")),
+        }
+    }
+
+    /// Number of changes repair made
+    pub fn repaired(&mut self, changes: usize) -> Result<()> {
+        let text = plural(changes as u64, "change");
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{text}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&RepairedRecord { changes }),
+            // Would break the table, the exit code says it all
+            OutputFormat::Csv => Ok(()),
+            OutputFormat::Tap => self.tap_comment(&text),
+        }
+    }
+
+    /// Checksum of shards joined end to end, `length` bytes in all
+    pub fn combined(&mut self, checksum: u32, length: u64) -> Result<()> {
+        let text = format!("Checksum: 32'h{checksum:0>8x} Length: {length}");
+        let checksum = hex(Algorithm::Adler32, checksum.into());
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{text}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&CombinedRecord {
+                checksum: checksum.clone(),
+                length,
+            }),
+            OutputFormat::Csv => self.csv("checksum,length", &[&checksum, &length.to_string()]),
+            OutputFormat::Tap => self.tap_comment(&text),
+        }
+    }
+
+    /// How fast `implementation` hashed `bytes` bytes, ending on `checksum`
+    pub fn throughput(
+        &mut self,
+        implementation: &str,
+        bytes: u64,
+        seconds: f64,
+        checksum: u32,
+    ) -> Result<()> {
+        let bytes_per_second = match seconds {
+            0.0 => 0.0,
+            _ => bytes as f64 / seconds,
+        };
+        let text = format!(
+            "{implementation}: {bytes} bytes in {seconds:.3}s, {:.2} GB/s, Checksum: 32'h{checksum:0>8x}",
+            bytes_per_second / 1e9
+        );
+        let checksum = hex(Algorithm::Adler32, checksum.into());
+        match self.format {
+            OutputFormat::Text => writeln!(self.out, "{text}").map_err(Error::Write),
+            OutputFormat::Json => self.json(&ThroughputRecord {
+                implementation,
+                bytes,
+                seconds,
+                bytes_per_second,
+                checksum: checksum.clone(),
+            }),
+            OutputFormat::Csv => self.csv(
+                "implementation,bytes,seconds,bytes_per_second,checksum",
+                &[
+                    implementation,
+                    &bytes.to_string(),
+                    &seconds.to_string(),
+                    &bytes_per_second.to_string(),
+                    &checksum,
+                ],
+            ),
+            OutputFormat::Tap => self.tap_comment(&text),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! A progress bar on stderr while a large file is read, when both stdout
+//! and stderr are terminals.
+
+use std::{
+    io::{self, BufRead, IsTerminal, LineWriter, Read, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+
+/// Files smaller than this are read without a bar
+const LARGE: u64 = 4 << 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The bar being shown, if any. Only one file has a bar at a time.
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Packets read since the bar was shown
+static PACKETS: AtomicU64 = AtomicU64::new(0);
+
+/// Show bars from now on, unless `quiet` or the output isn't going to a
+/// terminal.
+pub fn init(quiet: bool) {
+    let shown = !quiet && io::stdout().is_terminal() && io::stderr().is_terminal();
+    ENABLED.store(shown, Ordering::Relaxed);
+}
+
+/// `source`, the `len` bytes of `filename`, with a bar showing how far
+/// through it reading has got if it's large and no other file has one.
+pub fn track(filename: &str, len: u64, source: Box<dyn BufRead>) -> Box<dyn BufRead> {
+    let mut shown = BAR.lock().expect("no thread panics while drawing");
+    if !ENABLED.load(Ordering::Relaxed) || len < LARGE || shown.is_some() {
+        return source;
+    }
+    let style = ProgressStyle::with_template(
+        "{prefix} [{bar:30}] {bytes}/{total_bytes}, {packets} packets, ETA {eta}",
+    )
+    .expect("the template is valid")
+    .with_key(
+        "packets",
+        |_: &ProgressState, out: &mut dyn std::fmt::Write| {
+            let _ = write!(out, "{}", PACKETS.load(Ordering::Relaxed));
+        },
+    )
+    .progress_chars("=> ");
+    let bar = ProgressBar::new(len)
+        .with_style(style)
+        .with_prefix(filename.to_owned());
+    PACKETS.store(0, Ordering::Relaxed);
+    *shown = Some(bar.clone());
+    Box::new(Tracked { bar, source })
+}
+
+/// Count a packet towards the bar being shown.
+pub fn packet() {
+    PACKETS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Run `f` with the bar cleared from the screen, for writing to the terminal.
+fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    let shown = BAR.lock().expect("no thread panics while drawing");
+    match &*shown {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
+
+/// Stdout, written a line at a time around the bar
+pub fn stdout() -> impl Write {
+    LineWriter::new(Suspending(io::stdout()))
+}
+
+/// Stderr, written a line at a time around the bar
+pub fn stderr() -> impl Write {
+    LineWriter::new(Suspending(io::stderr()))
+}
+
+/// A terminal that's written with the bar cleared from it
+pub struct Suspending<W>(pub W);
+
+impl<W: Write> Write for Suspending<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        suspend(|| self.0.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        suspend(|| self.0.flush())
+    }
+}
+
+/// A file being read under a bar, which goes once it's dropped
+struct Tracked {
+    bar: ProgressBar,
+    source: Box<dyn BufRead>,
+}
+
+impl Read for Tracked {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.source.read(buf)?;
+        self.bar.inc(read as u64);
+        Ok(read)
+    }
+}
+
+impl BufRead for Tracked {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.source.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.bar.inc(amount as u64);
+        self.source.consume(amount);
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        *BAR.lock().expect("no thread panics while drawing") = None;
+    }
+}
@@ -0,0 +1,71 @@
+//! Adler-32 of a window sliding over data a byte at a time, as rsync uses
+//! it to find blocks that match.
+
+use crate::MOD_ADLER;
+
+/// Adler-32 of the bytes in a window, which bytes can be added to at the
+/// newest end and taken from at the oldest.
+///
+/// ```
+/// use adler32::{adler32, RollingAdler32};
+///
+/// let data = b"Wikipedia";
+/// let mut rolling = RollingAdler32::new();
+/// data[..4].iter().for_each(|&byte| rolling.push(byte));
+/// for start in 1..=data.len() - 4 {
+///     rolling.roll(data[start - 1], data[start + 3], 4);
+///     assert_eq!(rolling.finalize(), adler32(&data[start..start + 4]));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollingAdler32 {
+    a: u32,
+    b: u32,
+}
+
+impl RollingAdler32 {
+    /// Checksum of an empty window
+    pub fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+
+    /// Add `byte` at the newest end of the window.
+    pub fn push(&mut self, byte: u8) {
+        self.a = (self.a + byte as u32) % MOD_ADLER;
+        self.b = (self.b + self.a) % MOD_ADLER;
+    }
+
+    /// Take `oldest` from the oldest end of a window `window_len` bytes
+    /// long, counting `oldest`.
+    pub fn pop(&mut self, oldest: u8, window_len: u64) {
+        let modulus = MOD_ADLER as u64;
+        // `oldest` was counted in `b` once for each byte from it on, and
+        // the 1 `a` starts at once for each byte
+        let counted = (1 + window_len % modulus * oldest as u64) % modulus;
+        self.a = (self.a + MOD_ADLER - oldest as u32) % MOD_ADLER;
+        self.b = ((self.b as u64 + modulus - counted) % modulus) as u32;
+    }
+
+    /// Slide a window `window_len` bytes long along by one byte, from
+    /// starting at `oldest` to ending at `newest`.
+    pub fn roll(&mut self, oldest: u8, newest: u8, window_len: u64) {
+        self.pop(oldest, window_len);
+        self.push(newest);
+    }
+
+    /// Checksum of the bytes in the window
+    pub fn finalize(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+
+    /// Empty the window.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for RollingAdler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,52 @@
+//! Seeded randomness for stimulus generation, so a run can be reproduced from
+//! its seed.
+
+use std::ops::RangeInclusive;
+
+/// SplitMix64. Small, and a seed gives the same sequence on every platform
+/// and release, which a general purpose RNG crate doesn't promise.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// `true` with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        // 53 random bits, uniform in [0, 1)
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// A value in `range`. The bias towards low values is negligible for
+    /// ranges much smaller than 2^64.
+    pub fn range(&mut self, range: RangeInclusive<u64>) -> u64 {
+        let (low, high) = range.into_inner();
+        match (high - low).checked_add(1) {
+            Some(span) => low + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    /// Put `items` in a random order
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        // Fisher-Yates
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0..=i as u64) as usize);
+        }
+    }
+
+    /// `len` random bytes
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
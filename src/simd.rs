@@ -0,0 +1,191 @@
+//! Adler-32 over long inputs with vector instructions: AVX2 or SSE2 on
+//! x86_64 and NEON on aarch64, whichever the CPU running it has.
+//!
+//! Without the `simd` feature there's never a vector unit to use.
+//!
+//! Each vector of bytes adds its sum to `a` and its bytes weighted by their
+//! distance from the end of the vector to `b`, with `a` as it was before the
+//! vector counted once for every byte. Sums are only reduced modulo
+//! [`MOD_ADLER`] every [`NMAX`] bytes, rounded down to whole vectors.
+
+#[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use crate::checksum::{MOD_ADLER, NMAX};
+
+/// Inputs shorter than this are quicker done a byte at a time
+pub(crate) const MIN_LEN: usize = 64;
+
+/// `a` and `b` after adding `data`, or `None` if there's no vector unit to
+/// do it with.
+pub(crate) fn update(a: u32, b: u32, data: &[u8]) -> Option<(u32, u32)> {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU has AVX2
+            return Some(unsafe { x86::update_avx2(a, b, data) });
+        }
+        // SAFETY: every x86_64 CPU has SSE2
+        Some(unsafe { x86::update_sse2(a, b, data) })
+    }
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // SAFETY: the CPU has NEON
+            return Some(unsafe { neon::update(a, b, data) });
+        }
+        None
+    }
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    {
+        let _ = (a, b, data);
+        None
+    }
+}
+
+/// Name of the vector unit [`update`] uses, if there is one.
+pub(crate) fn unit() -> Option<&'static str> {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        match is_x86_feature_detected!("avx2") {
+            true => Some("avx2"),
+            false => Some("sse2"),
+        }
+    }
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    {
+        std::arch::is_aarch64_feature_detected!("neon").then_some("neon")
+    }
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    {
+        None
+    }
+}
+
+/// Add `data` to `a` and `b` a block at a time, calling `vectors` with the
+/// whole `WIDTH` byte vectors of each block and its starting `a`. `vectors`
+/// returns what they add to `a` and to `b`, leaving out the starting `a`
+/// counted for every byte. Bytes left over after the last whole vector are
+/// added one at a time.
+#[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[inline(always)]
+fn blocks<const WIDTH: usize>(
+    mut a: u32,
+    mut b: u32,
+    data: &[u8],
+    mut vectors: impl FnMut(&[u8]) -> (u32, u32),
+) -> (u32, u32) {
+    for block in data.chunks(NMAX / WIDTH * WIDTH) {
+        let whole = block.len() / WIDTH * WIDTH;
+        let (sum, weighted) = vectors(&block[..whole]);
+        b += a * whole as u32 + weighted;
+        a += sum;
+        for &byte in &block[whole..] {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (a, b)
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::blocks;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn update_avx2(a: u32, b: u32, data: &[u8]) -> (u32, u32) {
+        blocks::<32>(a, b, data, |vectors| {
+            let zero = _mm256_setzero_si256();
+            let ones = _mm256_set1_epi16(1);
+            let weights = _mm256_setr_epi8(
+                32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12,
+                11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1,
+            );
+            let mut sum = zero;
+            let mut weighted = zero;
+            // Sum of the vectors before each vector, counted 32 times over
+            let mut prefix = zero;
+            for vector in vectors.chunks_exact(32) {
+                let bytes = _mm256_loadu_si256(vector.as_ptr().cast());
+                prefix = _mm256_add_epi32(prefix, sum);
+                sum = _mm256_add_epi32(sum, _mm256_sad_epu8(bytes, zero));
+                let pairs = _mm256_maddubs_epi16(bytes, weights);
+                weighted = _mm256_add_epi32(weighted, _mm256_madd_epi16(pairs, ones));
+            }
+            let weighted = _mm256_add_epi32(weighted, _mm256_slli_epi32(prefix, 5));
+            (horizontal_sum_256(sum), horizontal_sum_256(weighted))
+        })
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn update_sse2(a: u32, b: u32, data: &[u8]) -> (u32, u32) {
+        blocks::<16>(a, b, data, |vectors| {
+            let zero = _mm_setzero_si128();
+            let high_weights = _mm_setr_epi16(16, 15, 14, 13, 12, 11, 10, 9);
+            let low_weights = _mm_setr_epi16(8, 7, 6, 5, 4, 3, 2, 1);
+            let mut sum = zero;
+            let mut weighted = zero;
+            // Sum of the vectors before each vector, counted 16 times over
+            let mut prefix = zero;
+            for vector in vectors.chunks_exact(16) {
+                let bytes = _mm_loadu_si128(vector.as_ptr().cast());
+                prefix = _mm_add_epi32(prefix, sum);
+                sum = _mm_add_epi32(sum, _mm_sad_epu8(bytes, zero));
+                let first = _mm_unpacklo_epi8(bytes, zero);
+                let last = _mm_unpackhi_epi8(bytes, zero);
+                weighted = _mm_add_epi32(weighted, _mm_madd_epi16(first, high_weights));
+                weighted = _mm_add_epi32(weighted, _mm_madd_epi16(last, low_weights));
+            }
+            let weighted = _mm_add_epi32(weighted, _mm_slli_epi32(prefix, 4));
+            (horizontal_sum_128(sum), horizontal_sum_128(weighted))
+        })
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn horizontal_sum_256(lanes: __m256i) -> u32 {
+        let halves = _mm_add_epi32(
+            _mm256_castsi256_si128(lanes),
+            _mm256_extracti128_si256(lanes, 1),
+        );
+        horizontal_sum_128(halves)
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn horizontal_sum_128(lanes: __m128i) -> u32 {
+        let pairs = _mm_add_epi32(lanes, _mm_shuffle_epi32(lanes, 0b01_00_11_10));
+        let total = _mm_add_epi32(pairs, _mm_shuffle_epi32(pairs, 0b10_11_00_01));
+        _mm_cvtsi128_si32(total) as u32
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::blocks;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn update(a: u32, b: u32, data: &[u8]) -> (u32, u32) {
+        const WEIGHTS: [u8; 16] = [16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1];
+        blocks::<16>(a, b, data, |vectors| {
+            let weights = vld1q_u8(WEIGHTS.as_ptr());
+            let mut sum = vdupq_n_u32(0);
+            let mut weighted = vdupq_n_u32(0);
+            // Sum of the vectors before each vector, counted 16 times over
+            let mut prefix = vdupq_n_u32(0);
+            for vector in vectors.chunks_exact(16) {
+                let bytes = vld1q_u8(vector.as_ptr());
+                prefix = vaddq_u32(prefix, sum);
+                sum = vpadalq_u16(sum, vpaddlq_u8(bytes));
+                let first = vmull_u8(vget_low_u8(bytes), vget_low_u8(weights));
+                let last = vmull_high_u8(bytes, weights);
+                weighted = vpadalq_u16(weighted, first);
+                weighted = vpadalq_u16(weighted, last);
+            }
+            let weighted = vaddq_u32(weighted, vshlq_n_u32(prefix, 4));
+            (vaddvq_u32(sum), vaddvq_u32(weighted))
+        })
+    }
+}
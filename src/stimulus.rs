@@ -0,0 +1,240 @@
+use std::io::{self, Write};
+
+use crate::{DataLine, Field, LineFormat, Segment};
+
+/// A [`DataLine`] packed into a single memory word.
+///
+/// Fields are concatenated in the order they appear in the [`LineFormat`],
+/// the first one in the most significant bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    width: u32,
+    /// Big endian, zero padded at the top to a whole number of bytes
+    bytes: Vec<u8>,
+}
+
+impl Word {
+    /// Bits in the word
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The word as big endian bytes, padded at the top to whole bytes
+    pub fn to_be_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The word as little endian bytes, padded at the top to whole bytes
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        self.bytes.iter().rev().copied().collect()
+    }
+
+    fn bit(&self, index: u32) -> bool {
+        let byte = self.bytes[self.bytes.len() - 1 - (index / 8) as usize];
+        byte >> (index % 8) & 1 == 1
+    }
+
+    /// `width` binary digits
+    pub fn to_bin(&self) -> String {
+        (0..self.width)
+            .rev()
+            .map(|bit| if self.bit(bit) { '1' } else { '0' })
+            .collect()
+    }
+
+    /// Enough hex digits for `width` bits
+    pub fn to_hex(&self) -> String {
+        let digits = self.width.div_ceil(4) as usize;
+        let hex = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        hex[hex.len() - digits..].to_owned()
+    }
+}
+
+impl LineFormat {
+    /// Bits in a packed line: the sum of the field widths
+    pub fn packed_width(&self) -> u32 {
+        self.segments()
+            .iter()
+            .map(|segment| match segment {
+                Segment::Field { width, .. } => *width,
+                Segment::Literal(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Most and least significant bit of `field` in a packed word
+    pub fn field_bits(&self, field: Field) -> (u32, u32) {
+        let mut offset = self.packed_width();
+        for segment in self.segments() {
+            if let Segment::Field {
+                field: this, width, ..
+            } = *segment
+            {
+                offset -= width;
+                if this == field {
+                    return (offset + width - 1, offset);
+                }
+            }
+        }
+        unreachable!("every field is in a LineFormat")
+    }
+
+    /// Pack `line` into a single word.
+    ///
+    /// ```
+    /// use adler32::{DataLine, LineFormat};
+    ///
+    /// let word = LineFormat::default().pack(&DataLine::from(b'A'));
+    /// assert_eq!(word.width(), 42);
+    /// assert_eq!(word.to_hex(), "00000000141");
+    /// ```
+    pub fn pack(&self, line: &DataLine) -> Word {
+        let width = self.packed_width();
+        let mut bytes = vec![0u8; width.div_ceil(8) as usize];
+        let mut offset = width;
+        for segment in self.segments() {
+            let Segment::Field {
+                field,
+                width: field_width,
+                ..
+            } = *segment
+            else {
+                continue;
+            };
+            let value = match field {
+                Field::LengthValid => line.length_valid as u64,
+                Field::Length => line.length,
+                Field::DataValid => line.data_valid as u64,
+                Field::Data => line.data,
+            };
+            offset -= field_width;
+            for bit in 0..field_width {
+                if value >> bit & 1 == 1 {
+                    let index = offset + bit;
+                    let len = bytes.len();
+                    bytes[len - 1 - (index / 8) as usize] |= 1 << (index % 8);
+                }
+            }
+        }
+        Word { width, bytes }
+    }
+}
+
+/// Encoded stimulus for a whole file, remembering where each packet starts,
+/// that can be written out as lines or as a memory image.
+#[derive(Debug, Clone)]
+pub struct Stimulus {
+    format: LineFormat,
+    lines: Vec<DataLine>,
+    packet_starts: Vec<usize>,
+}
+
+impl Stimulus {
+    pub fn new(format: LineFormat) -> Self {
+        Self {
+            format,
+            lines: Vec::new(),
+            packet_starts: Vec::new(),
+        }
+    }
+
+    /// Append the lines of one packet.
+    pub fn push_packet(&mut self, lines: impl IntoIterator<Item = DataLine>) {
+        self.packet_starts.push(self.lines.len());
+        self.lines.extend(lines);
+    }
+
+    pub fn format(&self) -> &LineFormat {
+        &self.format
+    }
+
+    pub fn lines(&self) -> &[DataLine] {
+        &self.lines
+    }
+
+    /// Index of the first line of each packet
+    pub fn packet_starts(&self) -> &[usize] {
+        &self.packet_starts
+    }
+
+    /// Index of the last beat of each packet, the one that completes it
+    pub fn packet_ends(&self) -> Vec<usize> {
+        let ends = self.packet_starts.iter().skip(1).copied();
+        let ends = ends.chain([self.lines.len()]);
+        self.packet_starts
+            .iter()
+            .zip(ends)
+            .map(|(&start, end)| {
+                (start..end)
+                    .rev()
+                    .find(|&index| self.lines[index].length_valid || self.lines[index].data_valid)
+                    .unwrap_or(start)
+            })
+            .collect()
+    }
+
+    /// Every line packed into a memory word
+    pub fn words(&self) -> impl Iterator<Item = Word> + '_ {
+        self.lines.iter().map(|line| self.format.pack(line))
+    }
+
+    /// Bits in each packed word
+    pub fn word_width(&self) -> u32 {
+        self.format.packed_width()
+    }
+
+    /// Put idle lines (nothing valid) in front of each line for as long as
+    /// `idle` returns `true` for it. Returns one flag per resulting line,
+    /// set for the idle lines that were added.
+    ///
+    /// ```
+    /// use adler32::{DataStream, LineFormat, Rng, Stimulus};
+    ///
+    /// let format = LineFormat::default();
+    /// let mut stimulus = Stimulus::new(format.clone());
+    /// stimulus.push_packet(format.encode_packet(b"Wikipedia").unwrap());
+    ///
+    /// let mut rng = Rng::new(1);
+    /// let stalls = stimulus.insert_idle(|_| rng.chance(0.5));
+    /// assert_eq!(stalls.len(), stimulus.lines().len());
+    ///
+    /// let lines = stimulus.lines().iter().copied();
+    /// assert_eq!(DataStream::new(lines).next().unwrap().0, 0x11e6_0398);
+    /// ```
+    pub fn insert_idle(&mut self, mut idle: impl FnMut(&DataLine) -> bool) -> Vec<bool> {
+        let mut lines = Vec::with_capacity(self.lines.len());
+        let mut added = Vec::with_capacity(self.lines.len());
+        let mut starts = self.packet_starts.iter_mut().peekable();
+        for (index, line) in self.lines.iter().enumerate() {
+            while idle(line) {
+                lines.push(DataLine::default());
+                added.push(true);
+            }
+            if let Some(start) = starts.next_if(|start| **start == index) {
+                *start = lines.len();
+            }
+            lines.push(*line);
+            added.push(false);
+        }
+        self.lines = lines;
+        added
+    }
+
+    /// Write one line per beat, for the verilog file reader.
+    pub fn write_lines(&self, mut out: impl Write) -> io::Result<()> {
+        for line in &self.lines {
+            writeln!(out, "{}", self.format.display(line))?;
+        }
+        Ok(())
+    }
+
+    /// Write one line per beat like [`write_lines`](Self::write_lines), with
+    /// a comment saying what each carries. See [`LineFormat::write_annotated`].
+    pub fn write_annotated(&self, prefix: &str, out: impl Write) -> io::Result<()> {
+        self.format.write_annotated(&self.lines, prefix, out)
+    }
+}
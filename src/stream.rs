@@ -0,0 +1,123 @@
+use crate::{Algorithm, Checksum, DataLine};
+
+/// Replays stimulus the way the hardware sees it, yielding
+/// `(checksum, content)` for every completed packet.
+pub struct DataStream<I>
+where
+    I: Iterator<Item = DataLine>,
+{
+    data: I,
+    bytes_per_beat: u64,
+    length: u64,
+    content: Vec<u8>,
+    hasher: Checksum,
+    empty_packets: bool,
+}
+
+impl<I> DataStream<I>
+where
+    I: Iterator<Item = DataLine>,
+{
+    /// Stream of one byte wide data beats
+    pub fn new(data: I) -> Self {
+        Self::with_data_width(data, 8)
+    }
+
+    /// Stream of `data_width` bit wide data beats, first byte in the least
+    /// significant bits.
+    pub fn with_data_width(data: I, data_width: u32) -> Self {
+        Self {
+            data,
+            bytes_per_beat: data_width as u64 / 8,
+            content: Vec::new(),
+            length: 0,
+            hasher: Checksum::default(),
+            empty_packets: false,
+        }
+    }
+
+    /// Yield a packet for every length line announcing 0 bytes, with
+    /// checksum `0x00000001` and no content, instead of skipping it.
+    ///
+    /// ```
+    /// use adler32::{encode_packet, DataStream};
+    ///
+    /// let lines = encode_packet(b"").chain(encode_packet(b"a"));
+    /// let packets: Vec<_> = DataStream::new(lines).with_empty_packets().collect();
+    /// assert_eq!(packets, [(1, Vec::new()), (0x0062_0062, b"a".to_vec())]);
+    /// ```
+    pub fn with_empty_packets(mut self) -> Self {
+        self.empty_packets = true;
+        self
+    }
+
+    /// Checksum packets with `algorithm` instead of Adler-32.
+    pub fn with_algorithm(self, algorithm: Algorithm) -> Self {
+        self.with_hasher(Checksum::new(algorithm))
+    }
+
+    /// Checksum packets with `hasher`, starting each from where it starts,
+    /// e.g. a resumed [`Adler32`](crate::Adler32)'s checksum.
+    pub fn with_hasher(mut self, hasher: Checksum) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    fn reset(&mut self) {
+        self.hasher.reset();
+        self.content.clear();
+        self.length = 0;
+    }
+
+    /// Checksum of the packet received so far.
+    pub fn checksum(&self) -> u64 {
+        self.hasher.finalize()
+    }
+
+    /// Checksum and content of the packet the input ended in the middle
+    /// of, once the stream has run out.
+    ///
+    /// ```
+    /// use adler32::{adler32, encode_packet, DataStream};
+    ///
+    /// let mut stream = DataStream::new(encode_packet(b"Wikipedia").take(5));
+    /// assert_eq!(stream.next(), None);
+    /// assert_eq!(stream.truncated(), Some((adler32(b"Wiki").into(), &b"Wiki"[..])));
+    /// ```
+    pub fn truncated(&self) -> Option<(u64, &[u8])> {
+        (self.length > 0).then(|| (self.checksum(), self.content.as_slice()))
+    }
+}
+
+impl<I> Iterator for DataStream<I>
+where
+    I: Iterator<Item = DataLine>,
+{
+    type Item = (u64, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for next in self.data.by_ref() {
+            if next.length_valid {
+                self.length = next.length;
+                if next.length == 0 && self.empty_packets {
+                    self.reset();
+                    return Some((self.checksum(), Vec::new()));
+                }
+            }
+
+            if next.data_valid && self.length > 0 {
+                let count = self.length.min(self.bytes_per_beat);
+                let bytes = &next.data.to_le_bytes()[..count as usize];
+                self.content.extend_from_slice(bytes);
+                self.hasher.update(bytes);
+                self.length -= count;
+                if self.length == 0 {
+                    let retval = (self.checksum(), std::mem::take(&mut self.content));
+                    self.reset();
+                    return Some(retval);
+                }
+            }
+        }
+        None
+    }
+}
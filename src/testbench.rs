@@ -0,0 +1,678 @@
+//! Self-checking testbenches that replay stimulus into the DUT and compare
+//! every checksum it produces against the golden model. The SystemVerilog
+//! and VHDL ones read packed words (as written by `encode --target
+//! readmemh`), the cocotb one reads encoded lines.
+
+use std::io::{self, Write};
+
+use crate::{Field, LineFormat, Radix, Segment};
+
+/// Names of the DUT's ports. Reset is active high.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ports {
+    pub clock: String,
+    pub reset: String,
+    pub length_valid: String,
+    pub length: String,
+    pub data_valid: String,
+    pub data: String,
+    pub checksum_valid: String,
+    pub checksum: String,
+}
+
+impl Ports {
+    /// Roles that can be renamed with [`Ports::set`]
+    pub const ROLES: [&'static str; 8] = [
+        "clock",
+        "reset",
+        "length_valid",
+        "length",
+        "data_valid",
+        "data",
+        "checksum_valid",
+        "checksum",
+    ];
+
+    /// Rename the port playing `role`.
+    pub fn set(&mut self, role: &str, name: &str) -> Result<(), String> {
+        let port = match role {
+            "clock" => &mut self.clock,
+            "reset" => &mut self.reset,
+            "length_valid" => &mut self.length_valid,
+            "length" => &mut self.length,
+            "data_valid" => &mut self.data_valid,
+            "data" => &mut self.data,
+            "checksum_valid" => &mut self.checksum_valid,
+            "checksum" => &mut self.checksum,
+            _ => {
+                return Err(format!(
+                    "unknown port '{role}', expected one of {}",
+                    Self::ROLES.join(", ")
+                ))
+            }
+        };
+        *port = name.to_owned();
+        Ok(())
+    }
+}
+
+impl Default for Ports {
+    fn default() -> Self {
+        Self {
+            clock: "clk".to_owned(),
+            reset: "rst".to_owned(),
+            length_valid: "length_valid".to_owned(),
+            length: "length".to_owned(),
+            data_valid: "data_valid".to_owned(),
+            data: "data".to_owned(),
+            checksum_valid: "checksum_valid".to_owned(),
+            checksum: "checksum".to_owned(),
+        }
+    }
+}
+
+/// A testbench for one stimulus file and the checksums it should produce.
+#[derive(Debug, Clone)]
+pub struct Testbench {
+    format: LineFormat,
+    expected: Vec<u64>,
+    /// Module name of the DUT
+    pub dut: String,
+    pub ports: Ports,
+    /// Path the testbench reads packed stimulus from
+    pub stimulus: String,
+    /// Clock cycles to wait for outstanding checksums after the last line
+    pub timeout: u32,
+    /// Width of the DUT's checksum port, 64 for Fletcher-64
+    pub checksum_width: u32,
+}
+
+impl Testbench {
+    pub fn new(format: LineFormat, expected: Vec<u64>) -> Self {
+        Self {
+            format,
+            expected,
+            dut: "adler32".to_owned(),
+            ports: Ports::default(),
+            stimulus: "stimulus.hex".to_owned(),
+            timeout: 10_000,
+            checksum_width: 32,
+        }
+    }
+
+    /// `(port, testbench signal)` pairs for the DUT instance
+    fn connections(&self) -> [(&str, &'static str); 8] {
+        let ports = &self.ports;
+        [
+            (&ports.clock, "clk"),
+            (&ports.reset, "rst"),
+            (&ports.length_valid, "length_valid"),
+            (&ports.length, "length"),
+            (&ports.data_valid, "data_valid"),
+            (&ports.data, "data"),
+            (&ports.checksum_valid, "checksum_valid"),
+            (&ports.checksum, "checksum"),
+        ]
+    }
+
+    /// Fields driven into the DUT, in port order
+    const FIELDS: [Field; 4] = [
+        Field::LengthValid,
+        Field::Length,
+        Field::DataValid,
+        Field::Data,
+    ];
+
+    /// Testbench signal `field` drives
+    fn signal(field: Field) -> &'static str {
+        match field {
+            Field::LengthValid => "length_valid",
+            Field::Length => "length",
+            Field::DataValid => "data_valid",
+            Field::Data => "data",
+        }
+    }
+
+    /// `[msb:lsb]` of `field` in a packed word
+    fn bits(&self, field: Field) -> String {
+        match self.format.field_bits(field) {
+            (msb, lsb) if msb == lsb => format!("[{msb}]"),
+            (msb, lsb) => format!("[{msb}:{lsb}]"),
+        }
+    }
+
+    /// Write a SystemVerilog testbench module named `<dut>_tb`.
+    pub fn write_sv(&self, mut out: impl Write) -> io::Result<()> {
+        let packets = self.expected.len();
+        let length_width = self.format.length_width();
+        let data_width = self.format.data_width();
+        let width = self.checksum_width;
+        let digits = width as usize / 4;
+
+        writeln!(
+            out,
+            "// Generated by adler32 gen-tb. Regenerate rather than edit."
+        )?;
+        writeln!(
+            out,
+            "// Reads stimulus written by `adler32 encode --target readmemh --format '{}'`.",
+            self.format
+        )?;
+        writeln!(out, "`timescale 1ns / 1ps")?;
+        writeln!(out)?;
+        writeln!(out, "module {}_tb;", self.dut)?;
+        writeln!(
+            out,
+            "  localparam int WORD_WIDTH = {};",
+            self.format.packed_width()
+        )?;
+        writeln!(out, "  localparam int PACKETS = {packets};")?;
+        writeln!(out, "  localparam int TIMEOUT = {};", self.timeout)?;
+        writeln!(out, "  localparam string STIMULUS = {:?};", self.stimulus)?;
+        write!(
+            out,
+            "  localparam logic [{}:0] EXPECTED [PACKETS > 0 ? PACKETS : 1] = '{{",
+            width - 1
+        )?;
+        if self.expected.is_empty() {
+            write!(out, "default: {width}'h0")?;
+        }
+        for (index, checksum) in self.expected.iter().enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            write!(out, "{separator}\n    {width}'h{checksum:0>digits$x}")?;
+        }
+        writeln!(out, "\n  }};")?;
+        writeln!(out)?;
+        writeln!(out, "  logic clk = 0;")?;
+        writeln!(out, "  logic rst = 1;")?;
+        writeln!(out, "  logic length_valid = 0;")?;
+        writeln!(out, "  logic [{}:0] length = 0;", length_width - 1)?;
+        writeln!(out, "  logic data_valid = 0;")?;
+        writeln!(out, "  logic [{}:0] data = 0;", data_width - 1)?;
+        writeln!(out, "  logic checksum_valid;")?;
+        writeln!(out, "  logic [{}:0] checksum;", width - 1)?;
+        writeln!(out)?;
+        writeln!(out, "  {} dut (", self.dut)?;
+        let connections = self.connections();
+        for (index, (port, signal)) in connections.iter().enumerate() {
+            let separator = if index + 1 == connections.len() {
+                ""
+            } else {
+                ","
+            };
+            writeln!(out, "    .{port}({signal}){separator}")?;
+        }
+        writeln!(out, "  );")?;
+        writeln!(out)?;
+        writeln!(out, "  always #5 clk = ~clk;")?;
+        writeln!(out)?;
+        writeln!(out, "  int received = 0;")?;
+        writeln!(out, "  int passed = 0;")?;
+        writeln!(out)?;
+        writeln!(out, "  always @(posedge clk) begin")?;
+        writeln!(out, "    if (!rst && checksum_valid) begin")?;
+        writeln!(out, "      if (received >= PACKETS)")?;
+        writeln!(
+            out,
+            "        $error(\"FAIL packet %0d: no expected checksum, got {width}'h%0{digits}h\", received, checksum);"
+        )?;
+        writeln!(out, "      else if (checksum === EXPECTED[received]) begin")?;
+        writeln!(
+            out,
+            "        $display(\"PASS packet %0d: {width}'h%0{digits}h\", received, checksum);"
+        )?;
+        writeln!(out, "        passed++;")?;
+        writeln!(out, "      end else")?;
+        writeln!(
+            out,
+            "        $error(\"FAIL packet %0d: expected {width}'h%0{digits}h, got {width}'h%0{digits}h\", received, EXPECTED[received], checksum);"
+        )?;
+        writeln!(out, "      received++;")?;
+        writeln!(out, "    end")?;
+        writeln!(out, "  end")?;
+        writeln!(out)?;
+        writeln!(out, "  initial begin")?;
+        writeln!(out, "    int fd;")?;
+        writeln!(out, "    logic [WORD_WIDTH-1:0] word;")?;
+        writeln!(out)?;
+        writeln!(out, "    fd = $fopen(STIMULUS, \"r\");")?;
+        writeln!(out, "    if (fd == 0)")?;
+        writeln!(out, "      $fatal(1, \"can't open %s\", STIMULUS);")?;
+        writeln!(out, "    repeat (4) @(posedge clk);")?;
+        writeln!(out, "    rst <= 0;")?;
+        writeln!(out, "    while ($fscanf(fd, \"%h\\n\", word) == 1) begin")?;
+        writeln!(out, "      @(posedge clk);")?;
+        for field in Self::FIELDS {
+            let signal = Self::signal(field);
+            writeln!(out, "      {signal} <= word{};", self.bits(field))?;
+        }
+        writeln!(out, "    end")?;
+        writeln!(out, "    $fclose(fd);")?;
+        writeln!(out, "    @(posedge clk);")?;
+        writeln!(out, "    length_valid <= 0;")?;
+        writeln!(out, "    data_valid <= 0;")?;
+        writeln!(out)?;
+        writeln!(out, "    fork")?;
+        writeln!(out, "      wait (received >= PACKETS);")?;
+        writeln!(out, "      repeat (TIMEOUT) @(posedge clk);")?;
+        writeln!(out, "    join_any")?;
+        writeln!(out, "    repeat (2) @(posedge clk);")?;
+        writeln!(
+            out,
+            "    $display(\"%0d of %0d packets passed\", passed, PACKETS);"
+        )?;
+        writeln!(out, "    if (passed == PACKETS && received == PACKETS)")?;
+        writeln!(out, "      $display(\"TEST PASSED\");")?;
+        writeln!(out, "    else")?;
+        writeln!(out, "      $fatal(1, \"TEST FAILED\");")?;
+        writeln!(out, "    $finish;")?;
+        writeln!(out, "  end")?;
+        writeln!(out, "endmodule")
+    }
+
+    /// Write a VHDL-2008 testbench entity named `<dut>_tb`.
+    pub fn write_vhdl(&self, mut out: impl Write) -> io::Result<()> {
+        let packets = self.expected.len();
+        let length_width = self.format.length_width();
+        let data_width = self.format.data_width();
+        // hread wants whole hex digits
+        let word_width = self.format.packed_width().div_ceil(4) * 4;
+        let width = self.checksum_width;
+        let digits = width as usize / 4;
+        let slice = |field| match self.format.field_bits(field) {
+            (msb, lsb) if msb == lsb => format!("({msb})"),
+            (msb, lsb) => format!("({msb} downto {lsb})"),
+        };
+
+        writeln!(
+            out,
+            "-- Generated by adler32 gen-tb. Regenerate rather than edit."
+        )?;
+        writeln!(
+            out,
+            "-- Reads stimulus written by `adler32 encode --target readmemh --format '{}'`.",
+            self.format
+        )?;
+        writeln!(out, "library ieee;")?;
+        writeln!(out, "use ieee.std_logic_1164.all;")?;
+        writeln!(out, "use std.textio.all;")?;
+        writeln!(out)?;
+        writeln!(out, "entity {}_tb is", self.dut)?;
+        writeln!(out, "end entity;")?;
+        writeln!(out)?;
+        writeln!(out, "architecture sim of {}_tb is", self.dut)?;
+        writeln!(out, "  constant WORD_WIDTH : natural := {word_width};")?;
+        writeln!(out, "  constant PACKETS : natural := {packets};")?;
+        writeln!(out, "  constant TIMEOUT : natural := {};", self.timeout)?;
+        writeln!(out, "  constant STIMULUS : string := {:?};", self.stimulus)?;
+        writeln!(
+            out,
+            "  type checksums is array (natural range <>) of std_logic_vector({} downto 0);",
+            width - 1
+        )?;
+        write!(
+            out,
+            "  constant EXPECTED : checksums(0 to {}) := (",
+            packets.max(1) - 1
+        )?;
+        if self.expected.is_empty() {
+            write!(out, "others => (others => '0')")?;
+        }
+        for (index, checksum) in self.expected.iter().enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            write!(
+                out,
+                "{separator}\n    {index} => x\"{checksum:0>digits$x}\""
+            )?;
+        }
+        writeln!(out, "\n  );")?;
+        writeln!(out)?;
+        writeln!(out, "  signal clk : std_logic := '0';")?;
+        writeln!(out, "  signal rst : std_logic := '1';")?;
+        writeln!(out, "  signal length_valid : std_logic := '0';")?;
+        writeln!(
+            out,
+            "  signal length : std_logic_vector({} downto 0) := (others => '0');",
+            length_width - 1
+        )?;
+        writeln!(out, "  signal data_valid : std_logic := '0';")?;
+        writeln!(
+            out,
+            "  signal data : std_logic_vector({} downto 0) := (others => '0');",
+            data_width - 1
+        )?;
+        writeln!(out, "  signal checksum_valid : std_logic;")?;
+        writeln!(
+            out,
+            "  signal checksum : std_logic_vector({} downto 0);",
+            width - 1
+        )?;
+        writeln!(out, "  signal received : natural := 0;")?;
+        writeln!(out, "  signal passed : natural := 0;")?;
+        writeln!(out, "begin")?;
+        writeln!(out, "  clk <= not clk after 5 ns;")?;
+        writeln!(out)?;
+        writeln!(out, "  dut : entity work.{}", self.dut)?;
+        writeln!(out, "    port map (")?;
+        let connections = self.connections();
+        for (index, (port, signal)) in connections.iter().enumerate() {
+            let separator = if index + 1 == connections.len() {
+                ""
+            } else {
+                ","
+            };
+            writeln!(out, "      {port} => {signal}{separator}")?;
+        }
+        writeln!(out, "    );")?;
+        writeln!(out)?;
+        writeln!(out, "  check : process (clk)")?;
+        writeln!(out, "  begin")?;
+        writeln!(
+            out,
+            "    if rising_edge(clk) and rst = '0' and checksum_valid = '1' then"
+        )?;
+        writeln!(out, "      if received >= PACKETS then")?;
+        writeln!(
+            out,
+            "        report \"FAIL packet \" & integer'image(received) & \": no expected checksum, got {width}'h\" & to_hstring(checksum) severity error;"
+        )?;
+        writeln!(out, "      elsif checksum = EXPECTED(received) then")?;
+        writeln!(
+            out,
+            "        report \"PASS packet \" & integer'image(received) & \": {width}'h\" & to_hstring(checksum);"
+        )?;
+        writeln!(out, "        passed <= passed + 1;")?;
+        writeln!(out, "      else")?;
+        writeln!(
+            out,
+            "        report \"FAIL packet \" & integer'image(received) & \": expected {width}'h\" & to_hstring(EXPECTED(received)) & \", got {width}'h\" & to_hstring(checksum) severity error;"
+        )?;
+        writeln!(out, "      end if;")?;
+        writeln!(out, "      received <= received + 1;")?;
+        writeln!(out, "    end if;")?;
+        writeln!(out, "  end process;")?;
+        writeln!(out)?;
+        writeln!(out, "  drive : process")?;
+        writeln!(out, "    file stimulus_file : text;")?;
+        writeln!(out, "    variable status : file_open_status;")?;
+        writeln!(out, "    variable text_line : line;")?;
+        writeln!(
+            out,
+            "    variable word : std_logic_vector(WORD_WIDTH - 1 downto 0);"
+        )?;
+        writeln!(out, "    variable good : boolean;")?;
+        writeln!(out, "  begin")?;
+        writeln!(
+            out,
+            "    file_open(status, stimulus_file, STIMULUS, read_mode);"
+        )?;
+        writeln!(
+            out,
+            "    assert status = open_ok report \"can't open \" & STIMULUS severity failure;"
+        )?;
+        writeln!(out, "    for i in 1 to 4 loop")?;
+        writeln!(out, "      wait until rising_edge(clk);")?;
+        writeln!(out, "    end loop;")?;
+        writeln!(out, "    rst <= '0';")?;
+        writeln!(out, "    while not endfile(stimulus_file) loop")?;
+        writeln!(out, "      readline(stimulus_file, text_line);")?;
+        writeln!(out, "      hread(text_line, word, good);")?;
+        writeln!(out, "      next when not good;")?;
+        writeln!(out, "      wait until rising_edge(clk);")?;
+        for field in Self::FIELDS {
+            let signal = Self::signal(field);
+            writeln!(out, "      {signal} <= word{};", slice(field))?;
+        }
+        writeln!(out, "    end loop;")?;
+        writeln!(out, "    file_close(stimulus_file);")?;
+        writeln!(out, "    wait until rising_edge(clk);")?;
+        writeln!(out, "    length_valid <= '0';")?;
+        writeln!(out, "    data_valid <= '0';")?;
+        writeln!(out)?;
+        writeln!(out, "    for i in 1 to TIMEOUT loop")?;
+        writeln!(out, "      exit when received >= PACKETS;")?;
+        writeln!(out, "      wait until rising_edge(clk);")?;
+        writeln!(out, "    end loop;")?;
+        writeln!(out, "    for i in 1 to 2 loop")?;
+        writeln!(out, "      wait until rising_edge(clk);")?;
+        writeln!(out, "    end loop;")?;
+        writeln!(
+            out,
+            "    report integer'image(passed) & \" of \" & integer'image(PACKETS) & \" packets passed\";"
+        )?;
+        writeln!(
+            out,
+            "    assert passed = PACKETS and received = PACKETS report \"TEST FAILED\" severity failure;"
+        )?;
+        writeln!(out, "    report \"TEST PASSED\";")?;
+        writeln!(out, "    std.env.finish;")?;
+        writeln!(out, "  end process;")?;
+        writeln!(out, "end architecture;")
+    }
+
+    /// A Python regex matching one encoded line, with a named group per field
+    fn line_pattern(&self) -> String {
+        let segments = self.format.segments();
+        let mut pattern = String::new();
+        for (index, segment) in segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    for c in literal.chars() {
+                        match c {
+                            '_' | ' ' => pattern.push_str("[_ ]"),
+                            c if c.is_ascii_alphanumeric() => pattern.push(c),
+                            c => {
+                                pattern.push('\\');
+                                pattern.push(c);
+                            }
+                        }
+                    }
+                }
+                &Segment::Field {
+                    field,
+                    radix,
+                    width,
+                } => {
+                    let digits = match radix {
+                        Radix::Binary => "[01]",
+                        Radix::Hex => "[0-9a-fA-F]",
+                        Radix::Decimal => "[0-9]",
+                    };
+                    // Same rule as LineFormat::parse: fixed width if another
+                    // field follows straight after
+                    let count = match segments.get(index + 1) {
+                        Some(Segment::Field { .. }) => format!("{{1,{}}}", radix.digits(width)),
+                        _ => "+".to_owned(),
+                    };
+                    let name = Self::signal(field);
+                    pattern.push_str(&format!("(?P<{name}>{digits}{count})"));
+                }
+            }
+        }
+        pattern
+    }
+
+    /// Write a cocotb test module that reads encoded lines and drives the
+    /// DUT one line per clock.
+    pub fn write_cocotb(&self, mut out: impl Write) -> io::Result<()> {
+        let ports = &self.ports;
+        let width = self.checksum_width;
+        let digits = width as usize / 4;
+
+        writeln!(
+            out,
+            "# Generated by adler32 gen-cocotb. Regenerate rather than edit."
+        )?;
+        writeln!(
+            out,
+            "# Reads stimulus written by `adler32 encode --format '{}'`.",
+            self.format
+        )?;
+        writeln!(
+            out,
+            "# Run with TOPLEVEL={} and MODULE set to this file.",
+            self.dut
+        )?;
+        writeln!(out)?;
+        writeln!(out, "import re")?;
+        writeln!(out)?;
+        writeln!(out, "import cocotb")?;
+        writeln!(out, "from cocotb.clock import Clock")?;
+        writeln!(
+            out,
+            "from cocotb.triggers import ClockCycles, ReadOnly, RisingEdge"
+        )?;
+        writeln!(out)?;
+        writeln!(out, "STIMULUS = {:?}", self.stimulus)?;
+        writeln!(out, "TIMEOUT = {}", self.timeout)?;
+        write!(out, "EXPECTED = [")?;
+        for checksum in &self.expected {
+            write!(out, "\n    0x{checksum:0>digits$x},")?;
+        }
+        if !self.expected.is_empty() {
+            writeln!(out)?;
+        }
+        writeln!(out, "]")?;
+        writeln!(out, "LINE = re.compile(r\"{}\")", self.line_pattern())?;
+        let bases = self
+            .format
+            .segments()
+            .iter()
+            .filter_map(|segment| match *segment {
+                Segment::Field { field, radix, .. } => {
+                    Some(format!("\"{}\": {}", Self::signal(field), radix.base()))
+                }
+                Segment::Literal(_) => None,
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "BASES = {{{}}}", bases.join(", "))?;
+        writeln!(out)?;
+        writeln!(out)?;
+        writeln!(out, "def read_stimulus(path):")?;
+        writeln!(
+            out,
+            "    \"\"\"Yield a dict of field values for each encoded line\"\"\""
+        )?;
+        writeln!(out, "    with open(path) as stimulus:")?;
+        writeln!(out, "        for number, text in enumerate(stimulus, 1):")?;
+        writeln!(out, "            if text.startswith(\"#\"):")?;
+        writeln!(out, "                continue")?;
+        writeln!(
+            out,
+            "            match = LINE.fullmatch(text.split(\"#\", 1)[0].rstrip())"
+        )?;
+        writeln!(out, "            if match is None:")?;
+        writeln!(
+            out,
+            "                raise ValueError(f\"{{path}}:{{number}}: can't parse {{text!r}}\")"
+        )?;
+        writeln!(
+            out,
+            "            yield {{name: int(match[name], base) for name, base in BASES.items()}}"
+        )?;
+        writeln!(out)?;
+        writeln!(out)?;
+        writeln!(out, "async def collect(dut, checksums):")?;
+        writeln!(
+            out,
+            "    \"\"\"Record every checksum the DUT produces\"\"\""
+        )?;
+        writeln!(out, "    while True:")?;
+        writeln!(out, "        await RisingEdge(dut.{})", ports.clock)?;
+        writeln!(out, "        await ReadOnly()")?;
+        writeln!(
+            out,
+            "        if dut.{}.value == 0 and dut.{}.value == 1:",
+            ports.reset, ports.checksum_valid
+        )?;
+        writeln!(
+            out,
+            "            checksums.append(int(dut.{}.value))",
+            ports.checksum
+        )?;
+        writeln!(out)?;
+        writeln!(out)?;
+        writeln!(out, "@cocotb.test()")?;
+        writeln!(out, "async def checksums_match(dut):")?;
+        writeln!(
+            out,
+            "    cocotb.start_soon(Clock(dut.{}, 10, \"ns\").start())",
+            ports.clock
+        )?;
+        writeln!(out, "    dut.{}.value = 1", ports.reset)?;
+        writeln!(out, "    dut.{}.value = 0", ports.length_valid)?;
+        writeln!(out, "    dut.{}.value = 0", ports.data_valid)?;
+        writeln!(out, "    await ClockCycles(dut.{}, 4)", ports.clock)?;
+        writeln!(out, "    dut.{}.value = 0", ports.reset)?;
+        writeln!(out)?;
+        writeln!(out, "    checksums = []")?;
+        writeln!(out, "    cocotb.start_soon(collect(dut, checksums))")?;
+        writeln!(out, "    for line in read_stimulus(STIMULUS):")?;
+        writeln!(out, "        await RisingEdge(dut.{})", ports.clock)?;
+        for (port, field) in [
+            &ports.length_valid,
+            &ports.length,
+            &ports.data_valid,
+            &ports.data,
+        ]
+        .iter()
+        .zip(Self::FIELDS)
+        {
+            writeln!(
+                out,
+                "        dut.{port}.value = line[\"{}\"]",
+                Self::signal(field)
+            )?;
+        }
+        writeln!(out, "    await RisingEdge(dut.{})", ports.clock)?;
+        writeln!(out, "    dut.{}.value = 0", ports.length_valid)?;
+        writeln!(out, "    dut.{}.value = 0", ports.data_valid)?;
+        writeln!(out)?;
+        writeln!(out, "    for _ in range(TIMEOUT):")?;
+        writeln!(out, "        if len(checksums) >= len(EXPECTED):")?;
+        writeln!(out, "            break")?;
+        writeln!(out, "        await RisingEdge(dut.{})", ports.clock)?;
+        writeln!(out, "    await ClockCycles(dut.{}, 2)", ports.clock)?;
+        writeln!(out)?;
+        writeln!(out, "    passed = 0")?;
+        writeln!(
+            out,
+            "    for packet in range(max(len(EXPECTED), len(checksums))):"
+        )?;
+        writeln!(
+            out,
+            "        want = EXPECTED[packet] if packet < len(EXPECTED) else None"
+        )?;
+        writeln!(
+            out,
+            "        got = checksums[packet] if packet < len(checksums) else None"
+        )?;
+        writeln!(out, "        if want is not None and want == got:")?;
+        writeln!(out, "            passed += 1")?;
+        writeln!(
+            out,
+            "            dut._log.info(f\"PASS packet {{packet}}: {width}'h{{got:0{digits}x}}\")"
+        )?;
+        writeln!(out, "        elif got is None:")?;
+        writeln!(
+            out,
+            "            dut._log.error(f\"FAIL packet {{packet}}: expected {width}'h{{want:0{digits}x}}, packet missing\")"
+        )?;
+        writeln!(out, "        elif want is None:")?;
+        writeln!(
+            out,
+            "            dut._log.error(f\"FAIL packet {{packet}}: no expected checksum, got {width}'h{{got:0{digits}x}}\")"
+        )?;
+        writeln!(out, "        else:")?;
+        writeln!(
+            out,
+            "            dut._log.error(f\"FAIL packet {{packet}}: expected {width}'h{{want:0{digits}x}}, got {width}'h{{got:0{digits}x}}\")"
+        )?;
+        writeln!(out, "    total = max(len(EXPECTED), len(checksums))")?;
+        writeln!(
+            out,
+            "    dut._log.info(f\"{{passed}} of {{total}} packets passed\")"
+        )?;
+        writeln!(out, "    assert passed == total, \"TEST FAILED\"")
+    }
+}
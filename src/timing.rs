@@ -0,0 +1,95 @@
+//! How long a run took and how fast it got through its input, for --timing.
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Bytes read from input files so far
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Packets read or encoded so far
+static PACKETS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimingFormat {
+    /// One line saying how long the run took and how fast it went
+    #[default]
+    Text,
+    /// One JSON object with the same numbers
+    Json,
+}
+
+/// Count `count` packets towards the summary.
+pub fn packets(count: usize) {
+    PACKETS.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// An input whose bytes count towards the summary as they're consumed
+pub struct Metered<R>(pub R);
+
+impl<R: BufRead> Read for Metered<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.0.read(buf)?;
+        BYTES.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Metered<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        BYTES.fetch_add(amount as u64, Ordering::Relaxed);
+        self.0.consume(amount);
+    }
+}
+
+#[derive(Serialize)]
+struct Summary {
+    seconds: f64,
+    bytes: u64,
+    bytes_per_second: f64,
+    packets: u64,
+    packets_per_second: f64,
+}
+
+/// Write how long it's been since `start` and the rates bytes and packets
+/// were got through at to `out`.
+pub fn report(format: TimingFormat, start: Instant, mut out: impl Write) -> io::Result<()> {
+    let seconds = start.elapsed().as_secs_f64();
+    let bytes = BYTES.load(Ordering::Relaxed);
+    let packets = PACKETS.load(Ordering::Relaxed);
+    let rate = |count| match seconds {
+        0.0 => 0.0,
+        _ => count as f64 / seconds,
+    };
+    let summary = Summary {
+        seconds,
+        bytes,
+        bytes_per_second: rate(bytes),
+        packets,
+        packets_per_second: rate(packets),
+    };
+    match format {
+        TimingFormat::Text => writeln!(
+            out,
+            "took {:.3}s: {} bytes at {:.0} bytes/s, {} packets at {:.0} packets/s",
+            summary.seconds,
+            summary.bytes,
+            summary.bytes_per_second,
+            summary.packets,
+            summary.packets_per_second,
+        ),
+        TimingFormat::Json => {
+            serde_json::to_writer(&mut out, &summary)?;
+            writeln!(out)
+        }
+    }
+}
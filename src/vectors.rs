@@ -0,0 +1,202 @@
+//! Golden vectors written out as source code, for test suites in other
+//! languages to build in.
+
+use std::{
+    ascii,
+    io::{self, Write},
+};
+
+use crate::Algorithm;
+
+/// Bytes per line of an array
+const ROW: usize = 12;
+
+/// `content` as the inside of a byte string literal, which reads the same in
+/// Rust and Python
+fn byte_string(content: &[u8]) -> String {
+    content
+        .iter()
+        .flat_map(|&byte| ascii::escape_default(byte))
+        .map(char::from)
+        .collect()
+}
+
+/// Packets and the checksums they should give.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vectors {
+    vectors: Vec<(Vec<u8>, u64)>,
+    algorithm: Algorithm,
+}
+
+impl Vectors {
+    /// Vectors for `(content, checksum)` pairs, in order
+    pub fn new(vectors: Vec<(Vec<u8>, u64)>) -> Self {
+        Self {
+            vectors,
+            algorithm: Algorithm::default(),
+        }
+    }
+
+    /// Say the checksums are `algorithm` checksums instead of Adler-32 ones.
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Write a C header with each packet as a `static const uint8_t` array
+    /// and an `adler32_vectors` table of their lengths and checksums.
+    ///
+    /// ```
+    /// use adler32::{adler32, Vectors};
+    ///
+    /// let vectors = Vectors::new(vec![(b"Wikipedia".to_vec(), adler32(b"Wikipedia").into())]);
+    /// let mut header = Vec::new();
+    /// vectors.write_c(&mut header).unwrap();
+    /// let header = String::from_utf8(header).unwrap();
+    /// assert!(header.contains("static const uint8_t adler32_packet_0[9] = {"));
+    /// assert!(header.contains("{adler32_packet_0, 9, 0x11e60398u},"));
+    /// ```
+    pub fn write_c(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "/* Generated by adler32 export. Regenerate rather than edit. */"
+        )?;
+        writeln!(out, "#ifndef ADLER32_VECTORS_H")?;
+        writeln!(out, "#define ADLER32_VECTORS_H")?;
+        writeln!(out)?;
+        writeln!(out, "#include <stddef.h>")?;
+        writeln!(out, "#include <stdint.h>")?;
+        for (index, (content, _)) in self.vectors.iter().enumerate() {
+            writeln!(out)?;
+            // C has no empty arrays, so empty packets get a byte that's
+            // never read
+            write!(
+                out,
+                "static const uint8_t adler32_packet_{index}[{}] = {{",
+                content.len().max(1)
+            )?;
+            if content.is_empty() {
+                write!(out, "0")?;
+            }
+            for row in content.chunks(ROW) {
+                let bytes = row.iter().map(|byte| format!("0x{byte:0>2x}"));
+                write!(out, "\n    {},", bytes.collect::<Vec<_>>().join(", "))?;
+            }
+            if !content.is_empty() {
+                writeln!(out)?;
+            }
+            writeln!(out, "}};")?;
+        }
+        writeln!(out)?;
+        writeln!(out, "struct adler32_vector {{")?;
+        writeln!(out, "    const uint8_t *data;")?;
+        writeln!(out, "    size_t length;")?;
+        let bits = self.algorithm.bits();
+        writeln!(out, "    uint{bits}_t checksum;")?;
+        writeln!(out, "}};")?;
+        writeln!(out)?;
+        writeln!(out, "#define ADLER32_VECTOR_COUNT {}", self.vectors.len())?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "static const struct adler32_vector adler32_vectors[{}] = {{",
+            self.vectors.len().max(1)
+        )?;
+        let digits = bits as usize / 4;
+        let suffix = match bits {
+            64 => "ull",
+            _ => "u",
+        };
+        for (index, (content, checksum)) in self.vectors.iter().enumerate() {
+            writeln!(
+                out,
+                "    {{adler32_packet_{index}, {}, 0x{checksum:0>digits$x}{suffix}}},",
+                content.len()
+            )?;
+        }
+        if self.vectors.is_empty() {
+            writeln!(out, "    {{NULL, 0, 0}},")?;
+        }
+        writeln!(out, "}};")?;
+        writeln!(out)?;
+        writeln!(out, "#endif /* ADLER32_VECTORS_H */")
+    }
+
+    /// Write a Rust file to `include!`, defining `VECTORS` as a slice of
+    /// `(content, checksum)` pairs.
+    ///
+    /// ```
+    /// use adler32::{adler32, Vectors};
+    ///
+    /// let vectors = Vectors::new(vec![(b"Wiki\n".to_vec(), adler32(b"Wiki\n").into())]);
+    /// let mut source = Vec::new();
+    /// vectors.write_rust(&mut source).unwrap();
+    /// let source = String::from_utf8(source).unwrap();
+    /// assert!(source.contains("pub const VECTORS: &[(&[u8], u32)] = &["));
+    /// assert!(source.contains("(b\"Wiki\\n\", 0x0579_019f),"));
+    /// ```
+    pub fn write_rust(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "// Generated by adler32 export. Regenerate rather than edit."
+        )?;
+        writeln!(out, "//")?;
+        writeln!(
+            out,
+            "// Each packet's content and its {} checksum.",
+            self.algorithm.title()
+        )?;
+        let bits = self.algorithm.bits();
+        writeln!(out, "pub const VECTORS: &[(&[u8], u{bits})] = &[")?;
+        for (content, checksum) in &self.vectors {
+            let groups = (0..bits / 16)
+                .rev()
+                .map(|group| format!("{:0>4x}", checksum >> (16 * group) & 0xffff));
+            writeln!(
+                out,
+                "    (b\"{}\", 0x{}),",
+                byte_string(content),
+                groups.collect::<Vec<_>>().join("_")
+            )?;
+        }
+        writeln!(out, "];")
+    }
+
+    /// Write a Python module defining `VECTORS` as a list of
+    /// `(content, checksum)` tuples.
+    ///
+    /// ```
+    /// use adler32::{adler32, Vectors};
+    ///
+    /// let vectors = Vectors::new(vec![(b"Wikipedia".to_vec(), adler32(b"Wikipedia").into())]);
+    /// let mut module = Vec::new();
+    /// vectors.write_python(&mut module).unwrap();
+    /// let module = String::from_utf8(module).unwrap();
+    /// assert!(module.contains("    (b\"Wikipedia\", 0x11e60398),"));
+    /// ```
+    pub fn write_python(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "# Generated by adler32 export. Regenerate rather than edit."
+        )?;
+        writeln!(
+            out,
+            "\"\"\"Each packet's content and its {} checksum.\"\"\"",
+            self.algorithm.title()
+        )?;
+        writeln!(out)?;
+        write!(out, "VECTORS = [")?;
+        let digits = self.algorithm.bits() as usize / 4;
+        for (content, checksum) in &self.vectors {
+            write!(
+                out,
+                "\n    (b\"{}\", 0x{checksum:0>digits$x}),",
+                byte_string(content)
+            )?;
+        }
+        if !self.vectors.is_empty() {
+            writeln!(out)?;
+        }
+        writeln!(out, "]")
+    }
+}
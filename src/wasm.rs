@@ -0,0 +1,103 @@
+//! JavaScript bindings, so stimulus can be written and inspected in a
+//! browser. Only built for wasm32, e.g. with `wasm-pack build --target web`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Adler32, DataStream, LineFormat};
+
+/// Incremental hasher, `new Hasher()` from JavaScript.
+#[wasm_bindgen(js_name = Hasher)]
+#[derive(Default)]
+pub struct JsHasher(Adler32);
+
+#[wasm_bindgen(js_class = Hasher)]
+impl JsHasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `data` to the running checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Checksum of everything added since the last reset.
+    pub fn finalize(&self) -> u32 {
+        self.0.finalize()
+    }
+
+    /// Start over as if freshly created.
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Adler-32 of a complete buffer.
+#[wasm_bindgen]
+pub fn checksum(data: &[u8]) -> u32 {
+    crate::adler32(data)
+}
+
+/// A packet read back from stimulus by [`decode`]
+#[wasm_bindgen]
+pub struct Packet {
+    pub checksum: u32,
+    content: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Packet {
+    #[wasm_bindgen(getter)]
+    pub fn content(&self) -> Vec<u8> {
+        self.content.clone()
+    }
+}
+
+/// Line format from a spec like `{lv}_{len:b32}_{dv}_{data:b8}`, or the
+/// default one
+fn line_format(spec: Option<String>) -> Result<LineFormat, JsError> {
+    match spec {
+        Some(spec) => spec
+            .parse()
+            .map_err(|e: crate::FormatSpecError| JsError::new(&e.to_string())),
+        None => Ok(LineFormat::default()),
+    }
+}
+
+/// Stimulus for one packet, a line per cycle, in `format` or the default
+/// format.
+#[wasm_bindgen]
+pub fn encode(data: &[u8], format: Option<String>) -> Result<String, JsError> {
+    let format = line_format(format)?;
+    let lines = format
+        .encode_packet(data)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(lines
+        .map(|line| format!("{}\n", format.display(&line)))
+        .collect())
+}
+
+/// Every packet in `stimulus`, written in `format` or the default format,
+/// with the checksum the hardware computes for it.
+#[wasm_bindgen]
+pub fn decode(stimulus: &str, format: Option<String>) -> Result<Vec<Packet>, JsError> {
+    let format = line_format(format)?;
+    let lines = format
+        .read_lines(stimulus.as_bytes())
+        .collect::<crate::Result<Vec<_>>>()
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let mut stream = DataStream::with_data_width(lines.into_iter(), format.data_width());
+    let packets = stream
+        .by_ref()
+        // Adler-32, so the checksum always fits
+        .map(|(checksum, content)| Packet {
+            checksum: checksum as u32,
+            content,
+        })
+        .collect();
+    if stream.truncated().is_some() {
+        return Err(JsError::new("stimulus ends in the middle of a packet"));
+    }
+    Ok(packets)
+}
@@ -0,0 +1,171 @@
+//! zlib streams (RFC 1950) holding data in stored, uncompressed deflate
+//! blocks (RFC 1951), for testing a DUT that checks the Adler-32 trailer,
+//! and reading such streams back. Compressed blocks can only be read with
+//! the `inflate` feature.
+
+use std::fmt::Display;
+
+use crate::adler32;
+
+/// Most bytes a stored block can hold
+const MAX_STORED: usize = 0xffff;
+
+/// `data` in a zlib stream of stored blocks, ending in its Adler-32.
+///
+/// ```
+/// use adler32::zlib_stored;
+///
+/// let stream = zlib_stored(b"Wikipedia");
+/// assert_eq!(stream[..2], [0x78, 0x01]);
+/// assert_eq!(stream[2..7], [0x01, 0x09, 0x00, 0xf6, 0xff]);
+/// assert_eq!(&stream[7..16], b"Wikipedia");
+/// assert_eq!(stream[16..], [0x11, 0xe6, 0x03, 0x98]);
+/// ```
+pub fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED).max(1);
+    let mut stream = Vec::with_capacity(2 + 5 * blocks + data.len() + 4);
+    // Deflate with a 32 KiB window, no dictionary, fastest compression
+    stream.extend([0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_STORED).peekable();
+    if chunks.peek().is_none() {
+        stream.extend([0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        stream.push(last as u8);
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(chunk);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+/// What a zlib stream holds, as read by [`read_zlib`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZlibStream {
+    /// The data, decompressed
+    pub payload: Vec<u8>,
+    /// Adler-32 of the payload according to the stream's trailer
+    pub trailer: u32,
+}
+
+impl ZlibStream {
+    /// Adler-32 of the payload, which the trailer should match
+    pub fn checksum(&self) -> u32 {
+        adler32(&self.payload)
+    }
+}
+
+/// Take apart a zlib stream, which has to make up the whole of `stream`.
+///
+/// ```
+/// use adler32::{read_zlib, zlib_stored};
+///
+/// let stream = read_zlib(&zlib_stored(b"Wikipedia")).unwrap();
+/// assert_eq!(stream.payload, b"Wikipedia");
+/// assert_eq!(stream.trailer, 0x11e6_0398);
+/// assert_eq!(stream.checksum(), stream.trailer);
+/// ```
+pub fn read_zlib(stream: &[u8]) -> Result<ZlibStream, ZlibError> {
+    let error = |message: String| Err(ZlibError(message));
+    let [cmf, flg, ref body @ ..] = *stream else {
+        return error("too short for a zlib header".to_owned());
+    };
+    if (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return error(format!("header {cmf:0>2x}{flg:0>2x} fails its check bits"));
+    }
+    if cmf & 0x0f != 8 {
+        return error(format!("compression method {} isn't deflate", cmf & 0x0f));
+    }
+    if flg & 0x20 != 0 {
+        return error("streams with a preset dictionary can't be read".to_owned());
+    }
+    let (payload, used) = inflate(body)?;
+    let Some((trailer, rest)) = body[used..].split_first_chunk() else {
+        return error("ends before the Adler-32 trailer".to_owned());
+    };
+    if !rest.is_empty() {
+        return error(format!("{} bytes follow the trailer", rest.len()));
+    }
+    Ok(ZlibStream {
+        payload,
+        trailer: u32::from_be_bytes(*trailer),
+    })
+}
+
+/// The deflate blocks at the start of `body` decompressed, and how many
+/// bytes they took up. Only stored blocks can be read.
+#[cfg(not(feature = "inflate"))]
+fn inflate(body: &[u8]) -> Result<(Vec<u8>, usize), ZlibError> {
+    let error = |message: &str| Err(ZlibError(message.to_owned()));
+    let mut payload = Vec::new();
+    let mut at = 0;
+    loop {
+        // A stored block ends on a byte boundary, so the next header starts
+        // on one
+        let Some(&[header, len_low, len_high, nlen_low, nlen_high]) = body.get(at..at + 5) else {
+            return error("ends in the middle of the deflate data");
+        };
+        match header >> 1 & 0b11 {
+            0b00 => {}
+            0b11 => return error("has a block of reserved type 3"),
+            _ => return error("has compressed blocks, which need the inflate feature to read"),
+        }
+        let len = u16::from_le_bytes([len_low, len_high]);
+        if u16::from_le_bytes([nlen_low, nlen_high]) != !len {
+            return error("has a stored block whose length fails its check");
+        }
+        let Some(data) = body.get(at + 5..at + 5 + len as usize) else {
+            return error("ends in the middle of the deflate data");
+        };
+        payload.extend_from_slice(data);
+        at += 5 + len as usize;
+        if header & 1 == 1 {
+            return Ok((payload, at));
+        }
+    }
+}
+
+/// The deflate blocks at the start of `body` decompressed, and how many
+/// bytes they took up.
+#[cfg(feature = "inflate")]
+fn inflate(body: &[u8]) -> Result<(Vec<u8>, usize), ZlibError> {
+    use miniz_oxide::{
+        inflate::stream::{inflate, InflateState},
+        DataFormat, MZError, MZFlush, MZStatus,
+    };
+
+    let mut state = InflateState::new_boxed(DataFormat::Raw);
+    let mut payload = Vec::new();
+    let mut buffer = vec![0; 1 << 16];
+    let mut input = body;
+    loop {
+        let result = inflate(&mut state, input, &mut buffer, MZFlush::None);
+        input = &input[result.bytes_consumed..];
+        payload.extend_from_slice(&buffer[..result.bytes_written]);
+        match result.status {
+            Ok(MZStatus::StreamEnd) => return Ok((payload, body.len() - input.len())),
+            Ok(_) => {}
+            Err(MZError::Buf) => {
+                return Err(ZlibError(
+                    "ends in the middle of the deflate data".to_owned(),
+                ))
+            }
+            Err(e) => return Err(ZlibError(format!("has bad deflate data ({e:?})"))),
+        }
+    }
+}
+
+/// A zlib stream that can't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZlibError(String);
+
+impl Display for ZlibError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid zlib stream: {}", self.0)
+    }
+}
+
+impl std::error::Error for ZlibError {}
@@ -0,0 +1,281 @@
+//! Adler-32 against a byte at a time reference, around the points where the
+//! sums are reduced and where a `u16` sum would overflow, and every
+//! implementation against the reference one, whole or through a rolling window.
+
+use adler32::{adler32, combine, Adler32, Implementation, RollingAdler32, MOD_ADLER};
+
+/// Most bytes summed between reductions
+const NMAX: usize = 5552;
+
+/// Adler-32 reduced after every byte, with room to spare
+fn reference(data: &[u8]) -> u32 {
+    reference_modulo(data, MOD_ADLER)
+}
+
+/// [`reference`] with the sums reduced modulo `modulus`
+fn reference_modulo(data: &[u8], modulus: u32) -> u32 {
+    let (mut a, mut b) = (1 % modulus as u64, 0u64);
+    for &byte in data {
+        a = (a + byte as u64) % modulus as u64;
+        b = (b + a) % modulus as u64;
+    }
+    (b << 16 | a) as u32
+}
+
+/// Repeatable bytes that aren't all the same
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn known_values() {
+    assert_eq!(adler32(b""), 1);
+    assert_eq!(adler32(b"a"), 0x0062_0062);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+}
+
+#[test]
+fn all_ones_around_reductions() {
+    let lengths = [
+        1,
+        15,
+        16,
+        17,
+        31,
+        32,
+        33,
+        63,
+        64,
+        65,
+        NMAX - 32,
+        NMAX - 1,
+        NMAX,
+        NMAX + 1,
+        2 * NMAX - 1,
+        2 * NMAX,
+        2 * NMAX + 1,
+        10 * NMAX + 7,
+    ];
+    for len in lengths {
+        let data = vec![0xff; len];
+        assert_eq!(adler32(&data), reference(&data), "{len} bytes of 0xff");
+    }
+}
+
+#[test]
+fn noise_of_every_short_length() {
+    let data = noise(600);
+    for len in 0..data.len() {
+        assert_eq!(
+            adler32(&data[..len]),
+            reference(&data[..len]),
+            "{len} bytes"
+        );
+    }
+}
+
+#[test]
+fn a_at_the_modulus() {
+    // 1 + 256 * 0xff + 0xef leaves `a` at 65520, the largest it can hold,
+    // so the next 0xff takes the unreduced sum past u16::MAX
+    let mut data = vec![0xff; 256];
+    data.push(0xef);
+    let mut hasher = Adler32::new();
+    hasher.update(&data);
+    assert_eq!(hasher.finalize() & 0xffff, MOD_ADLER - 1);
+
+    data.extend([0xff; 3]);
+    hasher.update(&[0xff; 3]);
+    assert_eq!(hasher.finalize(), reference(&data));
+}
+
+#[test]
+fn beat_at_a_time() {
+    let mut data = vec![0xff; 2 * NMAX + 100];
+    data.extend(noise(1000));
+    let mut hasher = Adler32::new();
+    for (index, &byte) in data.iter().enumerate() {
+        hasher.update(&[byte]);
+        let checksum = hasher.finalize();
+        assert!(checksum & 0xffff < MOD_ADLER && checksum >> 16 < MOD_ADLER);
+        if index % 97 == 0 {
+            assert_eq!(checksum, reference(&data[..=index]), "after {index} beats");
+        }
+    }
+    assert_eq!(hasher.finalize(), reference(&data));
+}
+
+#[test]
+fn split_anywhere() {
+    let data = noise(3 * NMAX);
+    let whole = reference(&data);
+    for split in [0, 1, 63, 64, 1000, NMAX - 1, NMAX, NMAX + 1, 2 * NMAX + 5] {
+        let mut hasher = Adler32::new();
+        hasher.update(&data[..split]);
+        hasher.update(&data[split..]);
+        assert_eq!(hasher.finalize(), whole, "split at {split}");
+    }
+}
+
+#[test]
+fn combine_anywhere() {
+    let mut data = noise(3 * NMAX);
+    data.extend(vec![0xff; 2 * MOD_ADLER as usize + 9]);
+    let whole = reference(&data);
+    let splits = [
+        0,
+        1,
+        64,
+        NMAX,
+        3 * NMAX,
+        3 * NMAX + MOD_ADLER as usize,
+        data.len(),
+    ];
+    for split in splits {
+        let (first, second) = data.split_at(split);
+        assert_eq!(
+            combine(adler32(first), adler32(second), second.len() as u64),
+            whole,
+            "split at {split}"
+        );
+    }
+}
+
+/// Checksum of `data` fed to `implementation` in `chunk` byte updates
+fn checksum_with(implementation: Implementation, data: &[u8], chunk: usize) -> u32 {
+    let mut hasher = Adler32::new();
+    for chunk in data.chunks(chunk) {
+        hasher.update_with(implementation, chunk);
+    }
+    hasher.finalize()
+}
+
+#[test]
+fn reference_implementation_is_the_reference() {
+    let data = noise(3 * NMAX + 17);
+    for len in [0, 1, 64, NMAX, data.len()] {
+        assert_eq!(
+            checksum_with(Implementation::Reference, &data[..len], len.max(1)),
+            reference(&data[..len]),
+            "{len} bytes"
+        );
+    }
+}
+
+#[test]
+fn implementations_agree() {
+    let mut data = noise(4 * NMAX);
+    data.extend(vec![0xff; 3 * NMAX + 33]);
+    data.extend(noise(1000));
+    let lengths = [
+        0,
+        1,
+        15,
+        31,
+        63,
+        64,
+        65,
+        257,
+        NMAX - 1,
+        NMAX,
+        NMAX + 1,
+        data.len(),
+    ];
+    for implementation in Implementation::ALL.into_iter().filter(|i| i.available()) {
+        for len in lengths {
+            for chunk in [1, 7, 64, 4096, NMAX + 3, len.max(1)] {
+                assert_eq!(
+                    checksum_with(implementation, &data[..len], chunk),
+                    checksum_with(Implementation::Reference, &data[..len], len.max(1)),
+                    "{} over {len} bytes in {chunk} byte updates",
+                    implementation.name()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn unavailable_implementations_fall_back() {
+    let data = noise(2 * NMAX);
+    for implementation in Implementation::ALL {
+        assert_eq!(
+            checksum_with(implementation, &data, data.len()),
+            reference(&data),
+            "{}",
+            implementation.name()
+        );
+    }
+}
+
+#[test]
+fn rolling_window_matches_the_window() {
+    let mut data = noise(3 * NMAX);
+    data.extend(vec![0xff; MOD_ADLER as usize + NMAX]);
+    for window in [1, 16, NMAX, MOD_ADLER as usize + 3] {
+        let mut rolling = RollingAdler32::new();
+        data[..window].iter().for_each(|&byte| rolling.push(byte));
+        assert_eq!(rolling.finalize(), reference(&data[..window]));
+        for start in 1..=data.len() - window {
+            rolling.roll(data[start - 1], data[start + window - 1], window as u64);
+            if start % 997 == 0 || start == data.len() - window {
+                let expected = reference(&data[start..start + window]);
+                assert_eq!(rolling.finalize(), expected, "{window} bytes from {start}");
+            }
+        }
+    }
+}
+
+#[test]
+fn pop_to_empty() {
+    let data = noise(100);
+    let mut rolling = RollingAdler32::new();
+    data.iter().for_each(|&byte| rolling.push(byte));
+    for (popped, &byte) in data.iter().enumerate() {
+        rolling.pop(byte, (data.len() - popped) as u64);
+        assert_eq!(rolling.finalize(), reference(&data[popped + 1..]));
+    }
+    assert_eq!(rolling, RollingAdler32::new());
+}
+
+#[test]
+fn resume_anywhere() {
+    let data = noise(3 * NMAX);
+    let whole = reference(&data);
+    for split in [0, 1, 64, NMAX, 2 * NMAX + 5, data.len()] {
+        let (first, second) = data.split_at(split);
+        let mut hasher = Adler32::resume(adler32(first));
+        hasher.update(second);
+        assert_eq!(hasher.finalize(), whole, "split at {split}");
+        hasher.reset();
+        assert_eq!(hasher.finalize(), reference(first), "reset after {split}");
+    }
+}
+
+#[test]
+fn other_moduli() {
+    let mut data = vec![0xff; 3 * NMAX + 7];
+    data.extend(noise(NMAX));
+    for modulus in [2, 255, 4096, MOD_ADLER, 65535, 1 << 16] {
+        for implementation in Implementation::ALL {
+            let mut hasher = Adler32::new().with_modulus(modulus);
+            for chunk in data.chunks(1000) {
+                hasher.update_with(implementation, chunk);
+            }
+            assert_eq!(
+                hasher.finalize(),
+                reference_modulo(&data, modulus),
+                "{} modulo {modulus}",
+                implementation.name()
+            );
+        }
+    }
+}
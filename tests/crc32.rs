@@ -0,0 +1,62 @@
+//! CRC-32 against a bit at a time reference, and through [`Checksum`] as
+//! the streams use it.
+
+use adler32::{crc32, Algorithm, Checksum, Crc32};
+
+/// CRC-32 shifted in a bit at a time
+fn reference(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = crc >> 1 ^ 0xedb8_8320 & mask;
+        }
+    }
+    !crc
+}
+
+#[test]
+fn known_values() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"a"), 0xe8b7_be43);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(
+        crc32(b"The quick brown fox jumps over the lazy dog"),
+        0x414f_a339
+    );
+}
+
+#[test]
+fn every_byte_value() {
+    let data: Vec<u8> = (0..=255).collect();
+    for len in 0..=data.len() {
+        assert_eq!(crc32(&data[..len]), reference(&data[..len]), "{len} bytes");
+    }
+}
+
+#[test]
+fn split_anywhere() {
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 37 + i / 7) as u8).collect();
+    let whole = reference(&data);
+    for split in [0, 1, 8, 255, 256, 999, 1000] {
+        let mut hasher = Crc32::new();
+        hasher.update(&data[..split]);
+        hasher.update(&data[split..]);
+        assert_eq!(hasher.finalize(), whole, "split at {split}");
+    }
+}
+
+#[test]
+fn checksum_follows_its_algorithm() {
+    for algorithm in Algorithm::ALL {
+        assert_eq!(Algorithm::from_name(algorithm.name()), Some(algorithm));
+        let mut checksum = Checksum::new(algorithm);
+        checksum.update(b"Wiki");
+        checksum.reset();
+        checksum.update(b"Wikipedia");
+        assert_eq!(checksum.algorithm(), algorithm);
+        assert_eq!(checksum.finalize(), algorithm.checksum(b"Wikipedia"));
+    }
+    assert_eq!(Algorithm::from_name("md5"), None);
+}
@@ -0,0 +1,81 @@
+//! Fletcher checksums against published values, and split across updates
+//! in the middle of words.
+
+use adler32::{
+    fletcher16, fletcher32, fletcher64, Algorithm, Checksum, Fletcher16, Fletcher32, Fletcher64,
+};
+
+#[test]
+fn known_values() {
+    assert_eq!(fletcher16(b""), 0);
+    assert_eq!(fletcher16(b"abcde"), 0xc8f0);
+    assert_eq!(fletcher16(b"abcdef"), 0x2057);
+    assert_eq!(fletcher16(b"abcdefgh"), 0x0627);
+    assert_eq!(fletcher32(b"abcde"), 0xf04f_c729);
+    assert_eq!(fletcher32(b"abcdef"), 0x5650_2d2a);
+    assert_eq!(fletcher32(b"abcdefgh"), 0xebe1_9591);
+    assert_eq!(fletcher64(b"abcde"), 0xc8c6_c527_6463_62c6);
+    assert_eq!(fletcher64(b"abcdef"), 0xc8c7_2b27_6463_c8c6);
+    assert_eq!(fletcher64(b"abcdefgh"), 0x312e_2b28_ccca_c8c6);
+}
+
+#[test]
+fn sums_wrap_at_the_modulus() {
+    // Every word is the modulus itself, so both sums stay at zero
+    assert_eq!(fletcher16(&[0xff; 1000]), 0);
+    assert_eq!(fletcher32(&[0xff; 1000]), 0);
+    assert_eq!(fletcher64(&[0xff; 1000]), 0);
+    assert_eq!(fletcher16(&[0xfe; 3]), 0xf9fc);
+}
+
+#[test]
+fn split_anywhere() {
+    let data: Vec<u8> = (0..999u32).map(|i| (i * 37 + i / 7) as u8).collect();
+    for split in [0, 1, 2, 3, 5, 500, 998, 999] {
+        let (first, second) = data.split_at(split);
+
+        let mut hasher = Fletcher16::new();
+        hasher.update(first);
+        hasher.update(second);
+        assert_eq!(hasher.finalize(), fletcher16(&data), "split at {split}");
+
+        let mut hasher = Fletcher32::new();
+        hasher.update(first);
+        hasher.update(second);
+        assert_eq!(hasher.finalize(), fletcher32(&data), "split at {split}");
+
+        let mut hasher = Fletcher64::new();
+        hasher.update(first);
+        hasher.update(second);
+        assert_eq!(hasher.finalize(), fletcher64(&data), "split at {split}");
+    }
+}
+
+#[test]
+fn byte_at_a_time() {
+    let data = b"The quick brown fox jumps over the lazy dog";
+    let mut hasher = Fletcher64::new();
+    for (index, &byte) in data.iter().enumerate() {
+        hasher.update(&[byte]);
+        assert_eq!(hasher.finalize(), fletcher64(&data[..=index]), "{index}");
+    }
+}
+
+#[test]
+fn fletcher64_keeps_all_64_bits() {
+    let mut checksum = Checksum::new(Algorithm::Fletcher64);
+    checksum.update(b"abcde");
+    assert_eq!(checksum.finalize(), fletcher64(b"abcde"));
+    assert_eq!(
+        Algorithm::Fletcher64
+            .literal(checksum.finalize())
+            .to_string(),
+        "64'hc8c6c527646362c6"
+    );
+    assert_eq!(
+        Algorithm::Fletcher16
+            .literal(fletcher16(b"abcde").into())
+            .to_string(),
+        "32'h0000c8f0"
+    );
+}
@@ -0,0 +1,235 @@
+//! Lint and repair of malformed stimulus: beats out of place, fields of the
+//! wrong width and bytes that aren't text.
+
+#![cfg(feature = "std")]
+
+use adler32::{
+    lint, repair, Change, Field, Finding, Fix, LineFormat, Lint, ParseLineError, Problem, Repair,
+};
+
+/// A line in the default format, `{lv}_{len:b32}_{dv}_{data:b8}`
+fn line(length_valid: bool, length: u64, data_valid: bool, data: u8) -> String {
+    format!(
+        "{}_{length:0>32b}_{}_{data:0>8b}",
+        length_valid as u8, data_valid as u8
+    )
+}
+
+fn lint_text(stimulus: &[u8]) -> Lint {
+    lint(&LineFormat::default(), stimulus, &["#"]).unwrap()
+}
+
+fn repair_text(stimulus: &[u8]) -> Repair {
+    repair(&LineFormat::default(), stimulus, &["#"]).unwrap()
+}
+
+fn joined(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[test]
+fn well_formed_stimulus_has_no_findings() {
+    let stimulus = joined(&[
+        "# two packets".to_owned(),
+        line(true, 2, false, 0),
+        line(false, 0, true, b'h'),
+        line(false, 0, true, b'i'),
+        line(true, 1, true, b'!'),
+    ]);
+    let report = lint_text(stimulus.as_bytes());
+    assert_eq!(report.findings, []);
+    assert_eq!(report.packets, 2);
+    assert_eq!(report.lines, 4);
+}
+
+#[test]
+fn length_beat_mid_packet() {
+    let stimulus = joined(&[
+        line(true, 3, false, 0),
+        line(false, 0, true, b'a'),
+        line(true, 1, true, b'b'),
+    ]);
+    let report = lint_text(stimulus.as_bytes());
+    assert_eq!(report.packets, 2);
+    assert_eq!(
+        report.findings,
+        [Finding {
+            line: 3,
+            problem: Problem::LengthMidPacket { remaining: 2 },
+        }]
+    );
+
+    let repaired = repair_text(stimulus.as_bytes());
+    assert_eq!(
+        repaired.changes,
+        [Change {
+            line: 1,
+            fix: Fix::Length { from: 3, to: 1 },
+        }]
+    );
+    assert_eq!(lint_text(joined(&repaired.lines).as_bytes()).findings, []);
+}
+
+#[test]
+fn data_beat_outside_a_packet() {
+    let stimulus = joined(&[
+        line(false, 0, true, b'x'),
+        line(true, 1, false, 0),
+        line(false, 0, true, b'a'),
+        line(false, 0, true, b'y'),
+    ]);
+    let report = lint_text(stimulus.as_bytes());
+    let problems: Vec<_> = report
+        .findings
+        .iter()
+        .map(|finding| (finding.line, &finding.problem))
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            (1, &Problem::DataOutsidePacket),
+            (4, &Problem::DataOutsidePacket)
+        ]
+    );
+
+    // The beat before any packet goes, the one after is counted in
+    let repaired = repair_text(stimulus.as_bytes());
+    assert_eq!(
+        repaired.changes,
+        [
+            Change {
+                line: 1,
+                fix: Fix::Dropped,
+            },
+            Change {
+                line: 2,
+                fix: Fix::Length { from: 1, to: 2 },
+            },
+        ]
+    );
+    assert_eq!(repaired.lines.len(), 3);
+    assert_eq!(lint_text(joined(&repaired.lines).as_bytes()).findings, []);
+}
+
+#[test]
+fn fields_of_the_wrong_width() {
+    let canonical = line(true, 1, true, b'a');
+    let short = "1_1_1_1100001";
+    let wide = format!("1_{:0>32b}_1_101100001", 1);
+    let stimulus = format!("{short}\n{wide}\n");
+
+    let report = lint_text(stimulus.as_bytes());
+    assert_eq!(
+        report.findings[0],
+        Finding {
+            line: 1,
+            problem: Problem::NotCanonical {
+                expected: canonical.clone(),
+            },
+        }
+    );
+    assert!(
+        matches!(
+            report.findings[1].problem,
+            Problem::Unparsable(ParseLineError::TooWide {
+                field: Field::Data,
+                width: 8,
+                ..
+            })
+        ),
+        "{:?}",
+        report.findings[1]
+    );
+
+    // Short fields are padded, a field too wide to read is dropped
+    let repaired = repair_text(stimulus.as_bytes());
+    assert_eq!(repaired.lines, [canonical]);
+    assert_eq!(
+        repaired.changes,
+        [
+            Change {
+                line: 1,
+                fix: Fix::Rewritten,
+            },
+            Change {
+                line: 2,
+                fix: Fix::Dropped,
+            },
+        ]
+    );
+}
+
+#[test]
+fn bytes_that_are_not_utf8() {
+    let mut stimulus = format!("{}\n", line(true, 2, false, 0)).into_bytes();
+    stimulus.extend(line(false, 0, true, b'a').bytes());
+    stimulus.extend(b"\xff\n");
+    stimulus.extend(format!("{}\n", line(false, 0, true, b'b')).bytes());
+
+    // Only the line that isn't text is skipped, so its byte is missing
+    let report = lint_text(&stimulus);
+    assert_eq!(
+        report.findings,
+        [
+            Finding {
+                line: 2,
+                problem: Problem::NotUtf8,
+            },
+            Finding {
+                line: 3,
+                problem: Problem::Truncated { remaining: 1 },
+            },
+        ]
+    );
+    assert_eq!(report.packets, 1);
+
+    let repaired = repair_text(&stimulus);
+    assert_eq!(
+        repaired.changes,
+        [Change {
+            line: 2,
+            fix: Fix::Stripped("\u{fffd}".to_owned()),
+        }]
+    );
+    assert_eq!(lint_text(joined(&repaired.lines).as_bytes()).findings, []);
+}
+
+#[test]
+fn truncated_and_stale_trailer() {
+    let stimulus = joined(&[
+        line(true, 1, true, b'a'),
+        "# adler32-payload: 32'h00000001".to_owned(),
+        line(true, 2, true, b'b'),
+    ]);
+    let report = lint_text(stimulus.as_bytes());
+    assert!(matches!(
+        report.findings[0],
+        Finding {
+            line: 2,
+            problem: Problem::Trailer { expected: 1, .. },
+        }
+    ));
+    assert_eq!(
+        report.findings[1],
+        Finding {
+            line: 3,
+            problem: Problem::Truncated { remaining: 1 },
+        }
+    );
+
+    let repaired = repair_text(stimulus.as_bytes());
+    assert!(matches!(
+        repaired.changes[..],
+        [
+            Change {
+                line: 2,
+                fix: Fix::Trailer { from: 1, .. },
+            },
+            Change {
+                line: 3,
+                fix: Fix::Length { from: 2, to: 1 },
+            },
+        ]
+    ));
+    assert_eq!(lint_text(joined(&repaired.lines).as_bytes()).findings, []);
+}
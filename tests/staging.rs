@@ -0,0 +1,132 @@
+//! How the command line writes output files that already exist: refusing,
+//! appending, overwriting and backing up, and leaving them untouched when a
+//! command fails part way through.
+
+#![cfg(feature = "std")]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// A directory of its own for test `name`, emptied
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("adler32-staging-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the command line in `dir`
+fn adler32(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_adler32"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+/// `hi` encoded, written to `packets.txt` in `dir` along with the packet
+fn encoded(dir: &Path) -> String {
+    fs::write(dir.join("packets.txt"), "hi\n").unwrap();
+    let output = adler32(dir, &["encode", "packets.txt", "-"]);
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Names of the files in `dir`, sorted
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn refuses_to_overwrite_by_default() {
+    let dir = scratch("refuse");
+    encoded(&dir);
+    fs::write(dir.join("dest.txt"), "old\n").unwrap();
+
+    let output = adler32(&dir, &["encode", "packets.txt", "dest.txt"]);
+    assert_eq!(output.status.code(), Some(6), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert_eq!(fs::read_to_string(dir.join("dest.txt")).unwrap(), "old\n");
+    assert_eq!(files(&dir), ["dest.txt", "packets.txt"]);
+
+    // Nothing worth keeping in an empty file
+    fs::write(dir.join("dest.txt"), "").unwrap();
+    let output = adler32(&dir, &["encode", "packets.txt", "dest.txt"]);
+    assert!(output.status.success(), "{output:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn force_overwrites_and_append_adds() {
+    let dir = scratch("force");
+    let encoded = encoded(&dir);
+    fs::write(dir.join("dest.txt"), "old\n").unwrap();
+
+    let output = adler32(&dir, &["--append", "encode", "packets.txt", "dest.txt"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(dir.join("dest.txt")).unwrap(),
+        format!("old\n{encoded}")
+    );
+
+    let output = adler32(&dir, &["--force", "encode", "packets.txt", "dest.txt"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(dir.join("dest.txt")).unwrap(), encoded);
+    // The temporary file was renamed into place
+    assert_eq!(files(&dir), ["dest.txt", "packets.txt"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backup_keeps_what_was_there() {
+    let dir = scratch("backup");
+    let encoded = encoded(&dir);
+    fs::write(dir.join("dest.txt"), "old\n").unwrap();
+
+    let output = adler32(&dir, &["--backup", "encode", "packets.txt", "dest.txt"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(dir.join("dest.txt")).unwrap(), encoded);
+    assert_eq!(
+        fs::read_to_string(dir.join("dest.txt.bak")).unwrap(),
+        "old\n"
+    );
+
+    let output = adler32(
+        &dir,
+        &["--backup=.orig", "encode", "packets.txt", "dest.txt"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(dir.join("dest.txt.orig")).unwrap(),
+        encoded
+    );
+    assert_eq!(
+        files(&dir),
+        ["dest.txt", "dest.txt.bak", "dest.txt.orig", "packets.txt"]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_command_leaves_files_as_they_were() {
+    let dir = scratch("discard");
+    // A whole packet, so something is written before the bad line
+    let stimulus = encoded(&dir) + "garbage\n";
+    fs::write(dir.join("stimulus.txt"), stimulus).unwrap();
+    fs::write(dir.join("dest.txt"), "old\n").unwrap();
+
+    let output = adler32(&dir, &["--backup", "decode", "stimulus.txt", "dest.txt"]);
+    assert_eq!(output.status.code(), Some(4), "{output:?}");
+    assert_eq!(fs::read_to_string(dir.join("dest.txt")).unwrap(), "old\n");
+    // No temporary file left behind and no backup of a file never replaced
+    assert_eq!(files(&dir), ["dest.txt", "packets.txt", "stimulus.txt"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+//! zlib streams of stored blocks, read back around the most a block holds
+//! and with each part of the stream damaged.
+
+#![cfg(feature = "std")]
+
+use adler32::{adler32, read_zlib, zlib_stored};
+
+/// Most bytes a stored block holds
+const MAX_STORED: usize = 0xffff;
+
+#[test]
+fn round_trip_around_block_size() {
+    for len in [
+        0,
+        1,
+        MAX_STORED - 1,
+        MAX_STORED,
+        MAX_STORED + 1,
+        3 * MAX_STORED + 7,
+    ] {
+        let data = (0..len).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        let stream = zlib_stored(&data);
+        let blocks = len.div_ceil(MAX_STORED).max(1);
+        assert_eq!(stream.len(), 2 + 5 * blocks + len + 4, "{len} bytes");
+        let read = read_zlib(&stream).unwrap();
+        assert_eq!(read.payload, data, "{len} bytes");
+        assert_eq!(read.trailer, adler32(&data), "{len} bytes");
+    }
+}
+
+#[test]
+fn trailer_mismatch_is_read_not_rejected() {
+    let mut stream = zlib_stored(b"Wikipedia");
+    *stream.last_mut().unwrap() ^= 1;
+    let read = read_zlib(&stream).unwrap();
+    assert_eq!(read.checksum(), 0x11e6_0398);
+    assert_eq!(read.trailer, 0x11e6_0399);
+}
+
+#[test]
+fn damaged_streams_are_rejected() {
+    let stream = zlib_stored(b"Wikipedia");
+    let damaged = |at: usize, byte: u8| {
+        let mut stream = stream.clone();
+        stream[at] = byte;
+        stream
+    };
+    assert!(read_zlib(&[0x78]).is_err());
+    // Check bits
+    assert!(read_zlib(&damaged(1, 0x02)).is_err());
+    // Compression method 7
+    assert!(read_zlib(&damaged(0, 0x77)).is_err());
+    // Block type 3
+    assert!(read_zlib(&damaged(2, 0x07)).is_err());
+    // NLEN isn't the complement of LEN
+    assert!(read_zlib(&damaged(5, 0x00)).is_err());
+    assert!(read_zlib(&stream[..stream.len() - 1]).is_err());
+    assert!(read_zlib(&[&stream[..], &[0]].concat()).is_err());
+}